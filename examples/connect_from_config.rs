@@ -1,5 +1,7 @@
+#![allow(deprecated)]
+
 use anyhow::Result;
-use libsql_client::{args, Client, ResultSet, Statement};
+use libsql_client::{args, Client, DisplayValue, ResultSet, Statement};
 use rand::prelude::SliceRandom;
 
 fn result_to_string(query_result: ResultSet) -> Result<String> {
@@ -11,7 +13,7 @@ fn result_to_string(query_result: ResultSet) -> Result<String> {
     ret += "\n| -------------------------------------------------------- |\n";
     for row in rows {
         for cell in row.values {
-            ret += &format!("| {:16} |", cell);
+            ret += &format!("| {:16} |", DisplayValue(&cell));
         }
         ret += "\n";
     }
@@ -64,12 +66,9 @@ async fn bump_counter(db: Client) -> Result<String> {
 
 #[tokio::main]
 async fn main() {
-    let db = Client::from_config(libsql_client::Config {
-        url: url::Url::parse("libsql://localhost:8080").unwrap(),
-        auth_token: None,
-    })
-    .await
-    .unwrap();
+    let db = Client::from_config(libsql_client::Config::new("libsql://localhost:8080").unwrap())
+        .await
+        .unwrap();
     let response = bump_counter(db)
         .await
         .unwrap_or_else(|e| format!("Error: {e}"));
@@ -1,5 +1,7 @@
+#![allow(deprecated)]
+
 use anyhow::Result;
-use libsql_client::{args, Client, ResultSet, Statement};
+use libsql_client::{args, AuthToken, Client, DisplayValue, ResultSet, Statement};
 use rand::prelude::SliceRandom;
 
 fn result_to_string(query_result: ResultSet) -> Result<String> {
@@ -11,7 +13,7 @@ fn result_to_string(query_result: ResultSet) -> Result<String> {
     ret += "\n| -------------------------------------------------------- |\n";
     for row in rows {
         for cell in row.values {
-            ret += &format!("| {:16} |", cell);
+            ret += &format!("| {:16} |", DisplayValue(&cell));
         }
         ret += "\n";
     }
@@ -78,6 +80,8 @@ async fn main() {
     println!(
         "Client parameters: url={:?} token={:?}\n{response}",
         std::env::var("LIBSQL_CLIENT_URL"),
-        std::env::var("LIBSQL_CLIENT_TOKEN"),
+        std::env::var("LIBSQL_CLIENT_TOKEN")
+            .ok()
+            .map(AuthToken::from),
     );
 }
@@ -0,0 +1,34 @@
+//! A library for the browser, querying the database with the `fetch` API.
+//!
+//! Build it with:
+//! ```sh
+//! cargo build --example fetch --target wasm32-unknown-unknown --no-default-features -F fetch_backend
+//! wasm-bindgen --target web --out-dir pkg target/wasm32-unknown-unknown/debug/examples/fetch.wasm
+//! ```
+//! and call `count_rows(url, token, table)` from JavaScript after importing `pkg/fetch.js`.
+#![allow(deprecated)]
+
+use libsql_client::{Backend, Client, Config};
+use wasm_bindgen::prelude::*;
+
+#[wasm_bindgen]
+pub async fn count_rows(url: String, token: String, table: String) -> Result<f64, JsError> {
+    let config = Config::new(url.as_str())
+        .map_err(|e| JsError::new(&e.to_string()))?
+        .with_auth_token(token)
+        .with_backend(Backend::Fetch);
+    let db = Client::from_config(config)
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let rs = db
+        .execute(format!(
+            "SELECT count(*) FROM \"{}\"",
+            table.replace('"', "\"\"")
+        ))
+        .await
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    let count: i64 = rs.rows[0]
+        .try_get(0)
+        .map_err(|e| JsError::new(&e.to_string()))?;
+    Ok(count as f64)
+}
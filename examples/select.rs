@@ -1,3 +1,5 @@
+#![allow(deprecated)]
+
 use anyhow::Result;
 use libsql_client::{args, de, Client, Statement};
 use rand::prelude::SliceRandom;
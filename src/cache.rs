@@ -0,0 +1,164 @@
+//! Client-side cache for results of read queries, see [`Config::with_query_cache()`](crate::Config::with_query_cache).
+
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+
+use crate::utils::Stopwatch;
+use crate::{ResultSet, Statement};
+
+/// Memoizes [ResultSet]s of `SELECT` statements, keyed by their SQL text and arguments.
+///
+/// Entries expire after `ttl`. Any statement that does not start with `SELECT`
+/// is treated as a potential write and clears the whole cache once it's executed,
+/// as do batches and the end of transactions.
+/// This heuristic only covers writes issued through the same client - changes made by other
+/// clients, or by triggers fired from this one, are only observed once the entries expire.
+#[derive(Debug)]
+pub(crate) struct QueryCache {
+    capacity: usize,
+    ttl: Duration,
+    next_seq: AtomicU64,
+    entries: Mutex<HashMap<String, Entry>>,
+}
+
+#[derive(Debug)]
+struct Entry {
+    inserted: Stopwatch,
+    // Insertion order, used for evicting the oldest entry
+    seq: u64,
+    rs: ResultSet,
+}
+
+impl QueryCache {
+    pub(crate) fn new(capacity: usize, ttl: Duration) -> Self {
+        Self {
+            capacity,
+            ttl,
+            next_seq: AtomicU64::new(0),
+            entries: Mutex::new(HashMap::new()),
+        }
+    }
+
+    /// Returns true if results of this statement can be cached
+    pub(crate) fn is_cacheable(stmt: &Statement) -> bool {
+        stmt.sql
+            .trim_start()
+            .get(..6)
+            .map(|prefix| prefix.eq_ignore_ascii_case("select"))
+            .unwrap_or(false)
+    }
+
+    /// Cache key for the statement, made of its SQL and arguments serialized with their types,
+    /// so that e.g. `1` and `'1'` bound to the same SQL don't share an entry
+    pub(crate) fn key(stmt: &Statement) -> String {
+        serde_json::json!({
+            "sql": stmt.sql,
+            "args": stmt.args,
+            "named_args": stmt.named_args,
+        })
+        .to_string()
+    }
+
+    pub(crate) fn get(&self, key: &str) -> Option<ResultSet> {
+        let mut entries = self.entries.lock().unwrap();
+        match entries.get(key) {
+            Some(entry) if entry.inserted.elapsed() < self.ttl => Some(entry.rs.clone()),
+            Some(_) => {
+                entries.remove(key);
+                None
+            }
+            None => None,
+        }
+    }
+
+    pub(crate) fn insert(&self, key: String, rs: ResultSet) {
+        if self.capacity == 0 {
+            return;
+        }
+        let mut entries = self.entries.lock().unwrap();
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            entries.retain(|_, entry| entry.inserted.elapsed() < self.ttl);
+        }
+        if entries.len() >= self.capacity && !entries.contains_key(&key) {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.seq)
+                .map(|(k, _)| k.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        let seq = self.next_seq.fetch_add(1, Ordering::Relaxed);
+        entries.insert(
+            key,
+            Entry {
+                inserted: Stopwatch::start(),
+                seq,
+                rs,
+            },
+        );
+    }
+
+    pub(crate) fn invalidate(&self) {
+        tracing::trace!("Invalidating query cache");
+        self.entries.lock().unwrap().clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Value;
+
+    fn rs(rows_affected: u64) -> ResultSet {
+        ResultSet {
+            columns: vec![],
+            rows: vec![],
+            rows_affected,
+            last_insert_rowid: None,
+            column_origins: vec![],
+            stats: None,
+            is_write: false,
+        }
+    }
+
+    #[test]
+    fn test_is_cacheable() {
+        assert!(QueryCache::is_cacheable(&Statement::new("  select 1")));
+        assert!(QueryCache::is_cacheable(&Statement::new("SELECT * FROM t")));
+        assert!(!QueryCache::is_cacheable(&Statement::new(
+            "INSERT INTO t VALUES (1)"
+        )));
+        assert!(!QueryCache::is_cacheable(&Statement::new("sel")));
+    }
+
+    #[test]
+    fn test_key_distinguishes_types() {
+        let key = |arg: Value| QueryCache::key(&Statement::with_args("SELECT ?", &[arg]));
+        assert_ne!(key(Value::from(1)), key(Value::from("1")));
+        assert_ne!(key(Value::from(1)), key(Value::from(1.0)));
+        assert_eq!(key(Value::from(1)), key(Value::from(1)));
+    }
+
+    #[test]
+    fn test_capacity_and_invalidation() {
+        let cache = QueryCache::new(2, Duration::from_secs(60));
+        cache.insert("a".into(), rs(1));
+        cache.insert("b".into(), rs(2));
+        cache.insert("c".into(), rs(3));
+        assert!(cache.get("a").is_none());
+        assert_eq!(cache.get("b").unwrap().rows_affected, 2);
+        assert_eq!(cache.get("c").unwrap().rows_affected, 3);
+        cache.invalidate();
+        assert!(cache.get("b").is_none());
+    }
+
+    #[test]
+    fn test_ttl() {
+        let cache = QueryCache::new(2, Duration::ZERO);
+        cache.insert("a".into(), rs(1));
+        assert!(cache.get("a").is_none());
+    }
+}
@@ -1,21 +1,31 @@
 //! [Client] is the main structure to interact with the database.
 use anyhow::Result;
 
-use crate::{proto, BatchResult, ResultSet, Statement, SyncTransaction, Transaction};
+use crate::cache::QueryCache;
+use crate::{
+    proto, utils, BatchResult, OwnedTransaction, ResultSet, Statement, SyncTransaction,
+    Transaction, TxBehavior,
+};
 
 static TRANSACTION_IDS: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(1);
 
+// Used by with_transaction() unless overridden with Config::with_transaction_retries()
+const DEFAULT_TRANSACTION_RETRIES: usize = 5;
+const DEFAULT_TRANSACTION_BACKOFF: std::time::Duration = std::time::Duration::from_millis(10);
+
 /// A generic client struct, wrapping possible backends.
 /// It's a convenience struct which allows implementing connect()
 /// with backends being passed as env parameters.
 #[derive(Debug)]
+#[allow(clippy::large_enum_variant)]
 pub enum Client {
     #[cfg(feature = "local_backend")]
     Local(crate::local::Client),
     #[cfg(any(
         feature = "reqwest_backend",
         feature = "workers_backend",
-        feature = "spin_backend"
+        feature = "spin_backend",
+        feature = "fetch_backend"
     ))]
     Http(crate::http::Client),
     #[cfg(feature = "hrana_backend")]
@@ -55,23 +65,43 @@ impl Client {
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
     ) -> Result<BatchResult> {
-        match self {
+        self.raw_batch_with_request_id(stmts, None).await
+    }
+
+    // Only the HTTP backend sends request ids, see BatchOpts::with_request_id()
+    async fn raw_batch_with_request_id(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
+        #[allow(unused)] request_id: Option<&str>,
+    ) -> Result<BatchResult> {
+        let result = match self {
             #[cfg(feature = "local_backend")]
             Self::Local(l) => l.raw_batch(stmts),
             #[cfg(any(
                 feature = "reqwest_backend",
                 feature = "workers_backend",
-                feature = "spin_backend"
+                feature = "spin_backend",
+                feature = "fetch_backend"
             ))]
-            Self::Http(r) => r.raw_batch(stmts).await,
+            Self::Http(r) => r.raw_batch_with_request_id(stmts, request_id).await,
             #[cfg(feature = "hrana_backend")]
             Self::Hrana(h) => h.raw_batch(stmts).await,
             _ => panic!("Must enable at least one feature"),
+        };
+        if let Some(cache) = self.query_cache() {
+            cache.invalidate();
         }
+        result
     }
 
     /// Transactionally executes a batch of SQL statements.
     ///
+    /// The statements are wrapped in `BEGIN` and `END`, so they either all succeed or all fail.
+    /// `SAVEPOINT` and `RELEASE` can be used within the batch, they create nested transactions
+    /// inside the wrapping one. Explicit `BEGIN`, `COMMIT` or `END` statements can't, they fail
+    /// with "cannot start a transaction within a transaction" - to manage transaction control
+    /// yourself, use [`Client::batch_opts()`] with [`BatchOpts::without_transaction()`].
+    ///
     /// For a version in which statements can fail or succeed independently, see [`Client::raw_batch()`]
     /// # Arguments
     /// * `stmts` - SQL statements
@@ -97,35 +127,162 @@ impl Client {
     where
         <I as IntoIterator>::IntoIter: Send,
     {
+        self.batch_opts(stmts, BatchOpts::default()).await
+    }
+
+    /// Executes a batch of SQL statements with given [BatchOpts].
+    ///
+    /// With default options, it's the same as [`Client::batch()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// use libsql_client::BatchOpts;
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let res = db.batch_opts([
+    ///   "begin immediate",
+    ///   "insert into foo(bar) values ('bar')",
+    ///   "commit",
+    /// ], BatchOpts::new().without_transaction()).await.unwrap();
+    /// assert_eq!(res.len(), 3)
+    /// # }
+    /// ```
+    pub async fn batch_opts<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+        opts: BatchOpts,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let request_id = opts.request_id.as_deref();
+        if !opts.wrap_in_transaction {
+            let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+            let writes: Vec<bool> = stmts.iter().map(Statement::is_write).collect();
+            let batch_results = self.raw_batch_with_request_id(stmts, request_id).await?;
+            if let Some(error) = batch_results.step_errors.into_iter().flatten().next() {
+                return Err(anyhow::anyhow!(error.message));
+            }
+            return batch_results
+                .step_results
+                .into_iter()
+                .zip(writes)
+                .map(|(maybe_rs, is_write)| {
+                    maybe_rs
+                        .map(|rs| ResultSet {
+                            is_write,
+                            ..ResultSet::from(rs)
+                        })
+                        .ok_or_else(|| anyhow::anyhow!("Unexpected missing result set"))
+                        .and_then(|rs| self.map_duplicate_columns(rs))
+                })
+                .collect();
+        }
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        self.batch_wrapped(stmts, request_id).await
+    }
+
+    /// Transactionally executes a batch of SQL statements, returning exactly one result set
+    /// per statement.
+    ///
+    /// The statements are wrapped in `BEGIN` and `END` like in [`Client::batch()`], but results
+    /// are matched to the statements by count rather than by dropping the first and the last one,
+    /// so a server which leaves out the results of the wrapping statements is handled too.
+    /// If the number of results matches neither, an error is returned instead of misaligned
+    /// result sets. [`Client::batch()`] goes through the same path.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let res = db.batch_in_transaction([
+    ///   "insert into foo(bar) values ('bar')",
+    ///   "select * from foo",
+    /// ]).await.unwrap();
+    /// assert_eq!(res.len(), 2);
+    /// assert_eq!(res[1].rows.len(), 1);
+    /// # }
+    /// ```
+    pub async fn batch_in_transaction<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as IntoIterator>::IntoIter: Send,
+    {
+        let stmts: Vec<Statement> = stmts.into_iter().map(|s| s.into()).collect();
+        self.batch_wrapped(stmts, None).await
+    }
+
+    async fn batch_wrapped(
+        &self,
+        stmts: Vec<Statement>,
+        request_id: Option<&str>,
+    ) -> Result<Vec<ResultSet>> {
+        let count = stmts.len();
+        let writes: Vec<bool> = stmts.iter().map(Statement::is_write).collect();
+        let in_transaction = self.in_local_transaction();
         let batch_results = self
-            .raw_batch(
+            .raw_batch_with_request_id(
                 std::iter::once(Statement::new("BEGIN"))
-                    .chain(stmts.into_iter().map(|s| s.into()))
+                    .chain(stmts)
                     .chain(std::iter::once(Statement::new("END"))),
+                request_id,
             )
-            .await?;
-        let step_error: Option<proto::Error> = batch_results
-            .step_errors
-            .into_iter()
-            .skip(1)
-            .find(|e| e.is_some())
-            .flatten();
-        if let Some(error) = step_error {
-            return Err(anyhow::anyhow!(error.message));
-        }
-        let mut step_results: Vec<Result<ResultSet>> = batch_results
-            .step_results
+            .await;
+        let batch_results = match batch_results {
+            Ok(batch_results) => batch_results,
+            Err(e) => {
+                if !in_transaction {
+                    self.rollback_failed_batch();
+                }
+                return Err(e);
+            }
+        };
+        if let Some(error) = batch_results.step_errors.iter().flatten().next() {
+            // If BEGIN failed, e.g. because the connection is already in a transaction,
+            // there's no transaction of the batch to roll back
+            if matches!(batch_results.step_errors.first(), Some(None)) {
+                self.rollback_failed_batch();
+            }
+            return Err(anyhow::anyhow!(error.message.clone()));
+        }
+        unwrap_batch_results(batch_results.step_results, count)?
             .into_iter()
-            .skip(1) // BEGIN is not counted in the result, it's implicitly ignored
-            .map(|maybe_rs| {
+            .zip(writes)
+            .map(|(maybe_rs, is_write)| {
                 maybe_rs
-                    .map(ResultSet::from)
+                    .map(|rs| ResultSet {
+                        is_write,
+                        ..ResultSet::from(rs)
+                    })
                     .ok_or_else(|| anyhow::anyhow!("Unexpected missing result set"))
+                    .and_then(|rs| self.map_duplicate_columns(rs))
             })
-            .collect();
-        step_results.pop(); // END is not counted in the result, it's implicitly ignored
-                            // Collect all the results into a single Result
-        step_results.into_iter().collect::<Result<Vec<ResultSet>>>()
+            .collect()
+    }
+
+    // True if a local connection is inside a transaction, e.g. an interactive one
+    // opened on the same client
+    fn in_local_transaction(&self) -> bool {
+        #[cfg(feature = "local_backend")]
+        if let Self::Local(l) = self {
+            return l.in_transaction();
+        }
+        false
+    }
+
+    // A local connection stays inside the wrapping transaction if a batch stops midway,
+    // remote ones are rolled back by the server when the batch's stream is closed
+    fn rollback_failed_batch(&self) {
+        #[cfg(feature = "local_backend")]
+        if let Self::Local(l) = self {
+            l.execute("ROLLBACK").ok();
+        }
     }
 
     /// Transactionally executes a batch of SQL statements, in synchronous contexts.
@@ -177,353 +334,681 @@ impl Client {
     /// # }
     /// ```
     pub async fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.execute(stmt),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.execute(stmt).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.execute(stmt).await,
-            _ => panic!("Must enable at least one feature"),
+        let stmt = stmt.into();
+        let cache = match self.query_cache() {
+            Some(cache) => cache,
+            None => return self.execute_uncached(stmt).await,
+        };
+        if !QueryCache::is_cacheable(&stmt) {
+            let result = self.execute_uncached(stmt).await;
+            cache.invalidate();
+            return result;
         }
+        let key = QueryCache::key(&stmt);
+        if let Some(rs) = cache.get(&key) {
+            tracing::trace!("Query cache hit for {key}");
+            return Ok(rs);
+        }
+        let rs = self.execute_uncached(stmt).await?;
+        cache.insert(key, rs.clone());
+        Ok(rs)
     }
 
-    /// Creates an interactive transaction
+    /// Executes an `INSERT` and returns the rowid of the inserted row, converted to `T`
+    ///
+    /// Fails if the database did not report a rowid, e.g. for tables declared `WITHOUT ROWID`
+    /// or if no row was inserted, or if the rowid cannot be converted to `T`.
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::Client::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").await.unwrap();
-    /// let tx = db.transaction().await.unwrap();
-    /// tx.execute("select * from foo").await.unwrap();
-    /// tx.commit();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use libsql_client::Value;
+    ///
+    /// struct UserId(i64);
+    ///
+    /// impl TryFrom<&Value> for UserId {
+    ///     type Error = String;
+    ///     fn try_from(value: &Value) -> Result<Self, String> {
+    ///         i64::try_from(value).map(UserId)
+    ///     }
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// let id: UserId = db
+    ///     .insert_returning_id("INSERT INTO users(name) VALUES ('Jane')")
+    ///     .await?;
+    /// assert_eq!(id.0, 1);
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub async fn transaction(&self) -> Result<Transaction> {
-        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        Transaction::new(self, id).await
-    }
-
-    pub(crate) async fn execute_in_transaction(
-        &self,
-        tx_id: u64,
-        stmt: Statement,
-    ) -> Result<ResultSet> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.execute_in_transaction(tx_id, stmt),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.execute_in_transaction(tx_id, stmt).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.execute_in_transaction(tx_id, stmt).await,
-
-            _ => panic!("Must enable at least one feature"),
-        }
-    }
-
-    pub(crate) async fn commit_transaction(&self, tx_id: u64) -> Result<()> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.commit_transaction(tx_id),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.commit_transaction(tx_id).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.commit_transaction(tx_id).await,
-
-            _ => panic!("Must enable at least one feature"),
-        }
-    }
-
-    pub(crate) async fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
-        match self {
-            #[cfg(feature = "local_backend")]
-            Self::Local(l) => l.rollback_transaction(tx_id),
-            #[cfg(any(
-                feature = "reqwest_backend",
-                feature = "workers_backend",
-                feature = "spin_backend"
-            ))]
-            Self::Http(r) => r.rollback_transaction(tx_id).await,
-            #[cfg(feature = "hrana_backend")]
-            Self::Hrana(h) => h.rollback_transaction(tx_id).await,
-
-            _ => panic!("Must enable at least one feature"),
-        }
+    pub async fn insert_returning_id<T>(&self, stmt: impl Into<Statement> + Send) -> Result<T>
+    where
+        T: for<'a> TryFrom<&'a proto::Value, Error = String>,
+    {
+        rowid_as(self.execute(stmt).await?)
     }
-}
 
-impl Client {
-    /// Creates an in-memory database
+    /// Executes a query expected to return exactly one row and deserializes it
+    /// with [`crate::de::from_row()`].
+    ///
+    /// Fails if the query returned no rows or more than one. For lookups which may find nothing,
+    /// see [`Client::query_opt()`].
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use libsql_client::{args, Statement};
+    ///
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     id: i64,
+    ///     name: String,
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// db.execute("INSERT INTO users(name) VALUES ('Jane')").await?;
+    /// let user: User = db
+    ///     .query_one(Statement::with_args("SELECT * FROM users WHERE id = ?", args!(1)))
+    ///     .await?;
+    /// assert_eq!(user.name, "Jane");
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    #[cfg(feature = "local_backend")]
-    pub fn in_memory() -> anyhow::Result<Client> {
-        Ok(Client::Local(crate::local::Client::in_memory()?))
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub async fn query_one<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<T> {
+        self.query_opt(stmt)
+            .await?
+            .ok_or_else(|| anyhow::anyhow!("The query returned no rows, expected exactly one"))
     }
 
-    /// Establishes a database client based on [Config] struct
+    /// Executes a query expected to return at most one row and deserializes it
+    /// with [`crate::de::from_row()`], if found.
+    ///
+    /// Returns `None` if the query returned no rows and fails if it returned more than one.
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
-    /// let config = Config {
-    ///   url: url::Url::parse("file:////tmp/example.db").unwrap(),
-    ///   auth_token: None
-    /// };
-    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// #[derive(serde::Deserialize)]
+    /// struct User {
+    ///     name: String,
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// let user: Option<User> = db.query_opt("SELECT name FROM users WHERE id = 1").await?;
+    /// assert!(user.is_none());
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    #[allow(unreachable_patterns)]
-    pub async fn from_config<'a>(mut config: Config) -> anyhow::Result<Client> {
-        config.url = if config.url.scheme() == "libsql" {
-            // We cannot use url::Url::set_scheme() because it prevents changing the scheme to http...
-            // Safe to unwrap, because we know that the scheme is libsql
-            url::Url::parse(&config.url.as_str().replace("libsql://", "https://")).unwrap()
-        } else {
-            config.url
-        };
-        let scheme = config.url.scheme();
-        Ok(match scheme {
-            #[cfg(feature = "local_backend")]
-            "file" => {
-                Client::Local(crate::local::Client::new(config.url.to_string())?)
-            },
-            #[cfg(feature = "hrana_backend")]
-            "ws" | "wss" => {
-                Client::Hrana(crate::hrana::Client::from_config(config).await?)
-            },
-            #[cfg(feature = "reqwest_backend")]
-            "http" | "https" => {
-                let inner = crate::http::InnerClient::Reqwest(crate::reqwest::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            #[cfg(feature = "workers_backend")]
-            "workers" | "http" | "https" => {
-                let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            #[cfg(feature = "spin_backend")]
-            "spin" | "http" | "https" => {
-                let inner = crate::http::InnerClient::Spin(crate::spin::HttpClient::new());
-                Client::Http(crate::http::Client::from_config(inner, config)?)
-            },
-            _ => anyhow::bail!("Unknown scheme: {scheme}. Make sure your backend exists and is enabled with its feature flag"),
-        })
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub async fn query_opt<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Option<T>> {
+        single_row_as(self.execute(stmt).await?)
     }
 
-    /// Establishes a database client based on environment variables
+    /// Gathers query planner statistics by running `ANALYZE`, for all tables or just `table`,
+    /// which may be qualified with a schema name, e.g. `main.users`.
     ///
-    /// # Env
-    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
-    ///   (with specified credentials) or local file:/// path for a local database
-    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
-    ///   does not require authentication
-    /// *
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
-    /// let db = libsql_client::Client::from_env().await.unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// db.analyze(Some("users")).await?;
+    /// db.analyze(None).await?;
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub async fn from_env() -> anyhow::Result<Client> {
-        let url = std::env::var("LIBSQL_CLIENT_URL").map_err(|_| {
-            anyhow::anyhow!("LIBSQL_CLIENT_URL variable should point to your libSQL/sqld database")
-        })?;
-        let auth_token = std::env::var("LIBSQL_CLIENT_TOKEN").ok();
-        Self::from_config(Config {
-            url: url::Url::parse(&url)?,
-            auth_token,
-        })
-        .await
-    }
-
-    #[cfg(feature = "workers_backend")]
-    pub fn from_workers_env(env: &worker::Env) -> anyhow::Result<Client> {
-        let url = env
-            .secret("LIBSQL_CLIENT_URL")
-            .map_err(|e| anyhow::anyhow!("{e}"))?
-            .to_string();
-        let token = env
-            .secret("LIBSQL_CLIENT_TOKEN")
-            .map_err(|e| anyhow::anyhow!("{e}"))?
-            .to_string();
-        let config = Config {
-            url: url::Url::parse(&url)?,
-            auth_token: Some(token),
+    pub async fn analyze(&self, table: Option<&str>) -> Result<()> {
+        let stmt = match table {
+            Some(table) => format!("ANALYZE {}", utils::quote_table(table)),
+            None => "ANALYZE".to_string(),
         };
-        let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
-        Ok(Client::Http(crate::http::Client::from_config(
-            inner, config,
-        )?))
+        self.execute(stmt).await.map(|_| ())
     }
-}
 
-pub mod sync {}
-impl SyncClient {
-    /// Creates an in-memory database
+    /// Reports how fresh query planner statistics are for each table, to decide when to
+    /// run [`Client::analyze()`] again.
+    ///
+    /// SQLite doesn't record when `ANALYZE` ran, so freshness is estimated by comparing
+    /// the number of rows recorded in `sqlite_stat1` against the current one - see [StatsAge].
+    /// Counting rows scans every table, so this is meant for periodic maintenance jobs.
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn f() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// db.execute("INSERT INTO users(name) VALUES ('John'), ('Jane')").await?;
+    /// assert_eq!(db.stats_age().await?[0].analyzed_rows, None);
+    ///
+    /// db.analyze(None).await?;
+    /// db.execute("INSERT INTO users(name) VALUES ('Jim')").await?;
+    /// let stats = db.stats_age().await?;
+    /// assert_eq!(stats[0].table, "users");
+    /// assert_eq!(stats[0].analyzed_rows, Some(2));
+    /// assert_eq!(stats[0].current_rows, 3);
+    /// assert!(stats[0].is_stale(0.2));
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    #[cfg(feature = "local_backend")]
-    pub fn in_memory() -> anyhow::Result<Self> {
-        Ok(Self {
-            inner: Client::in_memory()?,
-        })
+    pub async fn stats_age(&self) -> Result<Vec<StatsAge>> {
+        let has_stats = !self
+            .execute("SELECT 1 FROM sqlite_schema WHERE type = 'table' AND name = 'sqlite_stat1'")
+            .await?
+            .rows
+            .is_empty();
+        let tables = self
+            .execute(
+                "SELECT name FROM sqlite_schema WHERE type = 'table' AND name NOT LIKE 'sqlite_%' ORDER BY name",
+            )
+            .await?;
+        let mut stats = Vec::with_capacity(tables.rows.len());
+        for row in &tables.rows {
+            let table: &str = row.try_get(0)?;
+            let analyzed_rows = if has_stats {
+                // The first number of `stat` is the (approximate) number of rows in the table
+                let rs = self
+                    .execute(Statement::with_args(
+                        "SELECT max(CAST(stat AS INTEGER)) FROM sqlite_stat1 WHERE tbl = ?",
+                        &[table],
+                    ))
+                    .await?;
+                match rs.rows[0].values.first() {
+                    Some(proto::Value::Integer { value }) => Some(*value as u64),
+                    _ => None,
+                }
+            } else {
+                None
+            };
+            let rs = self
+                .execute(format!(
+                    "SELECT count(*) FROM {}",
+                    utils::quote_ident(table)
+                ))
+                .await?;
+            stats.push(StatsAge {
+                table: table.to_string(),
+                analyzed_rows,
+                current_rows: rs.rows[0].try_get(0)?,
+            });
+        }
+        Ok(stats)
     }
 
-    /// Establishes a database client based on [Config] struct
+    /// Executes a query and writes its result to `w` as CSV, returning the number of rows.
+    ///
+    /// The first record is a header with column names. NULLs become empty fields, blobs are
+    /// base64-encoded, and fields containing commas, quotes or line breaks are quoted,
+    /// as described in RFC 4180 - records end with CRLF too.
+    ///
+    /// The local backend streams rows into the writer as they are read from the database,
+    /// so large exports don't need to fit in memory. Remote backends receive the whole
+    /// result in a single response, so it's buffered before writing.
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn f() {
-    /// # use libsql_client::Config;
-    /// let config = Config { url: url::Url::parse("file:////tmp/example.db").unwrap(), auth_token: None };
-    /// let db = libsql_client::SyncClient::from_config(config).unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)").await?;
+    /// db.execute("INSERT INTO users VALUES (1, 'Jane'), (2, 'Doe, John')").await?;
+    ///
+    /// let mut csv = Vec::new();
+    /// let rows = db.export_csv("SELECT * FROM users", &mut csv).await?;
+    /// assert_eq!(rows, 2);
+    /// assert_eq!(csv, b"id,name\r\n1,Jane\r\n2,\"Doe, John\"\r\n");
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn from_config(config: Config) -> Result<Self> {
-        Ok(Self {
-            inner: futures::executor::block_on(Client::from_config(config))?,
-        })
+    pub async fn export_csv<W: std::io::Write>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+        mut w: W,
+    ) -> Result<u64> {
+        #[cfg(feature = "local_backend")]
+        if let Self::Local(l) = self {
+            // Rows are streamed rather than collected into a ResultSet, bypassing execute(),
+            // so the statement gets its span and invalidates the cache here
+            let stmt = stmt.into();
+            let is_cacheable = QueryCache::is_cacheable(&stmt);
+            let span = utils::QuerySpan::new("local", [&stmt], l.state.span_sql);
+            let result = span.span.in_scope(|| {
+                let mut rows = l.execute_lazy(stmt)?;
+                crate::csv::write_header(&mut w, rows.columns())?;
+                let mut count = 0;
+                for row in &mut rows {
+                    crate::csv::write_record(&mut w, row?.values.iter().map(crate::csv::field))?;
+                    count += 1;
+                }
+                w.flush()?;
+                Ok(count)
+            });
+            span.finish(&result, |count| *count as usize);
+            if let Some(cache) = self.query_cache().filter(|_| !is_cacheable) {
+                cache.invalidate();
+            }
+            return result;
+        }
+        let mut count = 0;
+        let rs = self.execute(stmt).await?;
+        crate::csv::write_header(&mut w, &rs.columns)?;
+        for row in &rs.rows {
+            crate::csv::write_record(&mut w, row.values.iter().map(crate::csv::field))?;
+            count += 1;
+        }
+        w.flush()?;
+        Ok(count)
     }
 
-    /// Establishes a database client based on environment variables
+    /// Counts rows of `table`, optionally only the ones matching a `WHERE` condition
+    /// with its arguments.
     ///
-    /// # Env
-    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
-    ///   (with specified credentials) or local file:/// path for a local database
-    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
-    ///   does not require authentication
+    /// The table name must be a plain identifier, optionally qualified with a schema
+    /// (`main.users`), so it can be safely put into the query. The condition is put into
+    /// the query as is, so only its arguments may come from untrusted input.
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
-    /// # use libsql_client::Config;
-    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
-    /// let db = libsql_client::SyncClient::from_env().unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use libsql_client::Value;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, age INTEGER)").await?;
+    /// db.execute("INSERT INTO users(age) VALUES (17), (25), (40)").await?;
+    /// assert_eq!(db.count("users", None).await?, 3);
+    /// assert_eq!(db.count("users", Some(("age >= ?", &[Value::from(18)]))).await?, 2);
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn from_env() -> Result<Self> {
-        Ok(Self {
-            inner: futures::executor::block_on(Client::from_env())?,
-        })
+    pub async fn count(
+        &self,
+        table: &str,
+        where_clause: Option<(&str, &[proto::Value])>,
+    ) -> Result<u64> {
+        validate_table_name(table)?;
+        let table = utils::quote_table(table);
+        let stmt = match where_clause {
+            Some((condition, args)) => Statement::with_args(
+                format!("SELECT count(*) FROM {table} WHERE {condition}"),
+                args,
+            ),
+            None => Statement::new(format!("SELECT count(*) FROM {table}")),
+        };
+        match self.scalar(stmt).await? {
+            proto::Value::Integer { value } => Ok(value as u64),
+            value => anyhow::bail!("Unexpected count: {value}"),
+        }
     }
 
-    #[cfg(feature = "workers_backend")]
-    pub fn from_workers_env(env: &worker::Env) -> Result<Self> {
-        Ok(Self {
-            inner: Client::from_workers_env(env)?,
-        })
+    /// Inserts rows into `table`, filling `columns` with the fields of each row in order,
+    /// and returns the number of inserted rows.
+    ///
+    /// Rows can be of any type which [`crate::ser::to_params()`] accepts, e.g. structs deriving
+    /// [`serde::Serialize`] or tuples, with as many fields as there are columns.
+    /// They are inserted with multi-row `INSERT ... VALUES (...), (...)` statements, split so that
+    /// each statement stays within SQLite's limit of bound parameters, and executed in a single
+    /// [batch](Client::batch()), so either all rows are inserted or none.
+    ///
+    /// The table name must be a plain identifier, optionally qualified with a schema
+    /// (`main.users`), like in [`Client::count()`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// #[derive(serde::Serialize)]
+    /// struct User {
+    ///     name: String,
+    ///     email: Option<String>,
+    /// }
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT, email TEXT)").await?;
+    /// let users = [
+    ///     User { name: "alice".into(), email: Some("alice@example.com".into()) },
+    ///     User { name: "bob".into(), email: None },
+    /// ];
+    /// assert_eq!(db.insert_many("users", &["name", "email"], &users).await?, 2);
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn insert_many<T: serde::Serialize>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: &[T],
+    ) -> Result<u64> {
+        validate_table_name(table)?;
+        let table = utils::quote_table(table);
+        if columns.is_empty() {
+            return Err(crate::Error::Misuse("No columns to insert into".into()).into());
+        }
+        if rows.is_empty() {
+            return Ok(0);
+        }
+        let columns_sql = columns
+            .iter()
+            .map(|c| utils::quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let row_sql = format!("({})", vec!["?"; columns.len()].join(", "));
+        let rows_per_stmt = (MAX_VARIABLE_NUMBER / columns.len()).max(1);
+
+        let mut stmts = Vec::new();
+        for chunk in rows.chunks(rows_per_stmt) {
+            let mut args = Vec::with_capacity(chunk.len() * columns.len());
+            for row in chunk {
+                let params = crate::ser::to_params(row)?;
+                if params.len() != columns.len() {
+                    return Err(crate::Error::Misuse(format!(
+                        "Row has {} fields, expected one per each of {} columns",
+                        params.len(),
+                        columns.len()
+                    ))
+                    .into());
+                }
+                args.extend(params);
+            }
+            let values = vec![row_sql.as_str(); chunk.len()].join(", ");
+            stmts.push(Statement::with_args(
+                format!("INSERT INTO {table}({columns_sql}) VALUES {values}"),
+                &args,
+            ));
+        }
+        let results = self.batch(stmts).await?;
+        Ok(results.iter().map(|rs| rs.rows_affected).sum())
     }
 
-    /// Executes a batch of independent SQL statements.
+    /// Describes the columns of `table` in their declaration order, with their declared types,
+    /// constraints and defaults. See [ColumnInfo] for details.
     ///
-    /// For a version in which statements execute transactionally, see [`SyncClient::batch()`]
-    /// # Arguments
-    /// * `stmts` - SQL statements
+    /// The columns are read with `PRAGMA table_info`, which all backends support, so the result
+    /// is the same for local and remote databases. Fails if the table doesn't exist.
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let res = db.raw_batch([
-    ///   "select * from foo",
-    ///   "insert into foo(bar) values ('bar')"
-    /// ]).unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT NOT NULL DEFAULT 'anon')")
+    ///     .await?;
+    /// let columns = db.describe("users").await?;
+    /// assert_eq!(columns[0].name, "id");
+    /// assert_eq!(columns[0].pk, Some(1));
+    /// assert_eq!(columns[1].decltype.as_deref(), Some("TEXT"));
+    /// assert!(columns[1].notnull);
+    /// assert_eq!(columns[1].default.as_deref(), Some("'anon'"));
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn raw_batch(
-        &self,
-        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
-    ) -> Result<BatchResult> {
-        futures::executor::block_on(self.inner.raw_batch(stmts))
+    pub async fn describe(&self, table: &str) -> Result<Vec<ColumnInfo>> {
+        let rs = self
+            .execute(Statement::with_args(
+                "SELECT name, type, \"notnull\", pk, dflt_value FROM pragma_table_info(?)",
+                &[table],
+            ))
+            .await?;
+        if rs.rows.is_empty() {
+            anyhow::bail!("No such table: {table}");
+        }
+        rs.rows
+            .iter()
+            .map(|row| {
+                let decltype: &str = row.try_get(1)?;
+                let pk: u32 = row.try_get(3)?;
+                Ok(ColumnInfo {
+                    name: row.try_get::<&str>(0)?.to_string(),
+                    decltype: (!decltype.is_empty()).then(|| decltype.to_string()),
+                    notnull: row.try_get::<i64>(2)? != 0,
+                    pk: (pk > 0).then_some(pk),
+                    default: match row.values.get(4) {
+                        Some(proto::Value::Text { value }) => Some(value.clone()),
+                        _ => None,
+                    },
+                })
+            })
+            .collect()
     }
 
-    /// Transactionally executes a batch of SQL statements.
+    // Returns the first column of the first row of the result
+    async fn scalar(&self, stmt: impl Into<Statement> + Send) -> Result<proto::Value> {
+        let rs = self.execute(stmt).await?;
+        rs.rows
+            .into_iter()
+            .next()
+            .and_then(|row| row.values.into_iter().next())
+            .ok_or_else(|| anyhow::anyhow!("Query returned no rows"))
+    }
+
+    /// Returns the `user_version` of the main database, an integer stored in its header
+    /// for the application's own use, e.g. to track the version of its schema.
     ///
-    /// For a version in which statements can fail or succeed independently, see [`SyncClient::raw_batch()`]
-    /// # Arguments
-    /// * `stmts` - SQL statements
+    /// It's read with `PRAGMA user_version`, which all backends support.
+    /// New databases start at 0.
     ///
     /// # Examples
     ///
     /// ```
-    /// # fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let res = db.batch([
-    ///   "select * from foo",
-    ///   "insert into foo(bar) values ('bar')"
-    /// ]).unwrap();
-    /// assert_eq!(res.len(), 2)
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// assert_eq!(db.user_version().await?, 0);
+    /// db.set_user_version(3).await?;
+    /// assert_eq!(db.user_version().await?, 3);
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
-        &self,
-        stmts: I,
-    ) -> Result<Vec<ResultSet>>
-    where
-        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
-    {
-        futures::executor::block_on(self.inner.batch(stmts))
+    pub async fn user_version(&self) -> Result<i64> {
+        self.pragma_integer("user_version").await
     }
 
-    /// Executes a single SQL statement
+    /// Sets the `user_version` of the main database, see [`Client::user_version()`].
+    /// SQLite stores it as a 32-bit integer.
+    pub async fn set_user_version(&self, version: i32) -> Result<()> {
+        self.execute(format!("PRAGMA user_version = {version}"))
+            .await
+            .map(|_| ())
+    }
+
+    /// Returns the `schema_version` of the main database, which SQLite increments
+    /// on every schema change, e.g. to tell if cached schema information is stale.
+    pub async fn schema_version(&self) -> Result<i64> {
+        self.pragma_integer("schema_version").await
+    }
+
+    // Reads a PRAGMA returning a single integer
+    async fn pragma_integer(&self, pragma: &str) -> Result<i64> {
+        match self.scalar(format!("PRAGMA {pragma}")).await? {
+            proto::Value::Integer { value } => Ok(value),
+            value => anyhow::bail!("Unexpected {pragma}: {value}"),
+        }
+    }
+
+    /// Exports the rows of `table` as `INSERT INTO table(columns) VALUES (...);` statements,
+    /// one per line, e.g. to create seeds or fixtures. The table may be qualified with a schema
+    /// name, e.g. `main.users`.
     ///
-    /// # Arguments
-    /// * `stmt` - SQL statements
+    /// Text is quoted with embedded quotes doubled, blobs are written as `x'..'` hex literals
+    /// and NULLs as `NULL`, so executing the statements restores the rows exactly.
+    /// Only rows are exported, not the schema - and since generated columns cannot be
+    /// inserted into, tables with them need to be exported with a hand-written query instead.
     ///
     /// # Examples
     ///
     /// ```
-    /// # async fn run() {
-    /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// db.execute("select * from foo").unwrap();
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT, avatar BLOB)").await?;
+    /// db.execute("INSERT INTO users VALUES (1, 'O''Brien', x'00ff'), (2, NULL, NULL)").await?;
+    /// assert_eq!(
+    ///     db.dump_table("users").await?,
+    ///     "INSERT INTO \"users\"(\"id\", \"name\", \"avatar\") VALUES (1, 'O''Brien', x'00ff');\n\
+    ///      INSERT INTO \"users\"(\"id\", \"name\", \"avatar\") VALUES (2, NULL, NULL);\n"
+    /// );
+    /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
-        futures::executor::block_on(self.inner.execute(stmt))
+    pub async fn dump_table(&self, table: &str) -> Result<String> {
+        let table = utils::quote_table(table);
+        let rs = self.execute(format!("SELECT * FROM {table}")).await?;
+        let columns = rs
+            .columns
+            .iter()
+            .map(|c| utils::quote_ident(c))
+            .collect::<Vec<_>>()
+            .join(", ");
+        let mut dump = String::new();
+        for row in &rs.rows {
+            let values = row
+                .values
+                .iter()
+                .map(utils::sql_literal)
+                .collect::<Vec<_>>()
+                .join(", ");
+            dump.push_str(&format!(
+                "INSERT INTO {table}({columns}) VALUES ({values});\n"
+            ));
+        }
+        Ok(dump)
+    }
+
+    /// Executes a script of SQL statements separated by semicolons, e.g. read from a `.sql`
+    /// file, returning one result set per statement.
+    ///
+    /// Semicolons inside string literals, quoted identifiers, comments and `CREATE TRIGGER`
+    /// bodies don't split statements, and fragments holding only comments are skipped.
+    /// Statements run one by one, in order, and outside of a transaction unless the script
+    /// opens one. Execution stops at the first failing statement, whose zero-based index
+    /// is added to the error as context.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// let results = db
+    ///     .execute_script(
+    ///         "CREATE TABLE notes(text TEXT);
+    ///          INSERT INTO notes VALUES ('first; and only');
+    ///          SELECT * FROM notes;",
+    ///     )
+    ///     .await?;
+    /// assert_eq!(results.len(), 3);
+    /// assert_eq!(results[2].rows[0].try_get::<&str>(0)?, "first; and only");
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn execute_script(&self, sql: &str) -> Result<Vec<ResultSet>> {
+        let mut results = Vec::new();
+        for (idx, stmt) in crate::migrate::split_statements(sql)
+            .into_iter()
+            .enumerate()
+        {
+            let rs = self
+                .execute(stmt)
+                .await
+                .map_err(|e| e.context(format!("Statement {idx} of the script failed")))?;
+            results.push(rs);
+        }
+        Ok(results)
+    }
+
+    async fn execute_uncached(&self, stmt: Statement) -> Result<ResultSet> {
+        let stopwatch = utils::Stopwatch::start();
+        let rs: Result<ResultSet> = match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.execute(stmt),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.execute(stmt).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.execute(stmt).await,
+            _ => panic!("Must enable at least one feature"),
+        };
+        let rs = self.record_stats(rs?, &stopwatch);
+        self.map_duplicate_columns(rs)
+    }
+
+    // Sets the elapsed time of the statement if Config::with_metrics() is enabled,
+    // keeping row counters reported by the backend
+    fn record_stats(&self, mut rs: ResultSet, stopwatch: &utils::Stopwatch) -> ResultSet {
+        if self.state().is_some_and(|state| state.metrics) {
+            rs.stats.get_or_insert_with(Default::default).elapsed = stopwatch.elapsed();
+        }
+        rs
+    }
+
+    // Applies Config::with_duplicate_columns() to the value maps of rows
+    fn map_duplicate_columns(&self, rs: ResultSet) -> Result<ResultSet> {
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        if let Some(state) = self.state() {
+            return rs.map_duplicate_columns(state.duplicate_columns);
+        }
+        Ok(rs)
+    }
+
+    /// Executes a statement and returns only the number of rows it changed,
+    /// for writes whose results aren't needed.
+    ///
+    /// The local backend skips reading any rows the statement returns, e.g. with a `RETURNING`
+    /// clause, instead of building a [ResultSet]. Other backends receive the rows over the
+    /// network anyway, so it's equivalent to [`Client::execute()`] for them.
+    /// Either way, the statement invalidates the [query cache](Config::with_query_cache()).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(name TEXT)").await?;
+    /// let inserted = db
+    ///     .execute_affected("INSERT INTO users VALUES ('John'), ('Jane')")
+    ///     .await?;
+    /// assert_eq!(inserted, 2);
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn execute_affected(&self, stmt: impl Into<Statement> + Send) -> Result<u64> {
+        #[cfg(feature = "local_backend")]
+        if let Self::Local(l) = self {
+            let result = l.execute_affected(stmt);
+            if let Some(cache) = self.query_cache() {
+                cache.invalidate();
+            }
+            return result;
+        }
+        self.execute(stmt).await.map(|rs| rs.rows_affected)
     }
 
     /// Creates an interactive transaction
@@ -533,75 +1018,2998 @@ impl SyncClient {
     /// ```
     /// # async fn run() {
     /// # use libsql_client::Config;
-    /// let db = libsql_client::SyncClient::in_memory().unwrap();
-    /// # db.execute("create table foo(bar text)").unwrap();
-    /// let tx = db.transaction().unwrap();
-    /// tx.execute("select * from foo").unwrap();
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").await.unwrap();
+    /// let tx = db.transaction().await.unwrap();
+    /// tx.execute("select * from foo").await.unwrap();
     /// tx.commit();
     /// # }
     /// ```
-    pub fn transaction(&self) -> Result<SyncTransaction> {
+    ///
+    /// Fails with [`Error::Misuse`](crate::Error::Misuse) if the limit set with
+    /// [`Config::with_max_open_transactions()`] is reached.
+    pub async fn transaction(&self) -> Result<Transaction<'_>> {
         let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
-        SyncTransaction::new(self, id)
-    }
-
-    pub(crate) fn execute_in_transaction(&self, tx_id: u64, stmt: Statement) -> Result<ResultSet> {
-        futures::executor::block_on(self.inner.execute_in_transaction(tx_id, stmt))
+        Transaction::new(self, id).await
     }
 
-    pub(crate) fn commit_transaction(&self, tx_id: u64) -> Result<()> {
-        futures::executor::block_on(self.inner.commit_transaction(tx_id))
+    /// Like [`Client::transaction()`], but starts the transaction with the given locking behavior,
+    /// e.g. `BEGIN IMMEDIATE` to take the write lock right away. Transactions which read before
+    /// writing should use it to avoid failing with `SQLITE_BUSY` when another one writes first.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use libsql_client::TxBehavior;
+    ///
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE counter(value INTEGER)").await?;
+    /// let tx = db.transaction_with(TxBehavior::Immediate).await?;
+    /// let rs = tx.execute("SELECT count(*) FROM counter").await?;
+    /// tx.execute("INSERT INTO counter VALUES (0)").await?;
+    /// tx.commit().await?;
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn transaction_with(&self, behavior: TxBehavior) -> Result<Transaction<'_>> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        Transaction::begin(self, id, behavior).await
     }
 
-    pub(crate) fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
-        futures::executor::block_on(self.inner.rollback_transaction(tx_id))
+    /// Starts an interactive transaction which holds a shared handle to the client
+    /// instead of borrowing it, so that it can outlive the current scope.
+    /// See [OwnedTransaction] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// use std::sync::Arc;
+    /// use libsql_client::{Client, OwnedTransaction};
+    ///
+    /// async fn begin(db: &Arc<Client>) -> anyhow::Result<OwnedTransaction> {
+    ///     db.clone().transaction_owned().await
+    /// }
+    ///
+    /// let db = Arc::new(Client::in_memory()?);
+    /// db.execute("CREATE TABLE users(name TEXT)").await?;
+    /// let tx = begin(&db).await?;
+    /// tx.execute("INSERT INTO users VALUES ('Jane')").await?;
+    /// tx.commit().await?;
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn transaction_owned(self: std::sync::Arc<Self>) -> Result<OwnedTransaction> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        OwnedTransaction::new(self, id).await
     }
-}
-
-/// Configuration for the database client
-#[derive(Debug)]
-pub struct Config {
-    pub url: url::Url,
-    pub auth_token: Option<String>,
-}
 
-impl Config {
-    /// Create a new [Config]
+    /// Runs `f` in an interactive transaction, committing it if `f` succeeds
+    /// and rolling it back if it fails.
+    ///
+    /// `f` is an async closure which borrows the transaction, so it can await
+    /// any number of statements on it, and borrow its surroundings too.
+    ///
     /// # Examples
     ///
     /// ```
     /// # async fn f() -> anyhow::Result<()> {
-    /// # use libsql_client::Config;
-    /// let config = Config::new("file:////tmp/example.db")?;
-    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE users(name TEXT)").await?;
+    /// let names = ["John", "Jane"];
+    /// let count: i64 = db
+    ///     .transaction_scope(async |tx| {
+    ///         for name in names {
+    ///             tx.execute(libsql_client::Statement::with_args(
+    ///                 "INSERT INTO users VALUES (?)",
+    ///                 &[name],
+    ///             ))
+    ///             .await?;
+    ///         }
+    ///         tx.execute("SELECT count(*) FROM users").await?.rows[0].try_get(0)
+    ///     })
+    ///     .await?;
+    /// assert_eq!(count, 2);
     /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn new<T: TryInto<url::Url>>(url: T) -> Result<Self>
-    where
-        <T as TryInto<url::Url>>::Error: std::fmt::Display,
-    {
-        Ok(Self {
-            url: url
-                .try_into()
-                .map_err(|e| anyhow::anyhow!("Failed to parse url: {}", e))?,
-            auth_token: None,
-        })
+    pub async fn transaction_scope<T>(
+        &self,
+        f: impl AsyncFnOnce(&Transaction<'_>) -> Result<T>,
+    ) -> Result<T> {
+        let tx = self.transaction().await?;
+        match f(&tx).await {
+            Ok(value) => tx.commit().await.map(|_| value),
+            Err(e) => {
+                tx.rollback().await.ok();
+                Err(e)
+            }
+        }
     }
 
-    /// Adds an authentication token to config
+    /// Like [`Client::transaction_scope()`], but retries the whole transaction
+    /// if the database is busy.
+    ///
+    /// If beginning the transaction, `f` or the commit fails with `SQLITE_BUSY`
+    /// (including extended codes like `SQLITE_BUSY_SNAPSHOT`), the transaction is rolled back
+    /// and `f` runs again in a new one, after a backoff which doubles with each attempt.
+    /// See [`Config::with_transaction_retries()`] for the limits. Any other error rolls
+    /// the transaction back and is returned right away, and so is the last busy error
+    /// once retries run out. Since `f` may run more than once, it should not have
+    /// side effects outside of the transaction.
+    ///
     /// # Examples
     ///
     /// ```
     /// # async fn f() -> anyhow::Result<()> {
-    /// # use libsql_client::Config;
-    /// let config = Config::new("https://example.com/db")?.with_auth_token("secret");
-    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("CREATE TABLE counter(value INTEGER)").await?;
+    /// db.execute("INSERT INTO counter VALUES (0)").await?;
+    /// let value: i64 = db
+    ///     .with_transaction(async |tx| {
+    ///         tx.execute("UPDATE counter SET value = value + 1").await?;
+    ///         let rs = tx.execute("SELECT value FROM counter").await?;
+    ///         rs.rows[0].try_get(0)
+    ///     })
+    ///     .await?;
+    /// assert_eq!(value, 1);
     /// # Ok(())
     /// # }
+    /// # futures::executor::block_on(f()).unwrap();
     /// ```
-    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
-        self.auth_token = Some(token.into());
-        self
+    pub async fn with_transaction<T>(
+        &self,
+        f: impl AsyncFn(&Transaction<'_>) -> Result<T>,
+    ) -> Result<T> {
+        let (max_retries, mut backoff) = self.transaction_retries();
+        let mut attempt = 0;
+        loop {
+            match self.transaction_scope(&f).await {
+                Err(e) if attempt < max_retries && is_busy(&e) => {
+                    tracing::debug!("Transaction failed with {e}, retrying in {backoff:?}");
+                    crate::utils::sleep(backoff).await;
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the URL of the database this client points at, with credentials stripped,
+    /// or `None` if no backend is set
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// assert_eq!(db.endpoint(), Some(":memory:"));
+    /// assert!(!db.has_auth());
+    /// # }
+    /// ```
+    pub fn endpoint(&self) -> Option<&str> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => Some(l.endpoint()),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => Some(r.endpoint()),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => Some(h.endpoint()),
+            Self::Default => None,
+        }
+    }
+
+    /// Returns true if the client was configured with an auth token
+    pub fn has_auth(&self) -> bool {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(_) => false,
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.has_auth(),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.has_auth(),
+            Self::Default => false,
+        }
+    }
+
+    /// Closes the client, releasing its connections at a well-defined point
+    /// rather than whenever it's dropped.
+    ///
+    /// Hrana WebSocket connections are shut down and their connection tasks awaited,
+    /// and local databases are closed. HTTP clients hold no connections of their own,
+    /// so it's a no-op for them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// db.execute("SELECT 1").await?;
+    /// db.close().await?;
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub async fn close(self) -> Result<()> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.close(),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(_) => Ok(()),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.shutdown().await,
+            Self::Default => Ok(()),
+        }
+    }
+
+    /// Returns time spent on JSON serialization, network and JSON parsing by requests
+    /// sent so far, or `None` if the client doesn't use an HTTP backend.
+    /// See [`crate::http::Timings`].
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend"
+    ))]
+    pub fn http_timings(&self) -> Option<crate::http::Timings> {
+        match self {
+            Self::Http(r) => Some(r.timings()),
+            #[allow(unreachable_patterns)]
+            _ => None,
+        }
+    }
+
+    /// Creates a loop syncing an embedded replica with its primary every `interval`.
+    /// See [`local::Client::background_sync()`](crate::local::Client::background_sync) for details.
+    ///
+    /// Fails with [`Error::Misuse`](crate::Error::Misuse) for remote backends and local
+    /// databases which are not replicas.
+    #[cfg(feature = "local_backend")]
+    pub fn background_sync(
+        &self,
+        interval: std::time::Duration,
+    ) -> Result<(
+        crate::local::SyncHandle,
+        impl std::future::Future<Output = ()> + '_,
+    )> {
+        match self {
+            Self::Local(l) => l.background_sync(interval),
+            #[allow(unreachable_patterns)]
+            _ => Err(crate::Error::Misuse(
+                "Background sync is only available for embedded replicas".into(),
+            )
+            .into()),
+        }
+    }
+
+    pub(crate) async fn execute_in_transaction(
+        &self,
+        tx_id: u64,
+        stmt: Statement,
+    ) -> Result<ResultSet> {
+        if let Some(cache) = self.query_cache() {
+            if !QueryCache::is_cacheable(&stmt) {
+                cache.invalidate();
+            }
+        }
+        let stopwatch = utils::Stopwatch::start();
+        let rs: Result<ResultSet> = match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.execute_in_transaction(tx_id, stmt),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.execute_in_transaction(tx_id, stmt).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.execute_in_transaction(tx_id, stmt).await,
+            _ => panic!("Must enable at least one feature"),
+        };
+        let rs = self.record_stats(rs?, &stopwatch);
+        self.map_duplicate_columns(rs)
+    }
+
+    pub(crate) async fn commit_transaction(&self, tx_id: u64) -> Result<()> {
+        if let Some(cache) = self.query_cache() {
+            cache.invalidate();
+        }
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.commit_transaction(tx_id),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.commit_transaction(tx_id).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.commit_transaction(tx_id).await,
+
+            _ => panic!("Must enable at least one feature"),
+        }
+    }
+
+    pub(crate) async fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
+        // The local backend shares a connection with transactions, so reads outside
+        // of the transaction could have cached its uncommitted writes
+        if let Some(cache) = self.query_cache() {
+            cache.invalidate();
+        }
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.rollback_transaction(tx_id),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.rollback_transaction(tx_id).await,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.rollback_transaction(tx_id).await,
+
+            _ => panic!("Must enable at least one feature"),
+        }
+    }
+
+    /// Best-effort cleanup of a transaction that was neither committed nor rolled back.
+    pub(crate) fn drop_transaction(&self, tx_id: u64) {
+        if let Some(cache) = self.query_cache() {
+            cache.invalidate();
+        }
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.drop_transaction(tx_id),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.drop_transaction(tx_id),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.drop_transaction(tx_id),
+            Self::Default => (),
+        }
+    }
+
+    pub(crate) fn baton(&self, tx_id: u64) -> Option<String> {
+        match self {
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.baton(tx_id),
+            #[allow(unreachable_patterns)]
+            _ => {
+                let _ = tx_id;
+                None
+            }
+        }
+    }
+
+    fn state(&self) -> Option<&ClientState> {
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => Some(&l.state),
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => Some(&r.state),
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => Some(&h.state),
+            Self::Default => None,
+        }
+    }
+
+    fn set_state(&mut self, state: ClientState) {
+        let state = std::sync::Arc::new(state);
+        match self {
+            #[cfg(feature = "local_backend")]
+            Self::Local(l) => l.state = state,
+            #[cfg(any(
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            Self::Http(r) => r.state = state,
+            #[cfg(feature = "hrana_backend")]
+            Self::Hrana(h) => h.state = state,
+            Self::Default => (),
+        }
+    }
+
+    fn transaction_retries(&self) -> (usize, std::time::Duration) {
+        self.state()
+            .and_then(|state| state.transaction_retries)
+            .unwrap_or((DEFAULT_TRANSACTION_RETRIES, DEFAULT_TRANSACTION_BACKOFF))
+    }
+
+    fn query_cache(&self) -> Option<&QueryCache> {
+        self.state().and_then(|state| state.query_cache.as_ref())
+    }
+
+    pub(crate) fn acquire_transaction_slot(&self) -> Result<()> {
+        let state = match self.state() {
+            Some(state) => state,
+            None => return Ok(()),
+        };
+        let max = state.max_open_transactions.unwrap_or(usize::MAX);
+        state
+            .open_transactions
+            .fetch_update(
+                std::sync::atomic::Ordering::AcqRel,
+                std::sync::atomic::Ordering::Acquire,
+                |open| (open < max).then_some(open + 1),
+            )
+            .map_err(|_| crate::Error::Misuse("too many open transactions".to_string()))?;
+        Ok(())
+    }
+
+    pub(crate) fn release_transaction_slot(&self) {
+        if let Some(state) = self.state() {
+            state
+                .open_transactions
+                .fetch_sub(1, std::sync::atomic::Ordering::AcqRel);
+        }
+    }
+}
+
+// Converts the rowid reported for an insert to the requested type
+fn rowid_as<T>(rs: ResultSet) -> Result<T>
+where
+    T: for<'a> TryFrom<&'a proto::Value, Error = String>,
+{
+    let rowid = rs
+        .last_insert_rowid
+        .ok_or_else(|| anyhow::anyhow!("The statement did not report a rowid"))?;
+    T::try_from(&proto::Value::Integer { value: rowid }).map_err(|e| anyhow::anyhow!(e))
+}
+
+// Drops the results of the BEGIN and END wrapping a batch of `count` statements.
+// Servers which leave them out already returned exactly one result per statement.
+fn unwrap_batch_results<T>(mut results: Vec<T>, count: usize) -> Result<Vec<T>> {
+    if results.len() == count + 2 {
+        results.pop();
+        results.remove(0);
+    }
+    if results.len() != count {
+        anyhow::bail!(
+            "Unexpected number of batch results: expected {count}, got {}",
+            results.len()
+        );
+    }
+    Ok(results)
+}
+
+// Deserializes the only row of a result set, if any
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+fn single_row_as<T: serde::de::DeserializeOwned>(rs: ResultSet) -> Result<Option<T>> {
+    match rs.rows.as_slice() {
+        [] => Ok(None),
+        [row] => crate::de::from_row(row).map(Some),
+        rows => anyhow::bail!(
+            "The query returned {} rows, expected at most one",
+            rows.len()
+        ),
+    }
+}
+
+// True for SQLITE_BUSY and its extended codes, which are worth retrying the transaction for
+pub(crate) fn is_busy(e: &anyhow::Error) -> bool {
+    e.downcast_ref::<crate::Error>()
+        .and_then(crate::Error::code)
+        .is_some_and(|code| code == "SQLITE_BUSY" || code.starts_with("SQLITE_BUSY_"))
+}
+
+/// Client-side state shared by all backends, set up from [Config]
+#[derive(Debug, Default)]
+pub(crate) struct ClientState {
+    query_cache: Option<QueryCache>,
+    max_open_transactions: Option<usize>,
+    open_transactions: std::sync::atomic::AtomicUsize,
+    transaction_retries: Option<(usize, std::time::Duration)>,
+    // Only checked by remote backends, local ones bind parameters of any size
+    #[cfg(any(
+        feature = "hrana_backend",
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend"
+    ))]
+    max_param_size: Option<usize>,
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    duplicate_columns: DuplicateColumns,
+    #[cfg(feature = "local_backend")]
+    pub(crate) text_decoding: TextDecoding,
+    pub(crate) metrics: bool,
+    pub(crate) span_sql: bool,
+}
+
+impl ClientState {
+    fn from_config(config: &Config) -> Result<Self> {
+        // Entries of the query cache expire based on the system clock, which isn't available there
+        if cfg!(target_arch = "wasm32") && config.query_cache.is_some() {
+            return Err(crate::Error::Misuse(
+                "The query cache is not supported on wasm32-unknown-unknown".into(),
+            )
+            .into());
+        }
+        Ok(Self {
+            query_cache: config
+                .query_cache
+                .map(|(capacity, ttl)| QueryCache::new(capacity, ttl)),
+            max_open_transactions: config.max_open_transactions,
+            open_transactions: Default::default(),
+            transaction_retries: config.transaction_retries,
+            #[cfg(any(
+                feature = "hrana_backend",
+                feature = "reqwest_backend",
+                feature = "workers_backend",
+                feature = "spin_backend",
+                feature = "fetch_backend"
+            ))]
+            max_param_size: config.max_param_size,
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            duplicate_columns: config.duplicate_columns,
+            #[cfg(feature = "local_backend")]
+            text_decoding: config.text_decoding,
+            metrics: config.metrics,
+            span_sql: config.span_sql,
+        })
+    }
+
+    /// Fails if a text or blob parameter of the statement is larger than
+    /// [`Config::with_max_param_size()`] allows
+    #[cfg(any(
+        feature = "hrana_backend",
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend"
+    ))]
+    pub(crate) fn check_param_sizes(&self, stmt: &Statement) -> Result<()> {
+        let Some(max) = self.max_param_size else {
+            return Ok(());
+        };
+        let size = |value: &proto::Value| match value {
+            proto::Value::Text { value } => value.len(),
+            proto::Value::Blob { value } => value.len(),
+            _ => 0,
+        };
+        for (idx, value) in stmt.args.iter().enumerate() {
+            if size(value) > max {
+                let n = idx + 1;
+                return Err(crate::Error::Misuse(format!("parameter {n} exceeds max size")).into());
+            }
+        }
+        for (name, value) in &stmt.named_args {
+            if size(value) > max {
+                return Err(
+                    crate::Error::Misuse(format!("parameter {name} exceeds max size")).into(),
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Client {
+    /// Creates an in-memory database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::Client::in_memory().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory() -> anyhow::Result<Client> {
+        Ok(Client::Local(crate::local::Client::in_memory()?))
+    }
+
+    /// Connects to a named in-memory database shared by all clients created with the same name,
+    /// see [`local::Client::in_memory_named()`](crate::local::Client::in_memory_named)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::Client::in_memory_named("test").unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory_named(name: &str) -> anyhow::Result<Client> {
+        Ok(Client::Local(crate::local::Client::in_memory_named(name)?))
+    }
+
+    /// Establishes a database client based on [Config] struct
+    ///
+    /// The backend is picked from [`Config::backend`] if set, and guessed from the URL scheme
+    /// otherwise - see [Backend] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db").unwrap();
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # }
+    /// ```
+    pub async fn from_config(mut config: Config) -> anyhow::Result<Client> {
+        let backend = Self::resolve_backend(&mut config)?;
+        let state = ClientState::from_config(&config)?;
+        let mut client: Client = match backend {
+            #[cfg(feature = "local_backend")]
+            Backend::Local => {
+                let local = if config.read_only {
+                    crate::local::Client::open_read_only(config.url.to_string())?
+                } else {
+                    crate::local::Client::new(config.url.to_string())?
+                };
+                if let Some(size) = config.cache_size {
+                    local.set_cache_size(size)?;
+                }
+                Client::Local(local)
+            }
+            #[cfg(feature = "hrana_backend")]
+            Backend::Hrana => Client::Hrana(crate::hrana::Client::from_config(config).await?),
+            #[cfg(feature = "reqwest_backend")]
+            Backend::Reqwest => {
+                let inner = crate::http::InnerClient::Reqwest(
+                    crate::reqwest::HttpClient::from_config(&config)?,
+                );
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            }
+            #[cfg(feature = "workers_backend")]
+            Backend::Workers => {
+                let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            }
+            #[cfg(feature = "spin_backend")]
+            Backend::Spin => {
+                let inner = crate::http::InnerClient::Spin(crate::spin::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            }
+            #[cfg(feature = "fetch_backend")]
+            Backend::Fetch => {
+                let inner = crate::http::InnerClient::Fetch(crate::fetch::HttpClient::new());
+                Client::Http(crate::http::Client::from_config(inner, config)?)
+            }
+            #[cfg(not(feature = "local_backend"))]
+            Backend::Local => {
+                anyhow::bail!("Backend {backend:?} requires the local_backend feature")
+            }
+            #[cfg(not(feature = "hrana_backend"))]
+            Backend::Hrana => {
+                anyhow::bail!("Backend {backend:?} requires the hrana_backend feature")
+            }
+            #[cfg(not(feature = "reqwest_backend"))]
+            Backend::Reqwest => {
+                anyhow::bail!("Backend {backend:?} requires the reqwest_backend feature")
+            }
+            #[cfg(not(feature = "workers_backend"))]
+            Backend::Workers => {
+                anyhow::bail!("Backend {backend:?} requires the workers_backend feature")
+            }
+            #[cfg(not(feature = "spin_backend"))]
+            Backend::Spin => anyhow::bail!("Backend {backend:?} requires the spin_backend feature"),
+            #[cfg(not(feature = "fetch_backend"))]
+            Backend::Fetch => {
+                anyhow::bail!("Backend {backend:?} requires the fetch_backend feature")
+            }
+        };
+        client.set_state(state);
+        Ok(client)
+    }
+
+    // Picks the backend for given config and rewrites libsql:// URLs to the scheme it expects
+    fn resolve_backend(config: &mut Config) -> anyhow::Result<Backend> {
+        let backend = match config.backend {
+            Some(backend) => backend,
+            None => Backend::from_scheme(config.url.scheme())?,
+        };
+        if config.url.scheme() == "libsql" {
+            // We cannot use url::Url::set_scheme() because it prevents changing the scheme to http...
+            // Safe to unwrap, because we know that the scheme is libsql
+            let scheme = match (backend == Backend::Hrana, config.tls) {
+                (true, true) => "wss://",
+                (true, false) => "ws://",
+                (false, true) => "https://",
+                (false, false) => "http://",
+            };
+            config.url =
+                url::Url::parse(&config.url.as_str().replace("libsql://", scheme)).unwrap();
+        }
+        Ok(backend)
+    }
+
+    /// Establishes a database client based on [Config] struct, sending HTTP requests through
+    /// the given `reqwest::Client` instead of one built from the config.
+    ///
+    /// Useful for test harnesses and embedded setups that need custom DNS resolution,
+    /// proxies, or an in-process server. TLS settings from the config
+    /// ([`Config::with_root_certificate()`] etc.) are ignored - configure them on the connector.
+    /// Only [`Backend::Reqwest`] is supported, and it's used if no backend is set in the config.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::{Client, Config};
+    /// let connector = reqwest::Client::builder()
+    ///     .timeout(std::time::Duration::from_secs(5))
+    ///     .build()?;
+    /// let config = Config::new("https://example.com/db")?;
+    /// let db = Client::from_config_with_connector(config, connector).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "reqwest_backend")]
+    pub async fn from_config_with_connector(
+        mut config: Config,
+        connector: reqwest::Client,
+    ) -> anyhow::Result<Client> {
+        config.backend.get_or_insert(Backend::Reqwest);
+        let backend = Self::resolve_backend(&mut config)?;
+        if backend != Backend::Reqwest {
+            anyhow::bail!("A custom connector requires the Reqwest backend, not {backend:?}");
+        }
+        let state = ClientState::from_config(&config)?;
+        let inner = crate::http::InnerClient::Reqwest(crate::reqwest::HttpClient::with_connector(
+            connector,
+        ));
+        let mut client = Client::Http(crate::http::Client::from_config(inner, config)?);
+        client.set_state(state);
+        Ok(client)
+    }
+
+    /// Creates a database client which sends its HTTP requests through a custom [HttpBackend](crate::http::HttpBackend).
+    ///
+    /// The config's URL is passed to the backend as-is, with the pipeline path appended.
+    /// Other options apply like for the built-in HTTP backends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f(transport: impl libsql_client::http::HttpBackend + 'static) -> anyhow::Result<()> {
+    /// # use libsql_client::{Client, Config};
+    /// let config = Config::new("http://localhost")?;
+    /// let db = Client::from_config_with_http_backend(config, transport)?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend",
+    ))]
+    pub fn from_config_with_http_backend(
+        config: Config,
+        backend: impl crate::http::HttpBackend + 'static,
+    ) -> anyhow::Result<Client> {
+        let state = ClientState::from_config(&config)?;
+        let inner = crate::http::InnerClient::custom(backend);
+        let mut client = Client::Http(crate::http::Client::from_config(inner, config)?);
+        client.set_state(state);
+        Ok(client)
+    }
+
+    /// Establishes a database client based on environment variables
+    ///
+    /// # Env
+    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
+    ///   (with specified credentials) or local file:/// path for a local database
+    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
+    ///   does not require authentication
+    /// *
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
+    /// let db = libsql_client::Client::from_env().await.unwrap();
+    /// # }
+    /// ```
+    pub async fn from_env() -> anyhow::Result<Client> {
+        Self::from_env_prefixed("LIBSQL_CLIENT").await
+    }
+
+    /// Establishes a database client based on environment variables with a custom prefix,
+    /// which lets a single process connect to multiple databases
+    ///
+    /// # Env
+    /// * `{prefix}_URL` - URL of the database endpoint, like `LIBSQL_CLIENT_URL` in [Client::from_env()]
+    /// * (optional) `{prefix}_TOKEN` - authentication token for the database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # std::env::set_var("ANALYTICS_DB_URL", "file:////tmp/analytics.db");
+    /// let analytics = libsql_client::Client::from_env_prefixed("ANALYTICS_DB").await.unwrap();
+    /// # }
+    /// ```
+    pub async fn from_env_prefixed(prefix: &str) -> anyhow::Result<Client> {
+        let url_var = format!("{prefix}_URL");
+        let url = std::env::var(&url_var).map_err(|_| {
+            anyhow::anyhow!("{url_var} variable should point to your libSQL/sqld database")
+        })?;
+        let auth_token = std::env::var(format!("{prefix}_TOKEN")).ok();
+        let mut config = Config::new(url.as_str())?;
+        config.auth_token = auth_token.map(AuthToken::from);
+        Self::from_config(config).await
+    }
+
+    #[cfg(feature = "workers_backend")]
+    pub fn from_workers_env(env: &worker::Env) -> anyhow::Result<Client> {
+        let url = env
+            .secret("LIBSQL_CLIENT_URL")
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .to_string();
+        let token = env
+            .secret("LIBSQL_CLIENT_TOKEN")
+            .map_err(|e| anyhow::anyhow!("{e}"))?
+            .to_string();
+        let config = Config::new(url.as_str())?
+            .with_auth_token(token)
+            .with_backend(Backend::Workers);
+        let inner = crate::http::InnerClient::Workers(crate::workers::HttpClient::new());
+        Ok(Client::Http(crate::http::Client::from_config(
+            inner, config,
+        )?))
+    }
+}
+
+/// Maximum number of bound parameters in a statement, SQLITE_MAX_VARIABLE_NUMBER
+/// in SQLite since 3.32.0
+const MAX_VARIABLE_NUMBER: usize = 32766;
+
+// Checks that the table name is a plain identifier, optionally qualified with a schema,
+// so it can be safely put into a query
+fn validate_table_name(table: &str) -> Result<()> {
+    let parts: Vec<&str> = table.split('.').collect();
+    if parts.len() > 2 || !parts.into_iter().all(utils::is_identifier) {
+        return Err(crate::Error::Misuse(format!("Invalid table name: {table:?}")).into());
+    }
+    Ok(())
+}
+
+pub mod sync {}
+impl SyncClient {
+    /// Creates an in-memory database
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory() -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: Client::in_memory()?,
+        })
+    }
+
+    /// Connects to a named in-memory database shared by all clients created with the same name,
+    /// see [`local::Client::in_memory_named()`](crate::local::Client::in_memory_named)
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::SyncClient::in_memory_named("test").unwrap();
+    /// # }
+    /// ```
+    #[cfg(feature = "local_backend")]
+    pub fn in_memory_named(name: &str) -> anyhow::Result<Self> {
+        Ok(Self {
+            inner: Client::in_memory_named(name)?,
+        })
+    }
+
+    /// Establishes a database client based on [Config] struct
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db").unwrap();
+    /// let db = libsql_client::SyncClient::from_config(config).unwrap();
+    /// # }
+    /// ```
+    pub fn from_config(config: Config) -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_config(config))?,
+        })
+    }
+
+    /// Establishes a database client based on environment variables
+    ///
+    /// # Env
+    /// * `LIBSQL_CLIENT_URL` - URL of the database endpoint - e.g. a https:// endpoint for remote connections
+    ///   (with specified credentials) or local file:/// path for a local database
+    /// * (optional) `LIBSQL_CLIENT_TOKEN` - authentication token for the database. Skip if your database
+    ///   does not require authentication
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// # std::env::set_var("LIBSQL_CLIENT_URL", "file:////tmp/example.db");
+    /// let db = libsql_client::SyncClient::from_env().unwrap();
+    /// # }
+    /// ```
+    pub fn from_env() -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_env())?,
+        })
+    }
+
+    /// Establishes a database client based on environment variables with a custom prefix,
+    /// see [Client::from_env_prefixed()]
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # std::env::set_var("ANALYTICS_DB_URL", "file:////tmp/analytics.db");
+    /// let analytics = libsql_client::SyncClient::from_env_prefixed("ANALYTICS_DB").unwrap();
+    /// # }
+    /// ```
+    pub fn from_env_prefixed(prefix: &str) -> Result<Self> {
+        Ok(Self {
+            inner: futures::executor::block_on(Client::from_env_prefixed(prefix))?,
+        })
+    }
+
+    #[cfg(feature = "workers_backend")]
+    pub fn from_workers_env(env: &worker::Env) -> Result<Self> {
+        Ok(Self {
+            inner: Client::from_workers_env(env)?,
+        })
+    }
+
+    /// Wraps an existing [Client], so synchronous code can use its connection
+    /// instead of opening another one.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::Client::in_memory()?;
+    /// let db = libsql_client::SyncClient::from_client(db);
+    /// db.execute("CREATE TABLE t(x)")?;
+    /// let db = db.into_inner(); // back to async
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn from_client(client: Client) -> Self {
+        Self { inner: client }
+    }
+
+    /// Returns the [Client] this client wraps, for use in asynchronous code
+    pub fn as_client(&self) -> &Client {
+        &self.inner
+    }
+
+    /// Unwraps the [Client] this client wraps, the inverse of [`SyncClient::from_client()`]
+    pub fn into_inner(self) -> Client {
+        self.inner
+    }
+
+    /// Executes a batch of independent SQL statements.
+    ///
+    /// For a version in which statements execute transactionally, see [`SyncClient::batch()`]
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let res = db.raw_batch([
+    ///   "select * from foo",
+    ///   "insert into foo(bar) values ('bar')"
+    /// ]).unwrap();
+    /// # }
+    /// ```
+    pub fn raw_batch(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
+    ) -> Result<BatchResult> {
+        futures::executor::block_on(self.inner.raw_batch(stmts))
+    }
+
+    /// Transactionally executes a batch of SQL statements.
+    ///
+    /// For a version in which statements can fail or succeed independently, see [`SyncClient::raw_batch()`]
+    /// # Arguments
+    /// * `stmts` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let res = db.batch([
+    ///   "select * from foo",
+    ///   "insert into foo(bar) values ('bar')"
+    /// ]).unwrap();
+    /// assert_eq!(res.len(), 2)
+    /// # }
+    /// ```
+    pub fn batch<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch(stmts))
+    }
+
+    /// Transactionally executes a batch of SQL statements, returning one result set per statement.
+    /// See [`Client::batch_in_transaction()`] for details.
+    pub fn batch_in_transaction<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch_in_transaction(stmts))
+    }
+
+    /// Executes a batch of SQL statements with given [BatchOpts].
+    /// See [`Client::batch_opts()`] for details.
+    pub fn batch_opts<I: IntoIterator<Item = impl Into<Statement> + Send> + Send>(
+        &self,
+        stmts: I,
+        opts: BatchOpts,
+    ) -> Result<Vec<ResultSet>>
+    where
+        <I as std::iter::IntoIterator>::IntoIter: std::marker::Send,
+    {
+        futures::executor::block_on(self.inner.batch_opts(stmts, opts))
+    }
+
+    /// Executes a single SQL statement
+    ///
+    /// # Arguments
+    /// * `stmt` - SQL statements
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// db.execute("select * from foo").unwrap();
+    /// # }
+    /// ```
+    pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
+        futures::executor::block_on(self.inner.execute(stmt))
+    }
+
+    /// Executes an `INSERT` and returns the rowid of the inserted row, converted to `T`.
+    /// See [`Client::insert_returning_id()`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)")?;
+    /// let id: u64 = db.insert_returning_id("INSERT INTO users(name) VALUES ('Jane')")?;
+    /// assert_eq!(id, 1);
+    ///
+    /// db.execute("CREATE TABLE tags(name TEXT PRIMARY KEY) WITHOUT ROWID")?;
+    /// assert!(db.insert_returning_id::<u64>("INSERT INTO tags VALUES ('new')").is_err());
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn insert_returning_id<T>(&self, stmt: impl Into<Statement> + Send) -> Result<T>
+    where
+        T: for<'a> TryFrom<&'a proto::Value, Error = String>,
+    {
+        futures::executor::block_on(self.inner.insert_returning_id(stmt))
+    }
+
+    /// Executes a query expected to return exactly one row and deserializes it.
+    /// See [`Client::query_one()`] for details.
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn query_one<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<T> {
+        futures::executor::block_on(self.inner.query_one(stmt))
+    }
+
+    /// Executes a query expected to return at most one row and deserializes it, if found.
+    /// See [`Client::query_opt()`] for details.
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn query_opt<T: serde::de::DeserializeOwned>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+    ) -> Result<Option<T>> {
+        futures::executor::block_on(self.inner.query_opt(stmt))
+    }
+
+    /// Gathers query planner statistics by running `ANALYZE`, for all tables or just `table`
+    pub fn analyze(&self, table: Option<&str>) -> Result<()> {
+        futures::executor::block_on(self.inner.analyze(table))
+    }
+
+    /// Reports how fresh query planner statistics are for each table.
+    /// See [`Client::stats_age()`] for details.
+    pub fn stats_age(&self) -> Result<Vec<StatsAge>> {
+        futures::executor::block_on(self.inner.stats_age())
+    }
+
+    /// Executes a query and writes its result to `w` as CSV, returning the number of rows.
+    /// See [`Client::export_csv()`] for details.
+    pub fn export_csv<W: std::io::Write>(
+        &self,
+        stmt: impl Into<Statement> + Send,
+        w: W,
+    ) -> Result<u64> {
+        futures::executor::block_on(self.inner.export_csv(stmt, w))
+    }
+
+    /// Counts rows of `table`, optionally only the ones matching a `WHERE` condition.
+    /// See [`Client::count()`] for details.
+    pub fn count(&self, table: &str, where_clause: Option<(&str, &[proto::Value])>) -> Result<u64> {
+        futures::executor::block_on(self.inner.count(table, where_clause))
+    }
+
+    /// Inserts rows into `table` in bulk, returning the number of inserted rows.
+    /// See [`Client::insert_many()`] for details.
+    pub fn insert_many<T: serde::Serialize>(
+        &self,
+        table: &str,
+        columns: &[&str],
+        rows: &[T],
+    ) -> Result<u64> {
+        futures::executor::block_on(self.inner.insert_many(table, columns, rows))
+    }
+
+    /// Describes the columns of `table`.
+    /// See [`Client::describe()`] for details.
+    pub fn describe(&self, table: &str) -> Result<Vec<ColumnInfo>> {
+        futures::executor::block_on(self.inner.describe(table))
+    }
+
+    /// Returns the `user_version` of the main database.
+    /// See [`Client::user_version()`] for details.
+    pub fn user_version(&self) -> Result<i64> {
+        futures::executor::block_on(self.inner.user_version())
+    }
+
+    /// Sets the `user_version` of the main database.
+    /// See [`Client::set_user_version()`] for details.
+    pub fn set_user_version(&self, version: i32) -> Result<()> {
+        futures::executor::block_on(self.inner.set_user_version(version))
+    }
+
+    /// Returns the `schema_version` of the main database.
+    /// See [`Client::schema_version()`] for details.
+    pub fn schema_version(&self) -> Result<i64> {
+        futures::executor::block_on(self.inner.schema_version())
+    }
+
+    /// Exports the rows of `table` as `INSERT` statements.
+    /// See [`Client::dump_table()`] for details.
+    pub fn dump_table(&self, table: &str) -> Result<String> {
+        futures::executor::block_on(self.inner.dump_table(table))
+    }
+
+    /// Executes a script of SQL statements separated by semicolons.
+    /// See [`Client::execute_script()`] for details.
+    pub fn execute_script(&self, sql: &str) -> Result<Vec<ResultSet>> {
+        futures::executor::block_on(self.inner.execute_script(sql))
+    }
+
+    /// Executes a statement and returns only the number of rows it changed.
+    /// See [`Client::execute_affected()`] for details.
+    pub fn execute_affected(&self, stmt: impl Into<Statement> + Send) -> Result<u64> {
+        futures::executor::block_on(self.inner.execute_affected(stmt))
+    }
+
+    /// Creates an interactive transaction
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn run() {
+    /// # use libsql_client::Config;
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// # db.execute("create table foo(bar text)").unwrap();
+    /// let tx = db.transaction().unwrap();
+    /// tx.execute("select * from foo").unwrap();
+    /// tx.commit();
+    /// # }
+    /// ```
+    ///
+    /// Fails with [`Error::Misuse`](crate::Error::Misuse) if the limit set with
+    /// [`Config::with_max_open_transactions()`] is reached.
+    pub fn transaction(&self) -> Result<SyncTransaction<'_>> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        SyncTransaction::new(self, id)
+    }
+
+    /// Like [`SyncClient::transaction()`], but starts the transaction with the given locking
+    /// behavior. See [`Client::transaction_with()`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// db.execute("CREATE TABLE users(name TEXT)")?;
+    /// let tx = db.transaction_with(libsql_client::TxBehavior::Exclusive)?;
+    /// tx.execute("INSERT INTO users VALUES ('John')")?;
+    /// tx.commit()?;
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn transaction_with(&self, behavior: TxBehavior) -> Result<SyncTransaction<'_>> {
+        let id = TRANSACTION_IDS.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+        SyncTransaction::begin(self, id, behavior)
+    }
+
+    /// Runs `f` in an interactive transaction, committing it if `f` succeeds
+    /// and rolling it back if it fails. See [`Client::transaction_scope()`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// db.execute("CREATE TABLE users(name TEXT)")?;
+    /// let count: i64 = db.transaction_scope(|tx| {
+    ///     tx.execute("INSERT INTO users VALUES ('John')")?;
+    ///     tx.execute("INSERT INTO users VALUES ('Jane')")?;
+    ///     tx.execute("SELECT count(*) FROM users")?.rows[0].try_get(0)
+    /// })?;
+    /// assert_eq!(count, 2);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn transaction_scope<T>(
+        &self,
+        f: impl FnOnce(&SyncTransaction<'_>) -> Result<T>,
+    ) -> Result<T> {
+        let tx = self.transaction()?;
+        match f(&tx) {
+            Ok(value) => tx.commit().map(|_| value),
+            Err(e) => {
+                tx.rollback().ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Like [`SyncClient::transaction_scope()`], but retries the whole transaction
+    /// if the database is busy. See [`Client::with_transaction()`] for details.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// db.execute("CREATE TABLE counter(value INTEGER)")?;
+    /// db.execute("INSERT INTO counter VALUES (0)")?;
+    /// let value: i64 = db.with_transaction(|tx| {
+    ///     tx.execute("UPDATE counter SET value = value + 1")?;
+    ///     tx.execute("SELECT value FROM counter")?.rows[0].try_get(0)
+    /// })?;
+    /// assert_eq!(value, 1);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn with_transaction<T>(&self, f: impl Fn(&SyncTransaction<'_>) -> Result<T>) -> Result<T> {
+        let (max_retries, mut backoff) = self.inner.transaction_retries();
+        let mut attempt = 0;
+        loop {
+            match self.transaction_scope(&f) {
+                Err(e) if attempt < max_retries && is_busy(&e) => {
+                    tracing::debug!("Transaction failed with {e}, retrying in {backoff:?}");
+                    std::thread::sleep(backoff);
+                    backoff *= 2;
+                    attempt += 1;
+                }
+                result => return result,
+            }
+        }
+    }
+
+    /// Returns the URL of the database this client points at, with credentials stripped,
+    /// or `None` if no backend is set
+    pub fn endpoint(&self) -> Option<&str> {
+        self.inner.endpoint()
+    }
+
+    /// Returns true if the client was configured with an auth token
+    pub fn has_auth(&self) -> bool {
+        self.inner.has_auth()
+    }
+
+    /// Closes the client, see [`Client::close()`]
+    pub fn close(self) -> Result<()> {
+        futures::executor::block_on(self.inner.close())
+    }
+
+    /// Returns time spent on JSON serialization, network and JSON parsing by requests
+    /// sent so far, or `None` if the client doesn't use an HTTP backend.
+    #[cfg(any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend"
+    ))]
+    pub fn http_timings(&self) -> Option<crate::http::Timings> {
+        self.inner.http_timings()
+    }
+
+    pub(crate) fn execute_in_transaction(&self, tx_id: u64, stmt: Statement) -> Result<ResultSet> {
+        futures::executor::block_on(self.inner.execute_in_transaction(tx_id, stmt))
+    }
+
+    pub(crate) fn commit_transaction(&self, tx_id: u64) -> Result<()> {
+        futures::executor::block_on(self.inner.commit_transaction(tx_id))
+    }
+
+    pub(crate) fn rollback_transaction(&self, tx_id: u64) -> Result<()> {
+        futures::executor::block_on(self.inner.rollback_transaction(tx_id))
+    }
+
+    pub(crate) fn drop_transaction(&self, tx_id: u64) {
+        self.inner.drop_transaction(tx_id)
+    }
+
+    pub(crate) fn baton(&self, tx_id: u64) -> Option<String> {
+        self.inner.baton(tx_id)
+    }
+
+    pub(crate) fn acquire_transaction_slot(&self) -> Result<()> {
+        self.inner.acquire_transaction_slot()
+    }
+
+    pub(crate) fn release_transaction_slot(&self) {
+        self.inner.release_transaction_slot()
+    }
+}
+
+/// Handling of result columns sharing a name in [`Row::value_map`](crate::Row::value_map),
+/// see [`Config::with_duplicate_columns()`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum DuplicateColumns {
+    /// Fail the statement with [`Error::Misuse`](crate::Error::Misuse)
+    Error,
+    /// Map the name to the first column with it
+    KeepFirst,
+    /// Map the name to the last column with it
+    #[default]
+    KeepLast,
+    /// Keep the first column under its name and add suffixes to the others, e.g. `id`, `id_1`, `id_2`
+    Suffix,
+    /// Qualify each column sharing a name with the table it was read from, e.g. `users.id` and
+    /// `posts.id`, leaving the bare name out so [`Row::try_column()`](crate::Row::try_column)
+    /// fails with an error listing the candidates. Only the local backend reports the tables -
+    /// other columns get suffixes like with [`DuplicateColumns::Suffix`], and so do qualified
+    /// names which still clash, e.g. in self-joins.
+    Qualify,
+}
+
+/// Description of a table column, returned by [`Client::describe()`]
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct ColumnInfo {
+    /// Name of the column
+    pub name: String,
+    /// Type the column was declared with, e.g. `INTEGER` or `VARCHAR(20)`,
+    /// or `None` if it was declared without one
+    pub decltype: Option<String>,
+    /// True if the column is declared `NOT NULL`
+    pub notnull: bool,
+    /// Position of the column in the primary key, starting at 1, or `None` if it's not part of it.
+    /// Columns of a composite primary key are numbered in the order the key lists them.
+    pub pk: Option<u32>,
+    /// SQL text of the column's default value, e.g. `'anon'` or `CURRENT_TIMESTAMP`
+    pub default: Option<String>,
+}
+
+/// Handling of invalid UTF-8 in TEXT values, see [`Config::with_text_decoding()`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TextDecoding {
+    /// Fail the statement
+    #[default]
+    Strict,
+    /// Replace invalid sequences with U+FFFD, like [`String::from_utf8_lossy()`]
+    Lossy,
+}
+
+/// Options of [`Client::batch_opts()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct BatchOpts {
+    /// Wrap the statements in `BEGIN` and `END`, see [`BatchOpts::without_transaction()`]
+    pub wrap_in_transaction: bool,
+    /// Id to tag the request with, see [`BatchOpts::with_request_id()`]
+    pub request_id: Option<String>,
+}
+
+impl BatchOpts {
+    /// Creates default options, same as [`Client::batch()`] uses
+    pub fn new() -> Self {
+        Self {
+            wrap_in_transaction: true,
+            request_id: None,
+        }
+    }
+
+    /// Tags the batch with an id, sent in the [`X-Idempotency-Key`](crate::http::REQUEST_ID_HEADER)
+    /// header by the HTTP backend, so that logs of retried requests can be correlated
+    /// and servers supporting it can skip batches they already applied.
+    ///
+    /// Reuse the same id when retrying the same batch. Other backends ignore it.
+    pub fn with_request_id(mut self, request_id: impl Into<String>) -> Self {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    /// Doesn't wrap the statements in `BEGIN` and `END`, for batches which
+    /// manage transaction control themselves, e.g. with `BEGIN IMMEDIATE` and `COMMIT`.
+    ///
+    /// Statements are then executed like in [`Client::raw_batch()`]: the first error
+    /// is returned, but statements after the failed one still run, including a final `COMMIT`.
+    pub fn without_transaction(mut self) -> Self {
+        self.wrap_in_transaction = false;
+        self
+    }
+}
+
+impl Default for BatchOpts {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Freshness of query planner statistics of a table, returned by [`Client::stats_age()`]
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct StatsAge {
+    /// Name of the table
+    pub table: String,
+    /// Number of rows recorded by the last `ANALYZE`, or `None` if it never covered the table
+    pub analyzed_rows: Option<u64>,
+    /// Current number of rows
+    pub current_rows: u64,
+}
+
+impl StatsAge {
+    /// Relative change of the number of rows since the last `ANALYZE`,
+    /// e.g. `0.5` if the table grew or shrank by half. `None` if it was never analyzed.
+    pub fn drift(&self) -> Option<f64> {
+        let analyzed = self.analyzed_rows? as f64;
+        let current = self.current_rows as f64;
+        Some((current - analyzed).abs() / analyzed.max(1.0))
+    }
+
+    /// Returns true if the table was never analyzed, or its number of rows
+    /// changed by more than `threshold` (relative, e.g. `0.2` for 20%) since.
+    pub fn is_stale(&self, threshold: f64) -> bool {
+        self.drift().is_none_or(|drift| drift > threshold)
+    }
+}
+
+/// Backend used to communicate with the database.
+///
+/// If [`Config::backend`] is not set, the backend is guessed from the URL scheme:
+/// * `file` - [`Backend::Local`]
+/// * `ws`, `wss` - [`Backend::Hrana`]
+/// * `workers` - [`Backend::Workers`]
+/// * `spin` - [`Backend::Spin`]
+/// * `fetch` - [`Backend::Fetch`]
+/// * `http`, `https`, `libsql` - the first enabled of [`Backend::Reqwest`], [`Backend::Workers`],
+///   [`Backend::Spin`] and [`Backend::Fetch`], in that order. Set the backend explicitly if more
+///   than one of `reqwest_backend`, `workers_backend`, `spin_backend` and `fetch_backend`
+///   features is enabled.
+///
+/// A `libsql://` URL is rewritten to `wss://` for [`Backend::Hrana`], and to `https://` otherwise,
+/// or to `ws://` and `http://` if TLS is disabled with [`Config::with_tls()`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Backend {
+    /// Local database file, requires `local_backend` feature
+    Local,
+    /// HTTP via reqwest, requires `reqwest_backend` feature
+    Reqwest,
+    /// HTTP via Cloudflare Workers fetch, requires `workers_backend` feature
+    Workers,
+    /// HTTP via Spin SDK, requires `spin_backend` feature
+    Spin,
+    /// HTTP via the JavaScript `fetch` API, e.g. in browsers, requires `fetch_backend` feature
+    Fetch,
+    /// Hrana over WebSockets, requires `hrana_backend` feature
+    Hrana,
+}
+
+impl Backend {
+    /// Guesses the backend from the URL scheme, see [Backend] for the rules.
+    #[allow(unreachable_code)]
+    pub fn from_scheme(scheme: &str) -> Result<Self> {
+        Ok(match scheme {
+            "file" => Backend::Local,
+            "ws" | "wss" => Backend::Hrana,
+            "workers" => Backend::Workers,
+            "spin" => Backend::Spin,
+            "fetch" => Backend::Fetch,
+            "http" | "https" | "libsql" => {
+                #[cfg(feature = "reqwest_backend")]
+                return Ok(Backend::Reqwest);
+                #[cfg(feature = "workers_backend")]
+                return Ok(Backend::Workers);
+                #[cfg(feature = "spin_backend")]
+                return Ok(Backend::Spin);
+                #[cfg(feature = "fetch_backend")]
+                return Ok(Backend::Fetch);
+                anyhow::bail!("No HTTP backend enabled for scheme {scheme}. Enable one of reqwest_backend, workers_backend, spin_backend or fetch_backend features")
+            }
+            _ => anyhow::bail!("Unknown scheme: {scheme}. Make sure your backend exists and is enabled with its feature flag"),
+        })
+    }
+}
+
+/// Authentication token, e.g. a JWT, which is redacted as `***` when formatted
+/// with `Debug` or `Display`, so it doesn't leak into logs and error messages.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::AuthToken;
+///
+/// let token = AuthToken::new("secret");
+/// assert_eq!(format!("{token:?}"), "***");
+/// assert_eq!(token.expose(), "secret");
+/// ```
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct AuthToken(String);
+
+impl AuthToken {
+    /// Wraps a token
+    pub fn new(token: impl Into<String>) -> Self {
+        Self(token.into())
+    }
+
+    /// Returns the token itself, e.g. to put it into a request header
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl From<String> for AuthToken {
+    fn from(token: String) -> Self {
+        Self(token)
+    }
+}
+
+impl From<&str> for AuthToken {
+    fn from(token: &str) -> Self {
+        Self(token.to_string())
+    }
+}
+
+impl std::fmt::Debug for AuthToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+impl std::fmt::Display for AuthToken {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("***")
+    }
+}
+
+/// Configuration for the database client
+#[derive(Debug)]
+pub struct Config {
+    pub url: url::Url,
+    /// Authentication token, see [`Config::with_auth_token()`]
+    pub auth_token: Option<AuthToken>,
+    /// Explicit backend choice, takes precedence over guessing from the URL scheme
+    pub backend: Option<Backend>,
+    /// Capacity and time-to-live of the query cache, see [`Config::with_query_cache()`]
+    pub query_cache: Option<(usize, std::time::Duration)>,
+    /// Maximum number of concurrently open interactive transactions, see
+    /// [`Config::with_max_open_transactions()`]
+    pub max_open_transactions: Option<usize>,
+    /// Number of connections in the pool, see [`Config::with_pool_size()`]
+    pub pool_size: Option<usize>,
+    /// Retries and initial backoff of [`Client::with_transaction()`],
+    /// see [`Config::with_transaction_retries()`]
+    pub transaction_retries: Option<(usize, std::time::Duration)>,
+    /// Maximum size of a text or blob parameter in bytes, see [`Config::with_max_param_size()`]
+    pub max_param_size: Option<usize>,
+    /// Path of the HTTP pipeline endpoint, see [`Config::with_pipeline_path()`]
+    pub pipeline_path: Option<String>,
+    /// Maximum size of an HTTP request body, see [`Config::with_max_request_bytes()`]
+    pub max_request_bytes: Option<usize>,
+    /// Handling of result columns sharing a name, see [`Config::with_duplicate_columns()`]
+    pub duplicate_columns: DuplicateColumns,
+    /// Handling of invalid UTF-8 in TEXT values, see [`Config::with_text_decoding()`]
+    pub text_decoding: TextDecoding,
+    /// Page cache size of local databases, see [`Config::with_cache_size()`]
+    pub cache_size: Option<i64>,
+    /// Collection of execution statistics, see [`Config::with_metrics()`]
+    pub metrics: bool,
+    /// Opening local databases in read-only mode, see [`Config::with_read_only()`]
+    pub read_only: bool,
+    /// Connecting to hrana servers on first use, see [`Config::with_lazy_connect()`]
+    pub lazy_connect: bool,
+    /// sqld namespace to route requests to, see [`Config::with_namespace()`]
+    pub namespace: Option<String>,
+    /// How long idle pooled HTTP connections are kept, see [`Config::with_pool_idle_timeout()`]
+    pub pool_idle_timeout: Option<std::time::Duration>,
+    /// Interval of TCP keep-alive probes, see [`Config::with_tcp_keepalive()`]
+    pub tcp_keepalive: Option<std::time::Duration>,
+    /// Whether `libsql://` URLs are served over TLS, see [`Config::with_tls()`]
+    pub tls: bool,
+    /// Extra PEM-encoded root certificates to trust, see [`Config::with_root_certificate()`]
+    pub tls_root_certificates: Vec<Vec<u8>>,
+    /// Whether query spans carry the SQL text, see [`Config::with_span_sql()`]
+    pub span_sql: bool,
+    /// Disables TLS certificate validation, see [`Config::with_danger_accept_invalid_certs()`]
+    #[cfg(feature = "danger_accept_invalid_certs")]
+    pub danger_accept_invalid_certs: bool,
+}
+
+impl Config {
+    /// Create a new [Config]
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?;
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn new<T: TryInto<url::Url>>(url: T) -> Result<Self>
+    where
+        <T as TryInto<url::Url>>::Error: std::fmt::Display,
+    {
+        Ok(Self {
+            url: url
+                .try_into()
+                .map_err(|e| anyhow::anyhow!("Failed to parse url: {}", e))?,
+            auth_token: None,
+            backend: None,
+            query_cache: None,
+            max_open_transactions: None,
+            pool_size: None,
+            transaction_retries: None,
+            max_param_size: None,
+            pipeline_path: None,
+            max_request_bytes: None,
+            duplicate_columns: DuplicateColumns::default(),
+            text_decoding: TextDecoding::default(),
+            cache_size: None,
+            metrics: false,
+            read_only: false,
+            lazy_connect: false,
+            namespace: None,
+            pool_idle_timeout: None,
+            tcp_keepalive: None,
+            tls: true,
+            tls_root_certificates: vec![],
+            span_sql: false,
+            #[cfg(feature = "danger_accept_invalid_certs")]
+            danger_accept_invalid_certs: false,
+        })
+    }
+
+    /// Adds an authentication token to config.
+    /// The token is redacted when the config is formatted with `Debug`, see [AuthToken].
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("https://example.com/db")?.with_auth_token("secret");
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_auth_token(mut self, token: impl Into<String>) -> Self {
+        self.auth_token = Some(AuthToken::new(token));
+        self
+    }
+
+    /// Selects the backend explicitly instead of guessing it from the URL scheme
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::{Backend, Config};
+    /// let config = Config::new("https://example.com/db")?.with_backend(Backend::Reqwest);
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_backend(mut self, backend: Backend) -> Self {
+        self.backend = Some(backend);
+        self
+    }
+
+    /// Enables a client-side cache for results of read queries.
+    ///
+    /// Results of statements starting with `SELECT` are memoized by their SQL text and arguments,
+    /// for up to `ttl` and at most `capacity` entries, evicting the oldest ones first.
+    /// Any other statement executed through the client, as well as any batch or the end of a transaction,
+    /// is assumed to be a write and clears the whole cache. Reads within interactive transactions
+    /// are never served from the cache.
+    ///
+    /// The cache trades freshness for latency: writes made by other clients (or processes) are not
+    /// detected, so cached results can be stale for up to `ttl`. Only use it for reads that can
+    /// tolerate that, and keep in mind that non-deterministic queries like `SELECT random()`
+    /// are cached too. It is not supported on `wasm32-unknown-unknown`, which has no system clock:
+    /// creating a client with it fails there with [`Error::Misuse`](crate::Error::Misuse).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?
+    ///     .with_query_cache(128, std::time::Duration::from_secs(5));
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_query_cache(mut self, capacity: usize, ttl: std::time::Duration) -> Self {
+        self.query_cache = Some((capacity, ttl));
+        self
+    }
+
+    /// Adds a PEM-encoded root certificate to trust on top of the default ones,
+    /// e.g. the certificate of a private CA.
+    ///
+    /// Only applies to [`Backend::Reqwest`].
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let ca = std::fs::read("/etc/ssl/private-ca.pem")?;
+    /// let config = Config::new("https://db.internal")?.with_root_certificate(ca);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_root_certificate(mut self, pem: impl Into<Vec<u8>>) -> Self {
+        self.tls_root_certificates.push(pem.into());
+        self
+    }
+
+    /// Disables validation of TLS certificates - any certificate, including self-signed
+    /// and expired ones, will be trusted.
+    ///
+    /// **This is dangerous**: it makes the connection vulnerable to man-in-the-middle attacks.
+    /// Only use it for local testing, and prefer [`Config::with_root_certificate()`] otherwise.
+    /// Requires the `danger_accept_invalid_certs` feature and only applies to [`Backend::Reqwest`].
+    #[cfg(feature = "danger_accept_invalid_certs")]
+    pub fn with_danger_accept_invalid_certs(mut self) -> Self {
+        self.danger_accept_invalid_certs = true;
+        self
+    }
+
+    /// Limits the number of interactive transactions that can be open at the same time.
+    ///
+    /// Each open transaction holds a stream on the server, so the limit guards against
+    /// leaking them. A transaction is counted from [`Client::transaction()`] until it's
+    /// committed, rolled back or dropped; going over the limit fails with
+    /// [`Error::Misuse`](crate::Error::Misuse).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?.with_max_open_transactions(1);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// let tx = db.transaction().await?;
+    /// assert!(db.transaction().await.is_err());
+    /// tx.rollback().await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_open_transactions(mut self, max: usize) -> Self {
+        self.max_open_transactions = Some(max);
+        self
+    }
+
+    /// Opens a pool of `size` WebSocket connections instead of a single one.
+    ///
+    /// Statements and batches are spread over the connections round-robin, so that concurrent
+    /// queries don't queue up behind each other on one socket. Connections that died are
+    /// reconnected lazily, the next time they're picked. An interactive transaction stays on the
+    /// connection it started on until it's committed, rolled back or dropped.
+    ///
+    /// Only applies to [`Backend::Hrana`], and a size of 0 is treated as 1.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("ws://localhost:8080")?.with_pool_size(4);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pool_size(mut self, size: usize) -> Self {
+        self.pool_size = Some(size);
+        self
+    }
+
+    /// Configures how many times [`Client::with_transaction()`] retries a transaction
+    /// that failed because the database was busy, and how long it waits before the first retry.
+    /// The wait doubles with each following retry.
+    ///
+    /// Defaults to 5 retries, starting at 10 milliseconds. On `wasm32-unknown-unknown`
+    /// there's no runtime-agnostic way to wait, so retries happen right away.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?
+    ///     .with_transaction_retries(10, std::time::Duration::from_millis(50));
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_transaction_retries(
+        mut self,
+        max_retries: usize,
+        backoff: std::time::Duration,
+    ) -> Self {
+        self.transaction_retries = Some((max_retries, backoff));
+        self
+    }
+
+    /// Rejects statements with a text or blob parameter larger than `bytes` before sending them,
+    /// failing fast with [`Error::Misuse`](crate::Error::Misuse) instead of waiting for the server
+    /// to reject the request.
+    ///
+    /// Only applies to the remote backends, i.e. HTTP and Hrana.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("https://example.com/db")?.with_max_param_size(1024 * 1024);
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_param_size(mut self, bytes: usize) -> Self {
+        self.max_param_size = Some(bytes);
+        self
+    }
+
+    /// Limits the size of HTTP request bodies to `bytes`, 10 MiB by default
+    /// ([`http::DEFAULT_MAX_REQUEST_BYTES`](crate::http::DEFAULT_MAX_REQUEST_BYTES)).
+    ///
+    /// A [`Client::raw_batch()`] or a batch without a transaction whose request would be larger
+    /// is split into several requests, each executed on its own stream. Batches which can't be
+    /// split, i.e. transactional ones and ones with transaction control statements, fail with
+    /// [`Error::RequestTooLarge`](crate::Error::RequestTooLarge) instead of an opaque HTTP error
+    /// from the server. Only applies to the HTTP backends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("https://example.com/db")?.with_max_request_bytes(1024 * 1024);
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_max_request_bytes(mut self, bytes: usize) -> Self {
+        self.max_request_bytes = Some(bytes);
+        self
+    }
+
+    /// Overrides the path of the HTTP pipeline endpoint, `v2/pipeline` by default,
+    /// e.g. for sqld deployments behind a reverse proxy expecting `/api/v2/pipeline`.
+    ///
+    /// The path is relative to the database URL, which may have a path prefix of its own,
+    /// and it's joined with exactly one slash. Only applies to the HTTP backends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// // Queries are sent to https://example.com/db/api/v2/pipeline
+    /// let config = Config::new("https://example.com/db")?.with_pipeline_path("api/v2/pipeline");
+    /// let db = libsql_client::Client::from_config(config).await.unwrap();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pipeline_path(mut self, path: impl Into<String>) -> Self {
+        self.pipeline_path = Some(path.into());
+        self
+    }
+
+    /// Selects how [`Row::value_map`](crate::Row::value_map) handles result columns sharing a name,
+    /// e.g. both `id` columns of `SELECT a.id, b.id FROM a JOIN b ...`.
+    ///
+    /// By default the last such column wins. Use [`DuplicateColumns::Error`] to catch queries
+    /// with accidentally duplicated columns. Positional access via [`Row::values`](crate::Row::values)
+    /// and [`ResultSet::columns`](crate::ResultSet::columns) are not affected.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::{Config, DuplicateColumns};
+    /// let config = Config::new("file:////tmp/example.db")?
+    ///     .with_duplicate_columns(DuplicateColumns::Suffix);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// let rs = db.execute("SELECT 1 AS id, 2 AS id").await?;
+    /// assert_eq!(rs.rows[0].try_column::<i64>("id")?, 1);
+    /// assert_eq!(rs.rows[0].try_column::<i64>("id_1")?, 2);
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub fn with_duplicate_columns(mut self, policy: DuplicateColumns) -> Self {
+        self.duplicate_columns = policy;
+        self
+    }
+
+    /// Selects whether TEXT values containing invalid UTF-8, e.g. left by a buggy writer,
+    /// fail the statement or have the invalid sequences replaced with U+FFFD.
+    ///
+    /// Applies to the local backend, which reads raw bytes from the database.
+    /// Remote backends receive text in JSON, which can only carry valid UTF-8,
+    /// so invalid sequences are already replaced by the server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::{Config, TextDecoding};
+    /// let config = Config::new("file:////tmp/example.db")?
+    ///     .with_text_decoding(TextDecoding::Lossy);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// let rs = db.execute("SELECT CAST(x'6869ff' AS TEXT)").await?;
+    /// assert_eq!(rs.rows[0].try_get::<&str>(0)?, "hi\u{FFFD}");
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub fn with_text_decoding(mut self, decoding: TextDecoding) -> Self {
+        self.text_decoding = decoding;
+        self
+    }
+
+    /// Sets the page cache size of a local database when it's opened.
+    ///
+    /// A positive size is a number of pages, a negative one an amount of memory in KiB -
+    /// e.g. `-65536` is a 64 MiB cache. See [`local::Client::set_cache_size()`](crate::local::Client::set_cache_size)
+    /// for details. Ignored by remote backends.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?.with_cache_size(-64 * 1024);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub fn with_cache_size(mut self, size: i64) -> Self {
+        self.cache_size = Some(size);
+        self
+    }
+
+    /// Enables collecting execution statistics of statements into [`ResultSet::stats`].
+    ///
+    /// The time it takes to execute each statement is measured for all backends, and the local
+    /// backend also reports the number of rows read and written. Statistics are collected
+    /// for [`Client::execute()`] and statements executed in transactions, not for batches.
+    /// Results served from the [query cache](Config::with_query_cache()) keep the statistics
+    /// of the execution which filled the cache.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("file:////tmp/example.db")?.with_metrics(true);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// let rs = db.execute("SELECT 1").await?;
+    /// if let Some(stats) = rs.stats {
+    ///     println!("Took {:?}", stats.elapsed);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub fn with_metrics(mut self, enabled: bool) -> Self {
+        self.metrics = enabled;
+        self
+    }
+
+    /// Opens local databases in read-only mode, so that statements which would write
+    /// fail with `SQLITE_READONLY`, e.g. for a dashboard sharing the database file
+    /// with the application. See [`local::Client::open_read_only()`](crate::local::Client::open_read_only)
+    /// for details. Ignored by remote backends - use a read-only auth token for them instead.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// # libsql_client::Client::from_config(Config::new("file:////tmp/example.db")?).await?;
+    /// let config = Config::new("file:////tmp/example.db")?.with_read_only(true);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// assert!(db.execute("CREATE TABLE dashboard(x)").await.is_err());
+    /// # Ok(())
+    /// # }
+    /// # futures::executor::block_on(f()).unwrap();
+    /// ```
+    pub fn with_read_only(mut self, enabled: bool) -> Self {
+        self.read_only = enabled;
+        self
+    }
+
+    /// Defers opening the WebSocket connections of the hrana backend until they're first used,
+    /// so that clients can be created at startup without network access, e.g. during a
+    /// serverless cold start. Connection errors are then returned by the first query.
+    /// See [`hrana::Client::lazy()`](crate::hrana::Client::lazy) for details.
+    /// Other backends don't connect upfront anyway and ignore it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("ws://localhost:8080")?.with_lazy_connect(true);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_lazy_connect(mut self, enabled: bool) -> Self {
+        self.lazy_connect = enabled;
+        self
+    }
+
+    /// Routes requests to the given namespace of a sqld instance hosting several databases.
+    ///
+    /// sqld picks the namespace from the first label of the `Host` header, so the namespace is
+    /// prepended to the host of the URL: `https://db.example.com` with namespace `customer-123`
+    /// connects to `https://customer-123.db.example.com`. The server's DNS needs to resolve
+    /// these subdomains, e.g. with a wildcard record. Namespaces may contain ASCII letters, digits,
+    /// `-` and `_`, and the URL's host must be a domain name rather than an IP address; otherwise
+    /// creating the client fails with [`Error::Misuse`](crate::Error::Misuse).
+    /// Applies to the HTTP and Hrana backends, local databases ignore it.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// let config = Config::new("https://db.example.com")?.with_namespace("customer-123");
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_namespace(mut self, namespace: impl Into<String>) -> Self {
+        self.namespace = Some(namespace.into());
+        self
+    }
+
+    /// Closes pooled HTTP connections which stayed idle for longer than `timeout`.
+    ///
+    /// Load balancers and NAT gateways often drop connections idle for a minute or so without
+    /// telling either side, and the first request sent over such a connection fails.
+    /// Setting a timeout below theirs, e.g. 30 seconds, makes the client open a fresh
+    /// connection instead. Defaults to 90 seconds. Only applies to [`Backend::Reqwest`].
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// use std::time::Duration;
+    ///
+    /// let config = Config::new("https://db.example.com")?
+    ///     .with_pool_idle_timeout(Duration::from_secs(30))
+    ///     .with_tcp_keepalive(Duration::from_secs(15));
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_pool_idle_timeout(mut self, timeout: std::time::Duration) -> Self {
+        self.pool_idle_timeout = Some(timeout);
+        self
+    }
+
+    /// Enables TCP keep-alive on HTTP connections, probing them after `interval` of inactivity.
+    ///
+    /// Keep-alive traffic keeps intermediaries from considering pooled connections idle,
+    /// and detects the ones which died. It's disabled by default; an interval of 15 to 60
+    /// seconds suits most setups. Only applies to [`Backend::Reqwest`],
+    /// see [`Config::with_pool_idle_timeout()`] for an example.
+    pub fn with_tcp_keepalive(mut self, interval: std::time::Duration) -> Self {
+        self.tcp_keepalive = Some(interval);
+        self
+    }
+
+    /// Controls whether a `libsql://` URL is rewritten to a TLS scheme, `https://` or `wss://`,
+    /// or to a plaintext one, `http://` or `ws://`.
+    ///
+    /// Defaults to true. Disable it for a local sqld started without TLS, e.g. in development.
+    /// URLs with an explicit `http`, `https`, `ws` or `wss` scheme are always used as they are.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// // Connects to http://127.0.0.1:8080
+    /// let config = Config::new("libsql://127.0.0.1:8080")?.with_tls(false);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_tls(mut self, enabled: bool) -> Self {
+        self.tls = enabled;
+        self
+    }
+
+    // The URL of remote databases, with the namespace prepended to its host if one is set
+    #[cfg(any(
+        feature = "hrana_backend",
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend"
+    ))]
+    pub(crate) fn routed_url(&self) -> Result<url::Url> {
+        let Some(namespace) = &self.namespace else {
+            return Ok(self.url.clone());
+        };
+        let misuse = |reason: String| crate::Error::Misuse(reason).into();
+        let valid = !namespace.is_empty()
+            && namespace
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_');
+        if !valid {
+            return Err(misuse(format!("Invalid namespace: {namespace:?}")));
+        }
+        let host = match self.url.host() {
+            Some(url::Host::Domain(host)) => host,
+            _ => {
+                return Err(misuse(format!(
+                    "Namespaces require a URL with a domain name, not {}",
+                    self.url
+                )))
+            }
+        };
+        let mut url = self.url.clone();
+        url.set_host(Some(&format!("{namespace}.{host}")))?;
+        Ok(url)
+    }
+
+    /// Controls whether the `query` tracing spans around executed statements carry their SQL.
+    ///
+    /// Every backend opens an `INFO` span named `query` for each statement or batch it executes,
+    /// with the `backend`, the number of `statements` and bound `args`, and once finished,
+    /// the number of returned `rows` or the `error`, and the `elapsed` time. The SQL text is
+    /// left out by default, since it may contain sensitive data inlined as literals -
+    /// enable it to record it in the `sql` field. Bound arguments are never recorded.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() -> anyhow::Result<()> {
+    /// # use libsql_client::Config;
+    /// tracing_subscriber::fmt::init();
+    /// let config = Config::new("file:////tmp/example.db")?.with_span_sql(true);
+    /// let db = libsql_client::Client::from_config(config).await?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_span_sql(mut self, enabled: bool) -> Self {
+        self.span_sql = enabled;
+        self
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use super::*;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    #[test]
+    fn auth_token_redacted() {
+        let config = Config::new("https://db.example.com")
+            .unwrap()
+            .with_auth_token("eyJhbGciOiJFZERTQSJ9.secret");
+        let debug = format!("{config:?}");
+        assert!(!debug.contains("secret"), "{debug}");
+        assert!(debug.contains("auth_token: Some(***)"), "{debug}");
+        assert_eq!(
+            config.auth_token.unwrap().expose(),
+            "eyJhbGciOiJFZERTQSJ9.secret"
+        );
+    }
+
+    #[test]
+    fn execute_script() {
+        let db = SyncClient::in_memory().unwrap();
+        let results = db
+            .execute_script(
+                "CREATE TABLE t(x TEXT);
+                 -- seed data; with a comment
+                 INSERT INTO t VALUES ('a;b'), ('c');
+                 SELECT x FROM t ORDER BY x;",
+            )
+            .unwrap();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[1].rows_affected, 2);
+        assert_eq!(results[2].rows[0].try_get::<&str>(0).unwrap(), "a;b");
+
+        let err = db
+            .execute_script("INSERT INTO t VALUES ('d'); INSERT INTO missing VALUES (1); SELECT 1")
+            .unwrap_err();
+        assert!(err.to_string().contains("Statement 1"), "{err}");
+        assert!(format!("{err:#}").contains("missing"), "{err:#}");
+        assert_eq!(db.count("t", None).unwrap(), 3);
+
+        let results = db
+            .execute_script(
+                "-- log every insert
+                 CREATE TRIGGER log AFTER INSERT ON t BEGIN
+                   INSERT INTO t VALUES ('logged');
+                 END;
+                 -- done",
+            )
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        db.execute("INSERT INTO t VALUES ('e')").unwrap();
+        assert_eq!(db.count("t", None).unwrap(), 5);
+    }
+
+    #[test]
+    fn batch_inside_open_transaction() {
+        futures::executor::block_on(async {
+            let db = Client::in_memory().unwrap();
+            db.execute("CREATE TABLE t(x)").await.unwrap();
+            let tx = db.transaction().await.unwrap();
+            tx.execute("INSERT INTO t VALUES (1)").await.unwrap();
+            // The batch's BEGIN fails, which must not roll back the open transaction
+            let err = db.batch(["SELECT 1"]).await.unwrap_err();
+            assert!(err.to_string().contains("within a transaction"), "{err}");
+            tx.commit().await.unwrap();
+            assert_eq!(db.count("t", None).await.unwrap(), 1);
+        })
+    }
+
+    #[test]
+    fn analyze_qualified_names() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute(r#"CREATE TABLE "odd ""name"""(x)"#).unwrap();
+        db.execute("INSERT INTO users(name) VALUES ('a'), ('b')")
+            .unwrap();
+        for table in ["main.users", "users", r#"odd "name""#, "main"] {
+            db.analyze(Some(table))
+                .unwrap_or_else(|e| panic!("{table}: {e}"));
+        }
+        assert!(db.analyze(Some("main.missing")).is_err());
+
+        let stats = db.stats_age().unwrap();
+        assert_eq!(stats[0].table, r#"odd "name""#);
+        assert_eq!(stats[1].table, "users");
+        assert_eq!(stats[1].analyzed_rows, Some(2));
+    }
+
+    #[test]
+    fn user_and_schema_version() {
+        let db = SyncClient::in_memory().unwrap();
+        assert_eq!(db.user_version().unwrap(), 0);
+        for version in [7, -1, i32::MAX] {
+            db.set_user_version(version).unwrap();
+            assert_eq!(db.user_version().unwrap(), version as i64);
+        }
+
+        let before = db.schema_version().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        assert!(db.schema_version().unwrap() > before);
+        // Setting the user version doesn't change the schema
+        let before = db.schema_version().unwrap();
+        db.set_user_version(1).unwrap();
+        assert_eq!(db.schema_version().unwrap(), before);
+    }
+
+    #[test]
+    fn libsql_scheme_rewrite() {
+        let resolved = |config: Config| {
+            let mut config = config;
+            let backend = Client::resolve_backend(&mut config).unwrap();
+            (backend, config.url.to_string())
+        };
+        let config = || Config::new("libsql://db.example.com").unwrap();
+        assert_eq!(
+            resolved(config()),
+            (Backend::Reqwest, "https://db.example.com/".to_string())
+        );
+        assert_eq!(
+            resolved(config().with_tls(false)),
+            (Backend::Reqwest, "http://db.example.com/".to_string())
+        );
+        assert_eq!(
+            resolved(config().with_backend(Backend::Hrana)).1,
+            "wss://db.example.com/"
+        );
+        assert_eq!(
+            resolved(config().with_backend(Backend::Hrana).with_tls(false)).1,
+            "ws://db.example.com/"
+        );
+        // Explicit schemes are left alone
+        for url in ["http://127.0.0.1:8080/", "https://db.example.com/"] {
+            let config = Config::new(url).unwrap().with_tls(false);
+            assert_eq!(resolved(config).1, url);
+        }
+    }
+
+    #[test]
+    fn batch_with_savepoints() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        let res = db
+            .batch([
+                "INSERT INTO t VALUES (1)",
+                "SAVEPOINT sp",
+                "INSERT INTO t VALUES (2)",
+                "ROLLBACK TO sp",
+                "RELEASE sp",
+                "INSERT INTO t VALUES (3)",
+            ])
+            .unwrap();
+        assert_eq!(res.len(), 6);
+        let rs = db.execute("SELECT group_concat(x) FROM t").unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "1,3");
+    }
+
+    #[test]
+    fn batch_with_explicit_transaction() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        let stmts = ["BEGIN IMMEDIATE", "INSERT INTO t VALUES (1)", "COMMIT"];
+
+        let err = db.batch(stmts).unwrap_err();
+        assert!(err.to_string().contains("within a transaction"), "{err}");
+        let rs = db.execute("SELECT count(*) FROM t").unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 0);
+
+        let res = db
+            .batch_opts(stmts, BatchOpts::new().without_transaction())
+            .unwrap();
+        assert_eq!(res.len(), 3);
+        let rs = db.execute("SELECT count(*) FROM t").unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn query_one_and_opt() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct User {
+            id: i64,
+            name: String,
+        }
+
+        let db = SyncClient::in_memory().unwrap();
+        db.batch([
+            "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)",
+            "INSERT INTO users(name) VALUES ('John'), ('Jane')",
+        ])
+        .unwrap();
+
+        let none = "SELECT * FROM users WHERE id = 3";
+        assert_eq!(db.query_opt::<User>(none).unwrap(), None);
+        let err = db.query_one::<User>(none).unwrap_err();
+        assert!(err.to_string().contains("no rows"), "{err}");
+
+        let one = "SELECT * FROM users WHERE id = 2";
+        let jane = User {
+            id: 2,
+            name: "Jane".to_string(),
+        };
+        assert_eq!(db.query_opt::<User>(one).unwrap().as_ref(), Some(&jane));
+        assert_eq!(db.query_one::<User>(one).unwrap(), jane);
+
+        let many = "SELECT * FROM users";
+        let err = db.query_opt::<User>(many).unwrap_err();
+        assert!(err.to_string().contains("2 rows"), "{err}");
+        assert!(db.query_one::<User>(many).is_err());
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn duplicate_columns() {
+        let query = |policy| {
+            let config = Config::new("file:////tmp/duplicate_columns.db")
+                .unwrap()
+                .with_duplicate_columns(policy);
+            let db = futures::executor::block_on(Client::from_config(config)).unwrap();
+            futures::executor::block_on(db.execute("SELECT 1 AS id, 2 AS id, 3 AS id_1"))
+        };
+
+        let rs = query(DuplicateColumns::KeepLast).unwrap();
+        assert_eq!(rs.rows[0].try_column::<i64>("id").unwrap(), 2);
+        let rs = query(DuplicateColumns::KeepFirst).unwrap();
+        assert_eq!(rs.rows[0].try_column::<i64>("id").unwrap(), 1);
+        assert_eq!(rs.columns, ["id", "id", "id_1"]);
+
+        let rs = query(DuplicateColumns::Suffix).unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_column::<i64>("id").unwrap(), 1);
+        assert_eq!(row.try_column::<i64>("id_1").unwrap(), 3);
+        assert_eq!(row.try_column::<i64>("id_2").unwrap(), 2);
+        assert_eq!(row.values.len(), 3);
+
+        let err = query(DuplicateColumns::Error).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(crate::Error::Misuse(msg)) if msg.contains("id")),
+            "{err}"
+        );
+
+        // Without a table to qualify them with, columns get suffixes
+        let rs = query(DuplicateColumns::Qualify).unwrap();
+        assert_eq!(rs.rows[0].try_column::<i64>("id_2").unwrap(), 2);
+    }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn qualified_duplicate_columns() {
+        let config = Config::new("file:////tmp/qualified_duplicate_columns.db")
+            .unwrap()
+            .with_duplicate_columns(DuplicateColumns::Qualify);
+        let db = SyncClient::from_config(config).unwrap();
+        db.batch([
+            "DROP TABLE IF EXISTS users",
+            "DROP TABLE IF EXISTS posts",
+            "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT, manager INTEGER)",
+            "CREATE TABLE posts(id INTEGER PRIMARY KEY, author INTEGER, title TEXT)",
+            "INSERT INTO users VALUES (1, 'Ann', NULL), (2, 'Bob', 1)",
+            "INSERT INTO posts VALUES (10, 2, 'Hello')",
+        ])
+        .unwrap();
+
+        let rs = db
+            .execute("SELECT * FROM users JOIN posts ON posts.author = users.id")
+            .unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_column::<i64>("users.id").unwrap(), 2);
+        assert_eq!(row.try_column::<i64>("posts.id").unwrap(), 10);
+        assert_eq!(row.try_column::<&str>("title").unwrap(), "Hello");
+        let err = row.try_column::<i64>("id").unwrap_err().to_string();
+        assert!(
+            err.contains("ambiguous") && err.contains("`users.id`") && err.contains("`posts.id`"),
+            "{err}"
+        );
+        assert_eq!(row.values.len(), 6);
+
+        // Self-joins read both columns from the same table
+        let rs = db
+            .execute("SELECT * FROM users AS e JOIN users AS m ON e.manager = m.id")
+            .unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_column::<&str>("users.name").unwrap(), "Bob");
+        assert_eq!(row.try_column::<&str>("users.name_1").unwrap(), "Ann");
+        assert_eq!(row.try_column::<i64>("users.id_1").unwrap(), 1);
+        assert!(row.try_column::<i64>("name").is_err());
+    }
+
+    #[test]
+    fn execute_affected_invalidates_cache() {
+        let config = Config::new("file:////tmp/execute_affected.db")
+            .unwrap()
+            .with_query_cache(16, std::time::Duration::from_secs(60));
+        let db = SyncClient::from_config(config).unwrap();
+        db.execute("DROP TABLE IF EXISTS t").unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        let count = || db.count("t", None).unwrap();
+        assert_eq!(count(), 0);
+        assert_eq!(
+            db.execute_affected("INSERT INTO t VALUES (1), (2) RETURNING x")
+                .unwrap(),
+            2
+        );
+        assert_eq!(count(), 2);
+    }
+
+    #[test]
+    fn query_cache_keys_on_arg_types() {
+        let config = Config::new("file:////tmp/query_cache_types.db")
+            .unwrap()
+            .with_query_cache(16, std::time::Duration::from_secs(60));
+        let db = SyncClient::from_config(config).unwrap();
+        let type_of = |arg: proto::Value| {
+            let rs = db
+                .execute(Statement::with_args("SELECT typeof(?)", &[arg]))
+                .unwrap();
+            rs.rows[0].try_get::<&str>(0).unwrap().to_string()
+        };
+        assert_eq!(type_of(proto::Value::from(1)), "integer");
+        assert_eq!(type_of(proto::Value::from("1")), "text");
+        assert_eq!(type_of(proto::Value::from(1)), "integer");
+    }
+
+    #[test]
+    fn describe_composite_primary_key() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE memberships(
+                note,
+                user_id INTEGER NOT NULL,
+                joined TEXT DEFAULT CURRENT_TIMESTAMP,
+                group_id INTEGER,
+                PRIMARY KEY (group_id, user_id)
+            )",
+        )
+        .unwrap();
+        let columns = db.describe("memberships").unwrap();
+        let names: Vec<&str> = columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(names, ["note", "user_id", "joined", "group_id"]);
+        let pks: Vec<Option<u32>> = columns.iter().map(|c| c.pk).collect();
+        assert_eq!(pks, [None, Some(2), None, Some(1)]);
+        assert_eq!(
+            columns[1],
+            ColumnInfo {
+                name: "user_id".into(),
+                decltype: Some("INTEGER".into()),
+                notnull: true,
+                pk: Some(2),
+                default: None,
+            }
+        );
+        assert_eq!(columns[0].decltype, None);
+        assert!(!columns[3].notnull);
+        assert_eq!(columns[2].default.as_deref(), Some("CURRENT_TIMESTAMP"));
+
+        assert!(db.describe("missing").is_err());
+    }
+
+    #[test]
+    fn dump_table_roundtrip() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE \"odd \"\"name\"(i, f, t, b)")
+            .unwrap();
+        db.execute(
+            "INSERT INTO \"odd \"\"name\" VALUES (1, 2.0, 'a''b\nc', x'00ff'), (NULL, 0.1, '', NULL)",
+        )
+        .unwrap();
+        let dump = db.dump_table("odd \"name").unwrap();
+        // Text may contain newlines, so count statements by their terminators
+        assert_eq!(dump.matches(";\n").count(), 2);
+
+        let restored = SyncClient::in_memory().unwrap();
+        restored
+            .execute("CREATE TABLE \"odd \"\"name\"(i, f, t, b)")
+            .unwrap();
+        restored
+            .batch(dump.split_terminator(";\n").map(|stmt| stmt.to_string()))
+            .unwrap();
+        let query = "SELECT i, typeof(f), f, t, hex(b) FROM \"odd \"\"name\"";
+        let expected = db.execute(query).unwrap();
+        let actual = restored.execute(query).unwrap();
+        let values = |rs: ResultSet| {
+            format!(
+                "{:?}",
+                rs.rows.into_iter().map(|r| r.values).collect::<Vec<_>>()
+            )
+        };
+        assert_eq!(values(actual), values(expected));
+        assert!(db.dump_table("missing").is_err());
+
+        db.execute("CREATE TABLE users(name)").unwrap();
+        db.execute("INSERT INTO users VALUES ('Ann')").unwrap();
+        assert_eq!(
+            db.dump_table("main.users").unwrap(),
+            "INSERT INTO \"main\".\"users\"(\"name\") VALUES ('Ann');\n"
+        );
+    }
+
+    #[test]
+    fn text_decoding() {
+        let connect = |decoding| {
+            let config = Config::new("file:////tmp/text_decoding.db")
+                .unwrap()
+                .with_text_decoding(decoding);
+            SyncClient::from_config(config).unwrap()
+        };
+        let query = "SELECT CAST(x'6869ff' AS TEXT), 'ok'";
+
+        let err = connect(TextDecoding::Strict).execute(query).unwrap_err();
+        assert!(err.to_string().contains("Invalid UTF-8"), "{err}");
+        let rs = connect(TextDecoding::Strict)
+            .execute("SELECT 'zażółć'")
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "zażółć");
+
+        let db = connect(TextDecoding::Lossy);
+        let rs = db.execute(query).unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "hi\u{FFFD}");
+        assert_eq!(rs.rows[0].try_get::<&str>(1).unwrap(), "ok");
+        let rs = db.batch([query]).unwrap();
+        assert_eq!(rs[0].rows[0].try_get::<&str>(0).unwrap(), "hi\u{FFFD}");
+    }
+
+    #[tokio::test]
+    async fn sync_client_shares_connection() {
+        // Each in-memory connection has its own database, so data only survives
+        // the conversions if the connection is shared
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+
+        let sync = SyncClient::from_client(db);
+        sync.execute("INSERT INTO t VALUES (1)").unwrap();
+        sync.as_client()
+            .execute("INSERT INTO t VALUES (2)")
+            .await
+            .unwrap();
+
+        let db = sync.into_inner();
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 2);
+    }
+
+    #[test]
+    fn count() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        assert_eq!(db.count("t", None).unwrap(), 0);
+        db.execute("INSERT INTO t VALUES (1), (2), (3)").unwrap();
+        assert_eq!(db.count("main.t", None).unwrap(), 3);
+        let args = [proto::Value::from(1), proto::Value::from(3)];
+        assert_eq!(db.count("t", Some(("x > ? AND x <= ?", &args))).unwrap(), 2);
+
+        for table in ["t; DROP TABLE t", "a.b.c", "", "t x"] {
+            let err = db.count(table, None).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))),
+                "{table}: {err}"
+            );
+        }
+        assert!(db.count("missing", None).is_err());
+
+        // Names which are keywords are quoted, including the schema
+        db.execute("CREATE TABLE \"order\"(\"group\")").unwrap();
+        assert_eq!(
+            db.insert_many("main.order", &["group"], &[(1,), (2,)])
+                .unwrap(),
+            2
+        );
+        assert_eq!(db.count("order", None).unwrap(), 2);
+        assert_eq!(db.count("main.order", None).unwrap(), 2);
+    }
+
+    #[test]
+    fn export_csv() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(i, f, t, b)").unwrap();
+        db.execute("INSERT INTO t VALUES (1, 0.5, 'a\"b', x'00ff'), (NULL, NULL, NULL, NULL)")
+            .unwrap();
+
+        let mut out = Vec::new();
+        assert_eq!(db.export_csv("SELECT * FROM t", &mut out).unwrap(), 2);
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "i,f,t,b\r\n1,0.5,\"a\"\"b\",AP8=\r\n,,,\r\n"
+        );
+
+        let mut out = Vec::new();
+        let stmt = Statement::with_args("SELECT i FROM t WHERE i = ?", &[2]);
+        assert_eq!(db.export_csv(stmt, &mut out).unwrap(), 0);
+        assert_eq!(out, b"i\r\n");
+
+        // Writes exported with RETURNING invalidate the query cache
+        let config = Config::new("file:////tmp/export_csv.db")
+            .unwrap()
+            .with_query_cache(16, std::time::Duration::from_secs(60));
+        let db = SyncClient::from_config(config).unwrap();
+        db.execute("DROP TABLE IF EXISTS t").unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        assert_eq!(db.count("t", None).unwrap(), 0);
+        let stmt = "INSERT INTO t VALUES (1), (2) RETURNING x";
+        assert_eq!(db.export_csv(stmt, std::io::sink()).unwrap(), 2);
+        assert_eq!(db.count("t", None).unwrap(), 2);
+    }
+
+    #[test]
+    fn from_env_prefixed() {
+        // Prefixes unique to this test, as the environment is shared by all tests
+        std::env::set_var("FROM_ENV_PRIMARY_URL", "file:////tmp/from_env_primary.db");
+        std::env::set_var(
+            "FROM_ENV_ANALYTICS_URL",
+            "file:////tmp/from_env_analytics.db",
+        );
+        let primary = SyncClient::from_env_prefixed("FROM_ENV_PRIMARY").unwrap();
+        let analytics = SyncClient::from_env_prefixed("FROM_ENV_ANALYTICS").unwrap();
+        primary.execute("DROP TABLE IF EXISTS t").unwrap();
+        primary.execute("CREATE TABLE t(x)").unwrap();
+        analytics.execute("DROP TABLE IF EXISTS t").unwrap();
+        assert_eq!(primary.count("t", None).unwrap(), 0);
+
+        let Err(err) = SyncClient::from_env_prefixed("FROM_ENV_MISSING") else {
+            panic!("client created without a URL");
+        };
+        assert!(err.to_string().contains("FROM_ENV_MISSING_URL"), "{err}");
+    }
+
+    #[test]
+    fn insert_many() {
+        #[derive(serde::Serialize, serde::Deserialize, Debug, PartialEq)]
+        struct User {
+            name: String,
+            email: Option<String>,
+        }
+
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT, email TEXT)")
+            .unwrap();
+        // Enough rows to be split into multiple statements
+        let users: Vec<User> = (0..MAX_VARIABLE_NUMBER)
+            .map(|i| User {
+                name: format!("user{i}"),
+                email: (i % 2 == 0).then(|| format!("user{i}@example.com")),
+            })
+            .collect();
+        assert_eq!(
+            db.insert_many("users", &["name", "email"], &users).unwrap(),
+            users.len() as u64
+        );
+        let rs = db
+            .execute("SELECT name, email FROM users ORDER BY id")
+            .unwrap();
+        let read: Vec<User> = rs
+            .rows
+            .iter()
+            .map(|row| crate::de::from_row_positional(row).unwrap())
+            .collect();
+        assert_eq!(read, users);
+
+        // Rows must match the columns, and a failure inserts nothing
+        let err = db
+            .insert_many("users", &["name"], &[("a", "b")])
+            .unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))));
+        assert!(db
+            .insert_many("users", &["name", "missing"], &users[..1])
+            .is_err());
+        assert_eq!(db.count("users", None).unwrap(), users.len() as u64);
+        assert!(db.insert_many("users; --", &["name"], &[("a",)]).is_err());
+        assert_eq!(db.insert_many::<(i64,)>("users", &["id"], &[]).unwrap(), 0);
+    }
+
+    // Collects the fields of `query` spans, both set on creation and recorded later
+    #[derive(Clone, Default)]
+    struct QuerySpans(Arc<std::sync::Mutex<Vec<(u64, SpanFields)>>>);
+
+    type SpanFields = HashMap<String, String>;
+
+    struct FieldVisitor<'a>(&'a mut SpanFields);
+
+    impl tracing::field::Visit for FieldVisitor<'_> {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn std::fmt::Debug) {
+            self.0
+                .insert(field.name().to_string(), format!("{value:?}"));
+        }
+
+        fn record_str(&mut self, field: &tracing::field::Field, value: &str) {
+            self.0.insert(field.name().to_string(), value.to_string());
+        }
+    }
+
+    impl<S: tracing::Subscriber> tracing_subscriber::Layer<S> for QuerySpans {
+        fn on_new_span(
+            &self,
+            attrs: &tracing::span::Attributes<'_>,
+            id: &tracing::span::Id,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            if attrs.metadata().name() == "query" {
+                let mut fields = HashMap::new();
+                attrs.record(&mut FieldVisitor(&mut fields));
+                self.0.lock().unwrap().push((id.into_u64(), fields));
+            }
+        }
+
+        fn on_record(
+            &self,
+            id: &tracing::span::Id,
+            values: &tracing::span::Record<'_>,
+            _ctx: tracing_subscriber::layer::Context<'_, S>,
+        ) {
+            let mut spans = self.0.lock().unwrap();
+            if let Some((_, fields)) = spans.iter_mut().rev().find(|(i, _)| *i == id.into_u64()) {
+                values.record(&mut FieldVisitor(fields));
+            }
+        }
+    }
+
+    #[test]
+    fn query_spans() {
+        use tracing_subscriber::layer::SubscriberExt;
+
+        let path = "/tmp/query_spans.db";
+        let _ = std::fs::remove_file(path);
+        let config = || Config::new(format!("file:///{path}").as_str()).unwrap();
+        let spans = QuerySpans::default();
+        let subscriber = tracing_subscriber::registry().with(spans.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            futures::executor::block_on(async {
+                let db = Client::from_config(config()).await.unwrap();
+                db.execute("CREATE TABLE t(x)").await.unwrap();
+                db.execute(Statement::with_args(
+                    "INSERT INTO t VALUES (?)",
+                    &["secret"],
+                ))
+                .await
+                .unwrap();
+                db.execute("SELECT * FROM missing").await.unwrap_err();
+
+                let db = Client::from_config(config().with_span_sql(true))
+                    .await
+                    .unwrap();
+                db.raw_batch(["SELECT x FROM t", "SELECT 1 UNION ALL SELECT 2"])
+                    .await
+                    .unwrap();
+                db.export_csv("SELECT x FROM t", std::io::sink())
+                    .await
+                    .unwrap();
+            })
+        });
+
+        let spans: Vec<_> = std::mem::take(&mut *spans.0.lock().unwrap())
+            .into_iter()
+            .map(|(_, fields)| fields)
+            .collect();
+        assert_eq!(spans.len(), 5, "{spans:?}");
+        for fields in &spans {
+            assert_eq!(fields["backend"], "local");
+            assert!(fields.contains_key("elapsed"), "{fields:?}");
+        }
+        assert!(!spans[..3].iter().any(|fields| fields.contains_key("sql")));
+        assert!(!format!("{spans:?}").contains("secret"));
+        assert_eq!(spans[1]["args"], "1");
+        assert_eq!(spans[1]["rows"], "0");
+        assert!(spans[2]["error"].contains("missing"), "{spans:?}");
+        assert_eq!(spans[3]["statements"], "2");
+        assert_eq!(spans[3]["rows"], "3");
+        assert_eq!(
+            spans[3]["sql"],
+            "SELECT x FROM t; SELECT 1 UNION ALL SELECT 2"
+        );
+        assert_eq!(spans[4]["rows"], "1");
+        assert_eq!(spans[4]["sql"], "SELECT x FROM t");
+    }
+
+    #[tokio::test]
+    async fn metrics() {
+        let path = "/tmp/metrics.db";
+        let _ = std::fs::remove_file(path);
+        let config = || Config::new(format!("file:///{path}").as_str()).unwrap();
+        let db = Client::from_config(config()).await.unwrap();
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        let rs = db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(rs.stats, None);
+
+        let db = Client::from_config(config().with_metrics(true))
+            .await
+            .unwrap();
+        let stats = db
+            .execute("INSERT INTO t VALUES (2), (3)")
+            .await
+            .unwrap()
+            .stats
+            .unwrap();
+        assert!(stats.elapsed > std::time::Duration::ZERO);
+        assert_eq!(stats.rows_written, Some(2));
+        let stats = db.execute("SELECT * FROM t").await.unwrap().stats.unwrap();
+        assert_eq!(stats.rows_read, Some(3));
+
+        let tx = db.transaction().await.unwrap();
+        let rs = tx.execute("SELECT * FROM t").await.unwrap();
+        assert!(rs.stats.unwrap().elapsed > std::time::Duration::ZERO);
+        tx.commit().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn result_set_is_write() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        let rs = db.execute("SELECT * FROM t").await.unwrap();
+        assert!(!rs.is_write());
+        let rs = db.execute("UPDATE t SET x = 1").await.unwrap();
+        assert_eq!(rs.rows_affected, 0);
+        assert!(rs.is_write());
+        let rs = db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        assert_eq!(rs.rows_affected, 1);
+        assert!(rs.is_write());
+
+        let rs = db
+            .batch(["SELECT * FROM t", "DELETE FROM t WHERE x = 2"])
+            .await
+            .unwrap();
+        assert!(!rs[0].is_write());
+        assert!(rs[1].is_write());
     }
 }
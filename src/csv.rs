@@ -0,0 +1,93 @@
+//! Minimal CSV writer for [`crate::Client::export_csv()`], following RFC 4180.
+
+use std::borrow::Cow;
+use std::io::Write;
+
+use base64::Engine;
+
+use crate::{DisplayValue, Value};
+
+/// Formats a value as a CSV field: NULL as an empty field, blobs base64-encoded
+/// and other values like [DisplayValue] does.
+pub(crate) fn field(value: &Value) -> Cow<'_, str> {
+    match value {
+        Value::Null => Cow::Borrowed(""),
+        Value::Text { value } => Cow::Borrowed(value),
+        Value::Blob { value } => {
+            Cow::Owned(base64::engine::general_purpose::STANDARD.encode(value))
+        }
+        value => Cow::Owned(DisplayValue(value).to_string()),
+    }
+}
+
+/// Writes the header record with column names
+pub(crate) fn write_header(w: &mut impl Write, columns: &[String]) -> std::io::Result<()> {
+    write_record(w, columns.iter().map(|c| Cow::Borrowed(c.as_str())))
+}
+
+/// Writes a record, quoting fields which contain separators, quotes or line breaks.
+/// Records are terminated with CRLF.
+pub(crate) fn write_record<'a>(
+    w: &mut impl Write,
+    fields: impl IntoIterator<Item = Cow<'a, str>>,
+) -> std::io::Result<()> {
+    for (i, field) in fields.into_iter().enumerate() {
+        if i > 0 {
+            w.write_all(b",")?;
+        }
+        if field.contains([',', '"', '\r', '\n']) {
+            write!(w, "\"{}\"", field.replace('"', "\"\""))?;
+        } else {
+            w.write_all(field.as_bytes())?;
+        }
+    }
+    w.write_all(b"\r\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn record(values: &[Value]) -> String {
+        let mut out = Vec::new();
+        write_record(&mut out, values.iter().map(field)).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn fields() {
+        assert_eq!(
+            record(&[
+                Value::Integer { value: 1 },
+                Value::Float { value: 2.0 },
+                Value::Null,
+                Value::Text {
+                    value: "plain".into()
+                },
+                Value::Blob {
+                    value: vec![0, 255]
+                },
+            ]),
+            "1,2.0,,plain,AP8=\r\n"
+        );
+    }
+
+    #[test]
+    fn quoting() {
+        assert_eq!(
+            record(&[
+                Value::Text {
+                    value: "a,b".into()
+                },
+                Value::Text {
+                    value: "say \"hi\"".into()
+                },
+                Value::Text {
+                    value: "two\nlines".into()
+                },
+            ]),
+            "\"a,b\",\"say \"\"hi\"\"\",\"two\nlines\"\r\n"
+        );
+        assert_eq!(record(&[]), "\r\n");
+    }
+}
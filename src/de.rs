@@ -1,11 +1,14 @@
 //! libsql deserialization utilities.
 
 use serde::de::{value::Error as DeError, Error};
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+use serde::de::{IntoDeserializer, MapAccess};
+#[cfg(feature = "mapping_names_to_values_in_rows")]
 use std::collections::hash_map::Iter;
 
 use hrana_client_proto::Value;
 use serde::{
-    de::{value::SeqDeserializer, IntoDeserializer, MapAccess, Visitor},
+    de::{value::SeqDeserializer, SeqAccess, Visitor},
     Deserialize, Deserializer,
 };
 
@@ -16,12 +19,16 @@ use crate::Row;
 /// # Types
 ///
 /// Structs must match their field name to the column name but the order does not matter.
+/// Fields marked with `#[serde(flatten)]` are filled from the same columns, so a nested struct
+/// can take its fields from a flat row - rename them, e.g. `#[serde(rename = "address_city")]`,
+/// to read columns selected with a prefix.
 /// There is a limited set of Rust types which are supported and those are:
 ///
 /// - String
 /// - Vec<u8>
 /// - i64
 /// - f64
+/// - bool (from `0`/`1` or `true`/`false` text, see [`SqlBool`](crate::SqlBool))
 /// - Option<T> (where T is any of the above)
 /// - ()
 ///
@@ -50,15 +57,80 @@ use crate::Row;
 /// # Ok(())
 /// # }
 /// ```
+#[cfg(feature = "mapping_names_to_values_in_rows")]
 pub fn from_row<'de, T: Deserialize<'de>>(row: &'de Row) -> anyhow::Result<T> {
     let de = De { row };
     T::deserialize(de).map_err(Into::into)
 }
 
+/// Deserialize from a [`Row`] into any type `T` that implements [`serde::Deserialize`],
+/// taking values in column order.
+///
+/// Unlike [`from_row`], it does not need column names, so it works for tuples and tuple structs,
+/// and does not require the `mapping_names_to_values_in_rows` feature. Structs with named fields
+/// are filled in declaration order. The number of fields must match the number of columns.
+/// Supported field types are the same as for [`from_row`].
+///
+/// # Example
+///
+/// ```no_run
+/// # async fn run(db: libsql_client::Client) -> anyhow::Result<()> {
+/// use libsql_client::de;
+///
+/// let users = db
+///     .execute("SELECT name, age FROM users")
+///     .await?
+///     .rows
+///     .iter()
+///     .map(de::from_row_positional)
+///     .collect::<Result<Vec<(String, i64)>, _>>()?;
+///
+/// println!("Users: {:?}", users);
+/// # Ok(())
+/// # }
+/// ```
+pub fn from_row_positional<'de, T: Deserialize<'de>>(row: &'de Row) -> anyhow::Result<T> {
+    let de = PositionalDe { row };
+    T::deserialize(de).map_err(Into::into)
+}
+
+/// Parses a text value holding a JSON array, e.g. one built with SQLite's `json_group_array()`,
+/// into a [`Vec`] of any type `T` that implements [`serde::de::DeserializeOwned`].
+///
+/// Fails if the value is not text, or not a JSON array of values convertible to `T`.
+/// Requires the `json` feature.
+///
+/// # Example
+///
+/// ```
+/// # fn run() -> anyhow::Result<()> {
+/// use libsql_client::de;
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// let rs = db.execute("SELECT json_array(1, 2, 3), '[\"a\",\"b\"]'")?;
+/// let ids: Vec<i64> = de::from_json_array(&rs.rows[0].values[0])?;
+/// let tags: Vec<String> = de::from_json_array(&rs.rows[0].values[1])?;
+/// assert_eq!(ids, [1, 2, 3]);
+/// assert_eq!(tags, ["a", "b"]);
+/// # Ok(())
+/// # }
+/// # run().unwrap();
+/// ```
+#[cfg(feature = "json")]
+pub fn from_json_array<T: serde::de::DeserializeOwned>(value: &Value) -> anyhow::Result<Vec<T>> {
+    match value {
+        Value::Text { value } => serde_json::from_str(value)
+            .map_err(|e| anyhow::anyhow!("Invalid JSON array {value:?}: {e}")),
+        _ => anyhow::bail!("Expected a JSON array stored as text, got {value}"),
+    }
+}
+
+#[cfg(feature = "mapping_names_to_values_in_rows")]
 struct De<'de> {
     row: &'de Row,
 }
 
+#[cfg(feature = "mapping_names_to_values_in_rows")]
 impl<'de> Deserializer<'de> for De<'de> {
     type Error = serde::de::value::Error;
 
@@ -78,50 +150,115 @@ impl<'de> Deserializer<'de> for De<'de> {
     where
         V: Visitor<'de>,
     {
-        struct RowMapAccess<'a> {
-            iter: Iter<'a, String, Value>,
-            value: Option<&'a Value>,
+        self.deserialize_map(visitor)
+    }
+
+    // Structs with `#[serde(flatten)]` fields are deserialized as maps, and their flattened
+    // fields pick their columns out of the same map
+    fn deserialize_map<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        visitor.visit_map(RowMapAccess {
+            iter: self.row.value_map.iter(),
+            value: None,
+        })
+    }
+
+    serde::forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct seq tuple
+        tuple_struct enum identifier ignored_any
+    }
+}
+
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+struct RowMapAccess<'a> {
+    iter: Iter<'a, String, Value>,
+    value: Option<&'a Value>,
+}
+
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+impl<'de> MapAccess<'de> for RowMapAccess<'de> {
+    type Error = serde::de::value::Error;
+
+    fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+    where
+        K: serde::de::DeserializeSeed<'de>,
+    {
+        if let Some((k, v)) = self.iter.next() {
+            self.value = Some(v);
+            seed.deserialize(k.to_string().into_deserializer())
+                .map(Some)
+        } else {
+            Ok(None)
         }
+    }
+
+    fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
+    where
+        V: serde::de::DeserializeSeed<'de>,
+    {
+        let value = self
+            .value
+            .take()
+            .expect("next_value called before next_key");
+
+        seed.deserialize(V(value))
+    }
+}
 
-        impl<'de> MapAccess<'de> for RowMapAccess<'de> {
+struct PositionalDe<'de> {
+    row: &'de Row,
+}
+
+impl<'de> Deserializer<'de> for PositionalDe<'de> {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        struct RowSeqAccess<'a> {
+            iter: std::slice::Iter<'a, Value>,
+        }
+
+        impl<'de> SeqAccess<'de> for RowSeqAccess<'de> {
             type Error = serde::de::value::Error;
 
-            fn next_key_seed<K>(&mut self, seed: K) -> Result<Option<K::Value>, Self::Error>
+            fn next_element_seed<T>(&mut self, seed: T) -> Result<Option<T::Value>, Self::Error>
             where
-                K: serde::de::DeserializeSeed<'de>,
+                T: serde::de::DeserializeSeed<'de>,
             {
-                if let Some((k, v)) = self.iter.next() {
-                    self.value = Some(v);
-                    seed.deserialize(k.to_string().into_deserializer())
-                        .map(Some)
-                } else {
-                    Ok(None)
+                match self.iter.next() {
+                    Some(value) => seed.deserialize(V(value)).map(Some),
+                    None => Ok(None),
                 }
             }
 
-            fn next_value_seed<V>(&mut self, seed: V) -> Result<V::Value, Self::Error>
-            where
-                V: serde::de::DeserializeSeed<'de>,
-            {
-                let value = self
-                    .value
-                    .take()
-                    .expect("next_value called before next_key");
-
-                seed.deserialize(V(value))
+            fn size_hint(&self) -> Option<usize> {
+                Some(self.iter.len())
             }
         }
 
-        visitor.visit_map(RowMapAccess {
-            iter: self.row.value_map.iter(),
-            value: None,
-        })
+        let mut seq = RowSeqAccess {
+            iter: self.row.values.iter(),
+        };
+        let value = visitor.visit_seq(&mut seq)?;
+        let remaining = seq.iter.len();
+        if remaining > 0 {
+            return Err(DeError::invalid_length(
+                self.row.values.len(),
+                &format!("{} columns", self.row.values.len() - remaining).as_str(),
+            ));
+        }
+        Ok(value)
     }
 
     serde::forward_to_deserialize_any! {
         bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
         bytes byte_buf option unit unit_struct newtype_struct seq tuple
-        tuple_struct map enum identifier ignored_any
+        tuple_struct map struct enum identifier ignored_any
     }
 }
 
@@ -153,30 +290,56 @@ impl<'de> Deserializer<'de> for V<'de> {
         V: Visitor<'de>,
     {
         match self.0 {
-            Value::Text { value } => visitor.visit_some(value.to_string().into_deserializer()),
             Value::Null => visitor.visit_none(),
-            Value::Float { value } => visitor.visit_some(value.into_deserializer()),
-            Value::Integer { value } => visitor.visit_some(value.into_deserializer()),
-            Value::Blob { value } => {
-                let seq = SeqDeserializer::new(value.iter().cloned());
-                visitor.visit_some(seq)
-            }
+            _ => visitor.visit_some(self),
         }
     }
 
+    #[inline]
+    fn deserialize_bool<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        let crate::SqlBool(value) = self.0.try_into().map_err(DeError::custom)?;
+        visitor.visit_bool(value)
+    }
+
+    // Blobs are handed over whole to types asking for bytes, e.g. `serde_bytes::ByteBuf`,
+    // rather than byte by byte like for `Vec<u8>`
+    #[inline]
+    fn deserialize_bytes<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        match self.0 {
+            Value::Blob { value } => visitor.visit_byte_buf(value.clone()),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    #[inline]
+    fn deserialize_byte_buf<V>(self, visitor: V) -> Result<V::Value, Self::Error>
+    where
+        V: Visitor<'de>,
+    {
+        self.deserialize_bytes(visitor)
+    }
+
     serde::forward_to_deserialize_any! {
-        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
-        bytes byte_buf unit unit_struct newtype_struct seq tuple
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        unit unit_struct newtype_struct seq tuple
         tuple_struct map enum struct identifier ignored_any
     }
 }
 
 #[cfg(test)]
 mod tests {
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
     use std::collections::HashMap;
 
     use super::*;
 
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
     #[derive(serde::Deserialize)]
     #[allow(unused)]
     struct Foo {
@@ -191,6 +354,7 @@ mod tests {
         bag: Option<Vec<u8>>,
     }
 
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
     #[test]
     fn struct_from_row() {
         let mut row = Row {
@@ -238,4 +402,200 @@ mod tests {
         assert_eq!(foo.bac, None);
         assert_eq!(foo.bag, Some(vec![6u8; 128]));
     }
+
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    #[test]
+    fn flattened_struct_from_row() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Address {
+            #[serde(rename = "address_street")]
+            street: String,
+            city: Option<String>,
+        }
+
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct User {
+            id: i64,
+            name: String,
+            #[serde(flatten)]
+            address: Address,
+        }
+
+        let text = |s: &str| Value::Text {
+            value: s.to_string(),
+        };
+        let row = Row::new(
+            &[
+                "id".to_string(),
+                "name".to_string(),
+                "address_street".to_string(),
+                "city".to_string(),
+            ],
+            vec![
+                Value::Integer { value: 1 },
+                text("Ann"),
+                text("Main St"),
+                Value::Null,
+            ],
+        );
+        assert_eq!(
+            from_row::<User>(&row).unwrap(),
+            User {
+                id: 1,
+                name: "Ann".to_string(),
+                address: Address {
+                    street: "Main St".to_string(),
+                    city: None,
+                },
+            }
+        );
+
+        let row = Row::new(
+            &["id".to_string(), "name".to_string(), "city".to_string()],
+            vec![Value::Integer { value: 2 }, text("Bob"), text("Oslo")],
+        );
+        assert!(from_row::<User>(&row).is_err());
+    }
+
+    fn positional_row() -> Row {
+        Row {
+            values: vec![
+                Value::Integer { value: 42 },
+                Value::Text {
+                    value: "foo".into(),
+                },
+                Value::Float { value: 4.5 },
+                Value::Null,
+            ],
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map: HashMap::new(),
+        }
+    }
+
+    #[test]
+    fn tuple_from_row_positional() {
+        let row = positional_row();
+        let (a, b, c, d) = from_row_positional::<(i64, String, f64, Option<i64>)>(&row).unwrap();
+        assert_eq!(a, 42);
+        assert_eq!(b, "foo");
+        assert_eq!(c, 4.5);
+        assert_eq!(d, None);
+
+        assert!(from_row_positional::<(i64, String)>(&row).is_err());
+        assert!(from_row_positional::<(i64, String, f64, Option<i64>, i64)>(&row).is_err());
+    }
+
+    #[test]
+    fn bool_from_row_positional() {
+        let row = Row {
+            values: vec![
+                Value::Integer { value: 1 },
+                Value::Text {
+                    value: "false".into(),
+                },
+                Value::Null,
+            ],
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map: HashMap::new(),
+        };
+        let row_bools = from_row_positional::<(bool, bool, Option<bool>)>(&row).unwrap();
+        assert_eq!(row_bools, (true, false, None));
+
+        let row = Row {
+            values: vec![Value::Integer { value: 2 }],
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map: HashMap::new(),
+        };
+        assert!(from_row_positional::<(bool,)>(&row).is_err());
+    }
+
+    #[test]
+    fn tuple_struct_from_row_positional() {
+        #[derive(serde::Deserialize)]
+        struct Foo(i64, String, f64, Option<i64>);
+
+        let row = positional_row();
+        let foo = from_row_positional::<Foo>(&row).unwrap();
+        assert_eq!(foo.0, 42);
+        assert_eq!(foo.1, "foo");
+        assert_eq!(foo.2, 4.5);
+        assert_eq!(foo.3, None);
+    }
+
+    // Deserializes only from bytes, like `serde_bytes::ByteBuf` and fields
+    // annotated with `#[serde(with = "serde_bytes")]`
+    #[derive(Debug, PartialEq)]
+    struct ByteBuf(Vec<u8>);
+
+    impl<'de> Deserialize<'de> for ByteBuf {
+        fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+            struct ByteBufVisitor;
+
+            impl<'de> Visitor<'de> for ByteBufVisitor {
+                type Value = ByteBuf;
+
+                fn expecting(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+                    f.write_str("a byte buffer")
+                }
+
+                fn visit_byte_buf<E: Error>(self, v: Vec<u8>) -> Result<ByteBuf, E> {
+                    Ok(ByteBuf(v))
+                }
+            }
+
+            deserializer.deserialize_byte_buf(ByteBufVisitor)
+        }
+    }
+
+    #[test]
+    fn blob_as_byte_buf() {
+        let row = Row {
+            values: vec![
+                Value::Blob {
+                    value: vec![1, 2, 3],
+                },
+                Value::Blob { value: vec![4] },
+                Value::Null,
+                Value::Blob { value: vec![5, 6] },
+            ],
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map: HashMap::new(),
+        };
+        let (a, b, c, d) =
+            from_row_positional::<(ByteBuf, Option<ByteBuf>, Option<ByteBuf>, Vec<u8>)>(&row)
+                .unwrap();
+        assert_eq!(a, ByteBuf(vec![1, 2, 3]));
+        assert_eq!(b, Some(ByteBuf(vec![4])));
+        assert_eq!(c, None);
+        assert_eq!(d, [5, 6]);
+
+        let row = Row {
+            values: vec![Value::Integer { value: 1 }],
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map: HashMap::new(),
+        };
+        assert!(from_row_positional::<(ByteBuf,)>(&row).is_err());
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn json_array() {
+        let text = |s: &str| Value::Text {
+            value: s.to_string(),
+        };
+        assert_eq!(
+            from_json_array::<i64>(&text("[1, 2, 3]")).unwrap(),
+            [1, 2, 3]
+        );
+        assert_eq!(
+            from_json_array::<Option<f64>>(&text("[0.5, null]")).unwrap(),
+            [Some(0.5), None]
+        );
+        assert!(from_json_array::<i64>(&text("[]")).unwrap().is_empty());
+        assert!(from_json_array::<i64>(&text("[\"a\"]")).is_err());
+        assert!(from_json_array::<i64>(&text("{\"a\": 1}")).is_err());
+        assert!(from_json_array::<i64>(&text("1,2")).is_err());
+        assert!(from_json_array::<i64>(&Value::Integer { value: 1 }).is_err());
+        assert!(from_json_array::<i64>(&Value::Null).is_err());
+    }
 }
@@ -0,0 +1,279 @@
+//! Structured errors, for inspecting failures programmatically.
+
+/// Errors reported by the database or the client.
+///
+/// Methods of this crate return [anyhow::Error]; use [`anyhow::Error::downcast_ref()`]
+/// to check if it wraps an [Error].
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// db.execute("CREATE TABLE t(x PRIMARY KEY)")?;
+/// db.execute("INSERT INTO t VALUES (1)")?;
+/// let err = db.execute("INSERT INTO t VALUES (1)").unwrap_err();
+/// let code = err.downcast_ref::<libsql_client::Error>().and_then(|e| e.code());
+/// assert_eq!(code, Some("SQLITE_CONSTRAINT_PRIMARYKEY"));
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Error {
+    /// The database failed to execute a statement, e.g. because of a constraint violation.
+    /// `code` is the name of the SQLite (extended) result code, like `SQLITE_CONSTRAINT_UNIQUE`
+    /// or `SQLITE_BUSY`, if reported.
+    Database {
+        message: String,
+        code: Option<String>,
+    },
+    /// The database ran out of storage or failed to read or write it, i.e. `SQLITE_FULL` or
+    /// `SQLITE_IOERR` and its extended codes. Unlike [`Error::Database`], retrying the statement
+    /// won't help until the underlying disk problem is fixed.
+    Storage { message: String, code: String },
+    /// The client was used incorrectly, e.g. a configured limit was exceeded.
+    Misuse(String),
+    /// The backend or server does not support the requested feature,
+    /// e.g. interactive transactions over HTTP without batons.
+    Unsupported(String),
+    /// The statement was aborted because it ran longer than its deadline,
+    /// e.g. in [`crate::local::Client::execute_with_timeout()`].
+    Timeout(String),
+    /// The server closed the stream of an interactive transaction, e.g. after it expired
+    /// due to inactivity, so the transaction was rolled back. It can only be restarted
+    /// from the beginning, in a new transaction.
+    StreamExpired(String),
+    /// The request would exceed [`Config::with_max_request_bytes()`](crate::Config::with_max_request_bytes)
+    /// and can't be split into smaller ones, e.g. a transactional batch or a single huge statement.
+    RequestTooLarge(String),
+}
+
+impl Error {
+    /// Creates an error for a failed statement, classifying it by its result code
+    #[cfg(any(
+        feature = "local_backend",
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend",
+    ))]
+    pub(crate) fn from_code(message: String, code: Option<String>) -> Self {
+        match code {
+            Some(code)
+                if code == "SQLITE_FULL"
+                    || code == "SQLITE_IOERR"
+                    || code.starts_with("SQLITE_IOERR_") =>
+            {
+                Error::Storage { message, code }
+            }
+            code => Error::Database { message, code },
+        }
+    }
+
+    /// Returns the SQLite result code name, if known
+    pub fn code(&self) -> Option<&str> {
+        match self {
+            Error::Database { code, .. } => code.as_deref(),
+            Error::Storage { code, .. } => Some(code),
+            Error::Misuse(_)
+            | Error::Unsupported(_)
+            | Error::Timeout(_)
+            | Error::StreamExpired(_)
+            | Error::RequestTooLarge(_) => None,
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Error::Database { message, .. } | Error::Storage { message, .. } => {
+                f.write_str(message)
+            }
+            Error::Misuse(message) => write!(f, "Misuse: {message}"),
+            Error::Unsupported(message) => write!(f, "Unsupported: {message}"),
+            Error::Timeout(message) => write!(f, "Timeout: {message}"),
+            Error::StreamExpired(message) => write!(f, "Stream expired: {message}"),
+            Error::RequestTooLarge(message) => write!(f, "Request too large: {message}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// Maps a numeric SQLite result code to its name, e.g. 2067 to `SQLITE_CONSTRAINT_UNIQUE`.
+/// See <https://www.sqlite.org/rescode.html>.
+#[cfg(feature = "local_backend")]
+pub(crate) fn sqlite_code_name(code: i32) -> Option<&'static str> {
+    Some(match code {
+        1 => "SQLITE_ERROR",
+        2 => "SQLITE_INTERNAL",
+        3 => "SQLITE_PERM",
+        4 => "SQLITE_ABORT",
+        5 => "SQLITE_BUSY",
+        6 => "SQLITE_LOCKED",
+        7 => "SQLITE_NOMEM",
+        8 => "SQLITE_READONLY",
+        9 => "SQLITE_INTERRUPT",
+        10 => "SQLITE_IOERR",
+        11 => "SQLITE_CORRUPT",
+        12 => "SQLITE_NOTFOUND",
+        13 => "SQLITE_FULL",
+        14 => "SQLITE_CANTOPEN",
+        15 => "SQLITE_PROTOCOL",
+        16 => "SQLITE_EMPTY",
+        17 => "SQLITE_SCHEMA",
+        18 => "SQLITE_TOOBIG",
+        19 => "SQLITE_CONSTRAINT",
+        20 => "SQLITE_MISMATCH",
+        21 => "SQLITE_MISUSE",
+        22 => "SQLITE_NOLFS",
+        23 => "SQLITE_AUTH",
+        24 => "SQLITE_FORMAT",
+        25 => "SQLITE_RANGE",
+        26 => "SQLITE_NOTADB",
+        27 => "SQLITE_NOTICE",
+        28 => "SQLITE_WARNING",
+        257 => "SQLITE_ERROR_MISSING_COLLSEQ",
+        261 => "SQLITE_BUSY_RECOVERY",
+        262 => "SQLITE_LOCKED_SHAREDCACHE",
+        264 => "SQLITE_READONLY_RECOVERY",
+        266 => "SQLITE_IOERR_READ",
+        267 => "SQLITE_CORRUPT_VTAB",
+        270 => "SQLITE_CANTOPEN_NOTEMPDIR",
+        275 => "SQLITE_CONSTRAINT_CHECK",
+        279 => "SQLITE_AUTH_USER",
+        283 => "SQLITE_NOTICE_RECOVER_WAL",
+        284 => "SQLITE_WARNING_AUTOINDEX",
+        513 => "SQLITE_ERROR_RETRY",
+        516 => "SQLITE_ABORT_ROLLBACK",
+        517 => "SQLITE_BUSY_SNAPSHOT",
+        518 => "SQLITE_LOCKED_VTAB",
+        520 => "SQLITE_READONLY_CANTLOCK",
+        522 => "SQLITE_IOERR_SHORT_READ",
+        523 => "SQLITE_CORRUPT_SEQUENCE",
+        526 => "SQLITE_CANTOPEN_ISDIR",
+        531 => "SQLITE_CONSTRAINT_COMMITHOOK",
+        539 => "SQLITE_NOTICE_RECOVER_ROLLBACK",
+        769 => "SQLITE_ERROR_SNAPSHOT",
+        773 => "SQLITE_BUSY_TIMEOUT",
+        776 => "SQLITE_READONLY_ROLLBACK",
+        778 => "SQLITE_IOERR_WRITE",
+        779 => "SQLITE_CORRUPT_INDEX",
+        782 => "SQLITE_CANTOPEN_FULLPATH",
+        787 => "SQLITE_CONSTRAINT_FOREIGNKEY",
+        795 => "SQLITE_NOTICE_RBU",
+        1032 => "SQLITE_READONLY_DBMOVED",
+        1034 => "SQLITE_IOERR_FSYNC",
+        1038 => "SQLITE_CANTOPEN_CONVPATH",
+        1043 => "SQLITE_CONSTRAINT_FUNCTION",
+        1288 => "SQLITE_READONLY_CANTINIT",
+        1290 => "SQLITE_IOERR_DIR_FSYNC",
+        1294 => "SQLITE_CANTOPEN_DIRTYWAL",
+        1299 => "SQLITE_CONSTRAINT_NOTNULL",
+        1544 => "SQLITE_READONLY_DIRECTORY",
+        1546 => "SQLITE_IOERR_TRUNCATE",
+        1550 => "SQLITE_CANTOPEN_SYMLINK",
+        1555 => "SQLITE_CONSTRAINT_PRIMARYKEY",
+        1802 => "SQLITE_IOERR_FSTAT",
+        1811 => "SQLITE_CONSTRAINT_TRIGGER",
+        2058 => "SQLITE_IOERR_UNLOCK",
+        2067 => "SQLITE_CONSTRAINT_UNIQUE",
+        2314 => "SQLITE_IOERR_RDLOCK",
+        2323 => "SQLITE_CONSTRAINT_VTAB",
+        2570 => "SQLITE_IOERR_DELETE",
+        2579 => "SQLITE_CONSTRAINT_ROWID",
+        2826 => "SQLITE_IOERR_BLOCKED",
+        2835 => "SQLITE_CONSTRAINT_PINNED",
+        3082 => "SQLITE_IOERR_NOMEM",
+        3091 => "SQLITE_CONSTRAINT_DATATYPE",
+        3338 => "SQLITE_IOERR_ACCESS",
+        3594 => "SQLITE_IOERR_CHECKRESERVEDLOCK",
+        3850 => "SQLITE_IOERR_LOCK",
+        4106 => "SQLITE_IOERR_CLOSE",
+        4362 => "SQLITE_IOERR_DIR_CLOSE",
+        4618 => "SQLITE_IOERR_SHMOPEN",
+        4874 => "SQLITE_IOERR_SHMSIZE",
+        5130 => "SQLITE_IOERR_SHMLOCK",
+        5386 => "SQLITE_IOERR_SHMMAP",
+        5642 => "SQLITE_IOERR_SEEK",
+        5898 => "SQLITE_IOERR_DELETE_NOENT",
+        6154 => "SQLITE_IOERR_MMAP",
+        6410 => "SQLITE_IOERR_GETTEMPPATH",
+        6666 => "SQLITE_IOERR_CONVPATH",
+        6922 => "SQLITE_IOERR_VNODE",
+        7178 => "SQLITE_IOERR_AUTH",
+        7434 => "SQLITE_IOERR_BEGIN_ATOMIC",
+        7690 => "SQLITE_IOERR_COMMIT_ATOMIC",
+        7946 => "SQLITE_IOERR_ROLLBACK_ATOMIC",
+        8202 => "SQLITE_IOERR_DATA",
+        8458 => "SQLITE_IOERR_CORRUPTFS",
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(feature = "local_backend")]
+    fn test_sqlite_code_name() {
+        assert_eq!(sqlite_code_name(5), Some("SQLITE_BUSY"));
+        assert_eq!(sqlite_code_name(2067), Some("SQLITE_CONSTRAINT_UNIQUE"));
+        assert_eq!(sqlite_code_name(-1), None);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn unique_constraint_code() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x UNIQUE)").unwrap();
+        db.execute("INSERT INTO t VALUES (1)").unwrap();
+        let err = db.execute("INSERT INTO t VALUES (1)").unwrap_err();
+        let err = err.downcast_ref::<Error>().unwrap();
+        assert_eq!(err.code(), Some("SQLITE_CONSTRAINT_UNIQUE"));
+        assert!(err.to_string().contains("UNIQUE constraint failed"));
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn disk_full_is_storage_error() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        db.execute("PRAGMA max_page_count = 3").unwrap();
+        let err = db
+            .execute("INSERT INTO t VALUES (zeroblob(100000))")
+            .unwrap_err();
+        let err = err.downcast_ref::<Error>().unwrap();
+        assert!(matches!(err, Error::Storage { .. }), "{err:?}");
+        assert_eq!(err.code(), Some("SQLITE_FULL"));
+    }
+
+    #[test]
+    #[cfg(any(
+        feature = "local_backend",
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend",
+    ))]
+    fn from_code() {
+        let storage = |code: &str| {
+            matches!(
+                Error::from_code(String::new(), Some(code.to_string())),
+                Error::Storage { .. }
+            )
+        };
+        assert!(storage("SQLITE_FULL"));
+        assert!(storage("SQLITE_IOERR"));
+        assert!(storage("SQLITE_IOERR_WRITE"));
+        assert!(!storage("SQLITE_BUSY"));
+        assert!(matches!(
+            Error::from_code(String::new(), None),
+            Error::Database { code: None, .. }
+        ));
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+use wasm_bindgen::{JsCast, JsValue};
+use wasm_bindgen_futures::JsFuture;
+
+use crate::proto::pipeline;
+
+/// HTTP client using the global `fetch` function, available in browsers,
+/// web workers and other JavaScript runtimes.
+#[derive(Clone, Debug)]
+pub struct HttpClient;
+
+// Converts a JavaScript exception to an error
+fn js_error(e: JsValue) -> anyhow::Error {
+    anyhow::anyhow!("{e:?}")
+}
+
+impl HttpClient {
+    pub fn new() -> Self {
+        Self
+    }
+
+    pub async fn send(
+        &self,
+        url: String,
+        auth: String,
+        body: String,
+    ) -> Result<pipeline::ServerMsg> {
+        let response = self.send_raw(url, auth, body).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Sends the request and returns the raw response body
+    pub async fn send_raw(&self, url: String, auth: String, body: String) -> Result<String> {
+        self.send_raw_with_request_id(url, auth, body, None).await
+    }
+
+    /// Like [`HttpClient::send_raw()`], tagging the request with
+    /// an [`X-Idempotency-Key`](crate::http::REQUEST_ID_HEADER) header if `request_id` is set
+    pub async fn send_raw_with_request_id(
+        &self,
+        url: String,
+        auth: String,
+        body: String,
+        request_id: Option<&str>,
+    ) -> Result<String> {
+        let headers = web_sys::Headers::new().map_err(js_error)?;
+        headers.append("Authorization", &auth).map_err(js_error)?;
+        if let Some(request_id) = request_id {
+            headers
+                .append(crate::http::REQUEST_ID_HEADER, request_id)
+                .map_err(js_error)?;
+        }
+        let mut init = web_sys::RequestInit::new();
+        init.method("POST")
+            .headers(&headers)
+            .body(Some(&JsValue::from_str(&body)));
+        let request = web_sys::Request::new_with_str_and_init(&url, &init).map_err(js_error)?;
+
+        // Looked up on the global object, because it's `Window` in browsers,
+        // but `WorkerGlobalScope` and others elsewhere
+        let global = js_sys::global();
+        let fetch: js_sys::Function = js_sys::Reflect::get(&global, &JsValue::from_str("fetch"))
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(|_| anyhow::anyhow!("fetch is not available in this environment"))?;
+        let promise: js_sys::Promise = fetch
+            .call1(&global, &request)
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+        let response: web_sys::Response = JsFuture::from(promise)
+            .await
+            .map_err(js_error)?
+            .dyn_into()
+            .map_err(js_error)?;
+
+        let status = response.status();
+        let text = JsFuture::from(response.text().map_err(js_error)?)
+            .await
+            .map_err(js_error)?
+            .as_string()
+            .unwrap_or_default();
+        if status != 200 {
+            anyhow::bail!("Status {status}: {text}");
+        }
+        Ok(text)
+    }
+}
+
+impl Default for HttpClient {
+    fn default() -> Self {
+        Self::new()
+    }
+}
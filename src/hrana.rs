@@ -1,8 +1,11 @@
 use crate::client::Config;
 use anyhow::Result;
+use futures::FutureExt;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use std::sync::RwLock;
+use std::sync::{Mutex, RwLock};
+use tracing::Instrument;
 
 use crate::{utils, BatchResult, ResultSet, Statement};
 
@@ -10,11 +13,46 @@ use crate::{utils, BatchResult, ResultSet, Statement};
 /// communicate with the database.
 pub struct Client {
     url: String,
-    token: Option<String>,
+    endpoint: String,
+    token: Option<crate::AuthToken>,
 
-    client: hrana_client::Client,
-    client_future: hrana_client::ConnFut,
+    // None for members which were never connected, see [`Client::lazy()`]
+    pool: Vec<Mutex<Option<PoolMember>>>,
+    // Held while connecting a pool member, so that concurrent users share one connection attempt
+    connecting: Vec<futures::lock::Mutex<()>>,
+    next_member: AtomicUsize,
     streams_for_transactions: RwLock<HashMap<u64, Arc<hrana_client::Stream>>>,
+    pub(crate) state: Arc<crate::client::ClientState>,
+}
+
+// A single WebSocket connection of the pool
+struct PoolMember {
+    client: hrana_client::Client,
+    // None once the connection task finished, i.e. the connection is dead
+    client_future: Option<hrana_client::ConnFut>,
+}
+
+impl PoolMember {
+    async fn connect(url: &str, token: Option<&crate::AuthToken>) -> Result<Self> {
+        let token = token.map(|token| token.expose().to_string());
+        let (client, client_future) = hrana_client::Client::connect(url, token).await?;
+        Ok(Self {
+            client,
+            client_future: Some(client_future),
+        })
+    }
+
+    // Returns false if the task driving the connection has already finished
+    fn is_alive(&mut self) -> bool {
+        match self.client_future.as_mut().map(|fut| fut.now_or_never()) {
+            Some(None) => true,
+            Some(Some(_)) => {
+                self.client_future = None;
+                false
+            }
+            None => false,
+        }
+    }
 }
 
 impl std::fmt::Debug for Client {
@@ -22,6 +60,7 @@ impl std::fmt::Debug for Client {
         f.debug_struct("Client")
             .field("url", &self.url)
             .field("token", &self.token)
+            .field("pool_size", &self.pool.len())
             .finish()
     }
 }
@@ -33,29 +72,128 @@ impl Client {
     /// * `url` - URL of the database endpoint
     /// * `token` - auth token
     pub async fn new(url: impl Into<String>, token: impl Into<String>) -> Result<Self> {
-        let token = token.into();
-        let token = if token.is_empty() { None } else { Some(token) };
-        let url = url.into();
+        Self::with_pool_size(url, token, 1).await
+    }
 
-        let (client, client_future) = hrana_client::Client::connect(&url, token.clone()).await?;
+    /// Creates a database client with JWT authentication, backed by a pool
+    /// of `pool_size` WebSocket connections. See [`Config::with_pool_size()`].
+    ///
+    /// # Arguments
+    /// * `url` - URL of the database endpoint
+    /// * `token` - auth token
+    /// * `pool_size` - number of connections to open, at least 1
+    pub async fn with_pool_size(
+        url: impl Into<String>,
+        token: impl Into<String>,
+        pool_size: usize,
+    ) -> Result<Self> {
+        let db = Self::lazy(url, token, pool_size);
+        for member in &db.pool {
+            let connected = PoolMember::connect(&db.url, db.token.as_ref()).await?;
+            *member.lock().unwrap() = Some(connected);
+        }
+        Ok(db)
+    }
 
-        Ok(Self {
+    /// Creates a database client without connecting to the database.
+    ///
+    /// Each connection of the pool is established when it's first used, so constructing
+    /// the client needs no network access and can't fail on an unreachable server;
+    /// connection errors are returned by the first query instead. Concurrent first queries
+    /// wait for a single connection rather than opening several. See [`Config::with_lazy_connect()`].
+    ///
+    /// # Arguments
+    /// * `url` - URL of the database endpoint
+    /// * `token` - auth token
+    /// * `pool_size` - number of connections in the pool, at least 1
+    pub fn lazy(url: impl Into<String>, token: impl Into<String>, pool_size: usize) -> Self {
+        let token = token.into();
+        let token = if token.is_empty() {
+            None
+        } else {
+            Some(crate::AuthToken::new(token))
+        };
+        let url = url.into();
+        Self {
+            endpoint: utils::redacted_url(&url),
             url,
             token,
-            client,
-            client_future,
+            pool: (0..pool_size.max(1)).map(|_| Mutex::new(None)).collect(),
+            connecting: (0..pool_size.max(1))
+                .map(|_| futures::lock::Mutex::new(()))
+                .collect(),
+            next_member: AtomicUsize::new(0),
             streams_for_transactions: RwLock::new(HashMap::new()),
-        })
+            state: Default::default(),
+        }
+    }
+
+    /// Returns the database URL, with credentials stripped
+    pub fn endpoint(&self) -> &str {
+        &self.endpoint
+    }
+
+    /// Returns true if the client was configured with an auth token
+    pub fn has_auth(&self) -> bool {
+        self.token.is_some()
+    }
+
+    /// Returns the number of connections in the pool
+    pub fn pool_size(&self) -> usize {
+        self.pool.len()
     }
 
+    /// Replaces all connections of the pool with new ones
     pub async fn reconnect(&mut self) -> Result<()> {
-        let (client, client_future) =
-            hrana_client::Client::connect(&self.url, self.token.clone()).await?;
-        self.client = client;
-        self.client_future = client_future;
+        for idx in 0..self.pool.len() {
+            self.reconnect_member(idx).await?;
+        }
         Ok(())
     }
 
+    // Replaces the connection at given index with a new one and returns its client
+    async fn reconnect_member(&self, idx: usize) -> Result<hrana_client::Client> {
+        tracing::debug!("Reconnecting pool member {idx}");
+        let member = PoolMember::connect(&self.url, self.token.as_ref()).await?;
+        let client = member.client.clone();
+        *self.pool[idx].lock().unwrap() = Some(member);
+        Ok(client)
+    }
+
+    // Returns the client of the member at given index, unless it's not connected or dead
+    fn live_client(&self, idx: usize) -> Option<hrana_client::Client> {
+        let mut member = self.pool[idx].lock().unwrap();
+        let member = member.as_mut()?;
+        member.is_alive().then(|| member.client.clone())
+    }
+
+    // Connects the member at given index, unless a concurrent caller already did
+    async fn connect_member(&self, idx: usize) -> Result<hrana_client::Client> {
+        let _connecting = self.connecting[idx].lock().await;
+        match self.live_client(idx) {
+            Some(client) => Ok(client),
+            None => self.reconnect_member(idx).await,
+        }
+    }
+
+    // Opens a stream on the next connection of the pool, round-robin.
+    // Dead or not yet connected members are connected lazily, when they're picked.
+    async fn open_stream(&self) -> Result<hrana_client::Stream> {
+        let idx = self.next_member.fetch_add(1, Ordering::Relaxed) % self.pool.len();
+        let client = match self.live_client(idx) {
+            Some(client) => client,
+            None => self.connect_member(idx).await?,
+        };
+        match client.open_stream().await {
+            Ok(stream) => Ok(stream),
+            // The connection died after the health check, give it one more chance
+            Err(hrana_client::error::Error::Shutdown) => {
+                Ok(self.reconnect_member(idx).await?.open_stream().await?)
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
     /// Creates a database client, given a `Url`
     ///
     /// # Arguments
@@ -97,12 +235,29 @@ impl Client {
 
     /// Creates a database client from a `Config` object.
     pub async fn from_config(config: Config) -> Result<Self> {
-        Self::new(config.url, config.auth_token.unwrap_or_default()).await
+        let url = config.routed_url()?;
+        let token = config
+            .auth_token
+            .map(|token| token.expose().to_string())
+            .unwrap_or_default();
+        let pool_size = config.pool_size.unwrap_or(1);
+        if config.lazy_connect {
+            return Ok(Self::lazy(url, token, pool_size));
+        }
+        Self::with_pool_size(url, token, pool_size).await
     }
 
+    /// Closes all connections of the pool, waiting until the tasks driving them finish
     pub async fn shutdown(self) -> Result<()> {
-        self.client.shutdown().await?;
-        self.client_future.await?;
+        for member in self.pool {
+            let Some(member) = member.into_inner().unwrap() else {
+                continue;
+            };
+            if let Some(client_future) = member.client_future {
+                member.client.shutdown().await?;
+                client_future.await?;
+            }
+        }
         Ok(())
     }
 
@@ -119,7 +274,8 @@ impl Client {
         // Pessimistic path - let's drop the mutex, create the stream and try to reinsert it.
         // Another way out of this situation is an async mutex, but I don't want to rely on Tokio or any other specific runtime
         // unless absolutely necessary.
-        let stream = Arc::new(self.client.open_stream().await?);
+        // The stream stays pinned to the connection it was opened on for the whole transaction.
+        let stream = Arc::new(self.open_stream().await?);
         tracing::trace!("Created new stream");
         let mut streams = self.streams_for_transactions.write().unwrap();
         if let std::collections::hash_map::Entry::Vacant(e) = streams.entry(tx_id) {
@@ -135,12 +291,16 @@ impl Client {
         streams.remove(&tx_id);
     }
 
-    fn into_hrana(stmt: Statement) -> hrana_client::proto::Stmt {
+    fn to_hrana(&self, stmt: Statement) -> Result<hrana_client::proto::Stmt> {
+        self.state.check_param_sizes(&stmt)?;
         let mut hrana_stmt = hrana_client::proto::Stmt::new(stmt.sql, true);
         for param in stmt.args {
             hrana_stmt.bind(param);
         }
-        hrana_stmt
+        for (name, param) in stmt.named_args {
+            hrana_stmt.bind_named(name, param);
+        }
+        Ok(hrana_stmt)
     }
 }
 
@@ -149,43 +309,69 @@ impl Client {
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement>>,
     ) -> anyhow::Result<BatchResult> {
-        let mut batch = hrana_client::proto::Batch::new();
-        for stmt in stmts.into_iter() {
-            let stmt: Statement = stmt.into();
-            let mut hrana_stmt = hrana_client::proto::Stmt::new(stmt.sql, true);
-            for param in stmt.args {
-                hrana_stmt.bind(param);
+        let stmts: Vec<Statement> = stmts.into_iter().map(Into::into).collect();
+        let span = utils::QuerySpan::new("hrana", &stmts, self.state.span_sql);
+        let result = async {
+            let mut batch = hrana_client::proto::Batch::new();
+            for stmt in stmts {
+                batch.step(None, self.to_hrana(stmt)?);
             }
-            batch.step(None, hrana_stmt);
-        }
 
-        let stream = self.client.open_stream().await?;
-        stream
-            .execute_batch(batch)
-            .await
-            .map_err(|e| anyhow::anyhow!("{}", e))
+            let stream = self.open_stream().await?;
+            stream
+                .execute_batch(batch)
+                .await
+                .map_err(|e| anyhow::anyhow!("{}", e))
+        }
+        .instrument(span.span.clone())
+        .await;
+        span.finish(&result, utils::batch_rows);
+        result
     }
 
     pub async fn execute(&self, stmt: impl Into<Statement>) -> Result<ResultSet> {
-        let stmt = Self::into_hrana(stmt.into());
+        let stmt = stmt.into();
+        let span = utils::QuerySpan::new("hrana", [&stmt], self.state.span_sql);
+        let result = async {
+            let is_write = stmt.is_write();
+            let stmt = self.to_hrana(stmt)?;
 
-        let stream = self.client.open_stream().await?;
-        stream
-            .execute(stmt)
-            .await
-            .map(ResultSet::from)
-            .map_err(|e| anyhow::anyhow!("{}", e))
+            let stream = self.open_stream().await?;
+            stream
+                .execute(stmt)
+                .await
+                .map(|rs| ResultSet {
+                    is_write,
+                    ..ResultSet::from(rs)
+                })
+                .map_err(|e| anyhow::anyhow!("{}", e))
+        }
+        .instrument(span.span.clone())
+        .await;
+        span.finish(&result, |rs| rs.rows.len());
+        result
     }
 
     pub async fn execute_in_transaction(&self, tx_id: u64, stmt: Statement) -> Result<ResultSet> {
-        let stmt = Self::into_hrana(stmt);
-        tracing::trace!("Transaction {tx_id} executing {}", stmt.sql);
-        let stream = self.stream_for_transaction(tx_id).await?;
-        stream
-            .execute(stmt)
-            .await
-            .map(ResultSet::from)
-            .map_err(|e| anyhow::anyhow!("{}", e))
+        let span = utils::QuerySpan::new("hrana", [&stmt], self.state.span_sql);
+        let result = async {
+            let is_write = stmt.is_write();
+            let stmt = self.to_hrana(stmt)?;
+            tracing::trace!("Transaction {tx_id} executing {}", stmt.sql);
+            let stream = self.stream_for_transaction(tx_id).await?;
+            stream
+                .execute(stmt)
+                .await
+                .map(|rs| ResultSet {
+                    is_write,
+                    ..ResultSet::from(rs)
+                })
+                .map_err(|e| anyhow::anyhow!("{}", e))
+        }
+        .instrument(span.span.clone())
+        .await;
+        span.finish(&result, |rs| rs.rows.len());
+        result
     }
 
     pub async fn commit_transaction(&self, tx_id: u64) -> Result<()> {
@@ -193,7 +379,7 @@ impl Client {
         let stream = self.stream_for_transaction(tx_id).await?;
         self.drop_stream_for_transaction(tx_id);
         stream
-            .execute(Self::into_hrana(Statement::from("COMMIT")))
+            .execute(self.to_hrana(Statement::from("COMMIT"))?)
             .await
             .map(|_| ())
             .map_err(|e| anyhow::anyhow!("{}", e))
@@ -204,9 +390,171 @@ impl Client {
         let stream = self.stream_for_transaction(tx_id).await?;
         self.drop_stream_for_transaction(tx_id);
         stream
-            .execute(Self::into_hrana(Statement::from("ROLLBACK")))
+            .execute(self.to_hrana(Statement::from("ROLLBACK"))?)
             .await
             .map(|_| ())
             .map_err(|e| anyhow::anyhow!("{}", e))
     }
+
+    pub fn drop_transaction(&self, tx_id: u64) {
+        // Closing the stream makes the server roll back its transaction
+        tracing::debug!(
+            "Transaction {tx_id} dropped without commit or rollback, closing its stream"
+        );
+        self.drop_stream_for_transaction(tx_id);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{json, Value as Json};
+    use tokio_tungstenite::tungstenite::Message;
+
+    // Serves hrana connections on a local port. Every `execute` returns a single row
+    // holding the index of the connection it arrived on; `kill_after` closes
+    // the first connection after this many requests.
+    async fn serve(kill_after: Option<usize>) -> String {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for conn_idx in 0.. {
+                let (socket, _) = listener.accept().await.unwrap();
+                tokio::spawn(handle(
+                    socket,
+                    conn_idx,
+                    kill_after.filter(|_| conn_idx == 0),
+                ));
+            }
+        });
+        format!("ws://127.0.0.1:{port}")
+    }
+
+    async fn handle(socket: tokio::net::TcpStream, conn_idx: i64, kill_after: Option<usize>) {
+        let mut ws = tokio_tungstenite::accept_async(socket).await.unwrap();
+        let mut handled = 0;
+        while let Some(Ok(Message::Text(msg))) = ws.next().await {
+            let msg: Json = serde_json::from_str(&msg).unwrap();
+            let resp = match msg["type"].as_str().unwrap() {
+                "hello" => json!({"type": "hello_ok"}),
+                _ => {
+                    if kill_after == Some(handled) {
+                        return;
+                    }
+                    handled += 1;
+                    let response = match msg["request"]["type"].as_str().unwrap() {
+                        "execute" => json!({"type": "execute", "result": {
+                            "cols": [{"name": "conn"}],
+                            "rows": [[{"type": "integer", "value": conn_idx.to_string()}]],
+                            "affected_row_count": 0,
+                            "last_insert_rowid": null,
+                        }}),
+                        other => json!({ "type": other }),
+                    };
+                    json!({"type": "response_ok", "request_id": msg["request_id"], "response": response})
+                }
+            };
+            ws.send(Message::Text(resp.to_string())).await.unwrap();
+        }
+    }
+
+    fn conn_idx(rs: &ResultSet) -> i64 {
+        rs.rows[0].try_get(0).unwrap()
+    }
+
+    #[tokio::test]
+    async fn pool_spreads_parallel_queries() {
+        let url = serve(None).await;
+        let db = Arc::new(Client::with_pool_size(url, "", 4).await.unwrap());
+        assert_eq!(db.pool_size(), 4);
+
+        let tasks: Vec<_> = (0..64)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move { conn_idx(&db.execute("SELECT 1").await.unwrap()) })
+            })
+            .collect();
+        let mut counts = [0; 4];
+        for task in tasks {
+            counts[task.await.unwrap() as usize] += 1;
+        }
+        assert_eq!(counts, [16; 4]);
+    }
+
+    #[tokio::test]
+    async fn pool_pins_transactions() {
+        let url = serve(None).await;
+        let db = Client::with_pool_size(url, "", 3).await.unwrap();
+        let pinned = conn_idx(
+            &db.execute_in_transaction(7, "SELECT 1".into())
+                .await
+                .unwrap(),
+        );
+        for _ in 0..5 {
+            db.execute("SELECT 1").await.unwrap();
+            let rs = db
+                .execute_in_transaction(7, "SELECT 1".into())
+                .await
+                .unwrap();
+            assert_eq!(conn_idx(&rs), pinned);
+        }
+        db.commit_transaction(7).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pool_reconnects_dead_members() {
+        // The first connection dies on its second request: it serves one open_stream,
+        // then goes away without answering the execute
+        let url = serve(Some(1)).await;
+        let db = Client::with_pool_size(url, "", 2).await.unwrap();
+        assert!(db.execute("SELECT 1").await.is_err());
+        // Give the connection task a chance to notice the closed socket
+        tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        let mut seen = vec![];
+        for _ in 0..4 {
+            seen.push(conn_idx(&db.execute("SELECT 1").await.unwrap()));
+        }
+        seen.sort();
+        // Connections 0 and 1 were opened initially, 2 replaced the dead one
+        assert_eq!(seen, [1, 1, 2, 2]);
+    }
+
+    #[tokio::test]
+    async fn close_completes_connections() {
+        let url = serve(None).await;
+        let db = crate::Client::Hrana(Client::with_pool_size(url, "", 2).await.unwrap());
+        db.execute("SELECT 1").await.unwrap();
+        tokio::time::timeout(std::time::Duration::from_secs(5), db.close())
+            .await
+            .expect("connection tasks did not finish")
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn lazy_connects_on_first_use() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("ws://127.0.0.1:{}", listener.local_addr().unwrap().port());
+        let config = Config::new(url.as_str()).unwrap().with_lazy_connect(true);
+        let db = Arc::new(Client::from_config(config).await.unwrap());
+        let accept =
+            || tokio::time::timeout(std::time::Duration::from_millis(100), listener.accept());
+        assert!(accept().await.is_err(), "connected before the first query");
+
+        let queries: Vec<_> = (0..8)
+            .map(|_| {
+                let db = db.clone();
+                tokio::spawn(async move { conn_idx(&db.execute("SELECT 1").await.unwrap()) })
+            })
+            .collect();
+        let (socket, _) = accept().await.unwrap().unwrap();
+        tokio::spawn(handle(socket, 0, None));
+        for query in queries {
+            assert_eq!(query.await.unwrap(), 0);
+        }
+        assert!(
+            accept().await.is_err(),
+            "concurrent first queries connected twice"
+        );
+    }
 }
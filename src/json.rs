@@ -0,0 +1,341 @@
+//! JSON helpers, enabled with the `json` feature: conversion of query results to JSON,
+//! and building queries over JSON documents stored in TEXT columns with SQLite's JSON functions.
+//!
+//! # Examples
+//!
+//! ```
+//! # fn f() -> anyhow::Result<()> {
+//! use libsql_client::{json, Statement};
+//!
+//! let db = libsql_client::SyncClient::in_memory()?;
+//! db.execute("CREATE TABLE events(doc TEXT)")?;
+//! let doc = serde_json::json!({"user": {"name": "Jane", "tags": ["admin"]}});
+//! db.execute(Statement::with_args("INSERT INTO events VALUES (?)", &[json::to_value(&doc)?]))?;
+//!
+//! let name = json::extract("doc", ["user", "name"])?;
+//! let rs = db.execute(format!("SELECT {name} AS name, doc FROM events"))?;
+//! assert_eq!(rs.rows[0].try_column::<&str>("name")?, "Jane");
+//! assert_eq!(rs.rows[0].try_json::<serde_json::Value>("doc")?, doc);
+//! # Ok(())
+//! # }
+//! # f().unwrap();
+//! ```
+
+use base64::Engine;
+use serde_json::Value as Json;
+
+use crate::utils::{quote_ident, sql_literal};
+use crate::{ResultSet, Row, Value};
+
+/// Segment of a JSON path, see [path()]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum PathSegment<'a> {
+    /// Key of an object
+    Key(&'a str),
+    /// Index of an array element
+    Index(usize),
+}
+
+impl<'a> From<&'a str> for PathSegment<'a> {
+    fn from(key: &'a str) -> Self {
+        PathSegment::Key(key)
+    }
+}
+
+impl From<usize> for PathSegment<'_> {
+    fn from(index: usize) -> Self {
+        PathSegment::Index(index)
+    }
+}
+
+/// Builds a JSON path for SQLite's JSON functions from object keys and array indexes,
+/// e.g. `$.user.tags[0]`.
+///
+/// Keys which are not plain identifiers are quoted, e.g. `$."first name"`.
+/// SQLite has no way to escape a double quote inside a quoted key, so keys
+/// containing one are rejected with [`Error::Misuse`](crate::Error::Misuse).
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::json::{path, PathSegment};
+///
+/// assert_eq!(path(["user", "name"]).unwrap(), "$.user.name");
+/// assert_eq!(
+///     path([PathSegment::Key("a.b"), PathSegment::Index(2)]).unwrap(),
+///     "$.\"a.b\"[2]"
+/// );
+/// ```
+pub fn path<'a, S: Into<PathSegment<'a>>>(
+    segments: impl IntoIterator<Item = S>,
+) -> anyhow::Result<String> {
+    let mut path = String::from("$");
+    for segment in segments {
+        match segment.into() {
+            PathSegment::Key(key) if crate::utils::is_identifier(key) => {
+                path.push('.');
+                path.push_str(key);
+            }
+            PathSegment::Key(key) if key.contains('"') => {
+                return Err(crate::Error::Misuse(format!(
+                    "JSON path key {key:?} cannot contain a double quote"
+                ))
+                .into())
+            }
+            PathSegment::Key(key) => path.push_str(&format!(".\"{key}\"")),
+            PathSegment::Index(index) => path.push_str(&format!("[{index}]")),
+        }
+    }
+    Ok(path)
+}
+
+/// Builds a `json_extract(column, path)` expression selecting the value at the [path()]
+/// made of `segments` from the JSON document in `column`.
+///
+/// The column name is quoted, and the path is put into the expression as an SQL string literal,
+/// so neither can break out of it. Objects and arrays are extracted as JSON text,
+/// other values as the matching SQL values.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::json::extract;
+///
+/// assert_eq!(
+///     extract("doc", ["user", "name"]).unwrap(),
+///     "json_extract(\"doc\", '$.user.name')"
+/// );
+/// ```
+pub fn extract<'a, S: Into<PathSegment<'a>>>(
+    column: &str,
+    segments: impl IntoIterator<Item = S>,
+) -> anyhow::Result<String> {
+    Ok(format!(
+        "json_extract({}, {})",
+        quote_ident(column),
+        sql_literal(&Value::from(path(segments)?))
+    ))
+}
+
+/// Serializes `value` as compact JSON into a TEXT [Value], e.g. to bind a document
+/// for a JSON column. Read it back with [`Row::try_json()`].
+///
+/// Keys of a [`serde_json::Value`] object are sorted, so equal documents are stored as equal text.
+pub fn to_value<T: serde::Serialize + ?Sized>(value: &T) -> anyhow::Result<Value> {
+    Ok(Value::from(serde_json::to_string(value)?))
+}
+
+// Integers and floats map to JSON numbers, text to strings, blobs to base64 strings and NULL to null.
+// Floats that JSON cannot represent (NaN and infinities) become null.
+fn value_to_json(value: &Value) -> Json {
+    match value {
+        Value::Null => Json::Null,
+        Value::Integer { value } => Json::from(*value),
+        Value::Float { value } => Json::from(*value),
+        Value::Text { value } => Json::from(value.as_str()),
+        Value::Blob { value } => {
+            Json::from(base64::engine::general_purpose::STANDARD.encode(value))
+        }
+    }
+}
+
+impl Row {
+    /// Parses the TEXT value of the column named `col` as JSON and deserializes it, e.g. into a
+    /// [`serde_json::Value`] or a struct deriving [`serde::Deserialize`]. NULL is parsed as JSON
+    /// `null`, so it can be read into an [Option].
+    ///
+    /// Fails like [`Row::try_column()`] if there's no such column,
+    /// and if the value is neither TEXT nor NULL or is not valid JSON for `T`.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute(r#"select '{"tags": ["a", "b"]}' as doc, null as missing"#).unwrap();
+    /// let doc: serde_json::Value = rs.rows[0].try_json("doc").unwrap();
+    /// assert_eq!(doc["tags"][1], "b");
+    /// let missing: Option<Vec<String>> = rs.rows[0].try_json("missing").unwrap();
+    /// assert_eq!(missing, None);
+    /// ```
+    pub fn try_json<T: serde::de::DeserializeOwned>(&self, col: &str) -> anyhow::Result<T> {
+        let Some(value) = self.value_map.get(col) else {
+            // Reports the missing or ambiguous column
+            return Err(self.try_column::<&str>(col).unwrap_err());
+        };
+        match value {
+            Value::Text { value } => Ok(serde_json::from_str(value)?),
+            Value::Null => Ok(serde_json::from_value(Json::Null)?),
+            value => anyhow::bail!("Cannot parse {value} in column `{col}` as JSON"),
+        }
+    }
+
+    /// Converts the row to a JSON object keyed by column name
+    ///
+    /// Integers and floats become JSON numbers, text becomes strings, blobs become
+    /// base64-encoded strings and NULL becomes `null`.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as id, 'Jane' as name").unwrap();
+    /// let json = rs.rows[0].to_json_object();
+    /// assert_eq!(json["name"], "Jane");
+    /// assert_eq!(serde_json::Value::Object(json), serde_json::json!({"id": 1, "name": "Jane"}));
+    /// ```
+    pub fn to_json_object(&self) -> serde_json::Map<String, Json> {
+        self.value_map
+            .iter()
+            .map(|(column, value)| (column.clone(), value_to_json(value)))
+            .collect()
+    }
+}
+
+impl ResultSet {
+    /// Converts the rows to a JSON array of objects keyed by column name,
+    /// see [`Row::to_json_object()`] for how values are represented.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db
+    ///     .execute("select 1 as id, x'00ff' as data union all select 2, null")
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     rs.to_json(),
+    ///     serde_json::json!([{"id": 1, "data": "AP8="}, {"id": 2, "data": null}])
+    /// );
+    /// ```
+    pub fn to_json(&self) -> Json {
+        self.rows
+            .iter()
+            .map(|row| {
+                let object: serde_json::Map<String, Json> = self
+                    .columns
+                    .iter()
+                    .zip(&row.values)
+                    .map(|(column, value)| (column.clone(), value_to_json(value)))
+                    .collect();
+                Json::Object(object)
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_value_to_json() {
+        assert_eq!(value_to_json(&Value::Null), Json::Null);
+        assert_eq!(value_to_json(&Value::from(-7)), Json::from(-7));
+        assert_eq!(value_to_json(&Value::from(1.5)), Json::from(1.5));
+        assert_eq!(value_to_json(&Value::from(f64::NAN)), Json::Null);
+        assert_eq!(value_to_json(&Value::from("a\"b")), Json::from("a\"b"));
+        assert_eq!(
+            value_to_json(&Value::Blob {
+                value: b"hello".to_vec()
+            }),
+            Json::from("aGVsbG8=")
+        );
+    }
+
+    #[test]
+    fn paths() {
+        assert_eq!(path::<&str>([]).unwrap(), "$");
+        assert_eq!(
+            path([
+                PathSegment::Key("items"),
+                PathSegment::Index(0),
+                PathSegment::Key("it's here"),
+            ])
+            .unwrap(),
+            "$.items[0].\"it's here\""
+        );
+        assert_eq!(
+            extract("my \"doc\"", ["it's"]).unwrap(),
+            "json_extract(\"my \"\"doc\"\"\", '$.\"it''s\"')"
+        );
+        let err = path(["a\"b"]).unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))));
+    }
+
+    #[test]
+    fn canonical_value() {
+        let doc = serde_json::json!({"b": [1, 2.5, null], "a": "x"});
+        let value = to_value(&doc).unwrap();
+        assert!(
+            matches!(&value, Value::Text { value } if value == r#"{"a":"x","b":[1,2.5,null]}"#)
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn extract_nested_field() {
+        #[derive(Debug, PartialEq, serde::Deserialize)]
+        struct Address {
+            city: String,
+            zip: Option<String>,
+        }
+
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, profile TEXT)")
+            .unwrap();
+        let profiles = [
+            serde_json::json!({"name": "Ann", "address": {"city": "Oslo", "zip": "0150"}, "tags": ["x", "y"]}),
+            serde_json::json!({"name": "Bob", "address": {"city": "Bergen"}, "tags": []}),
+        ];
+        for profile in &profiles {
+            db.execute(crate::Statement::with_args(
+                "INSERT INTO users(profile) VALUES (?)",
+                &[to_value(profile).unwrap()],
+            ))
+            .unwrap();
+        }
+
+        let city = extract("profile", ["address", "city"]).unwrap();
+        let address = extract("profile", ["address"]).unwrap();
+        let tag = extract("profile", [PathSegment::Key("tags"), PathSegment::Index(1)]).unwrap();
+        let rs = db
+            .execute(format!(
+                "SELECT {city} AS city, {address} AS address, {tag} AS tag, profile
+                 FROM users WHERE {city} = 'Oslo'"
+            ))
+            .unwrap();
+        assert_eq!(rs.rows.len(), 1);
+        let row = &rs.rows[0];
+        assert_eq!(row.try_column::<&str>("city").unwrap(), "Oslo");
+        assert_eq!(row.try_column::<&str>("tag").unwrap(), "y");
+        assert_eq!(
+            row.try_json::<Address>("address").unwrap(),
+            Address {
+                city: "Oslo".into(),
+                zip: Some("0150".into())
+            }
+        );
+        assert_eq!(
+            row.try_json::<serde_json::Value>("profile").unwrap(),
+            profiles[0]
+        );
+
+        let rs = db
+            .execute(format!("SELECT {tag} AS tag FROM users WHERE id = 2"))
+            .unwrap();
+        assert_eq!(rs.rows[0].try_json::<Option<String>>("tag").unwrap(), None);
+        assert!(rs.rows[0].try_json::<String>("tag").is_err());
+        assert!(rs.rows[0].try_json::<String>("missing").is_err());
+    }
+
+    #[test]
+    fn empty_result_set() {
+        let rs = ResultSet {
+            columns: vec!["x".to_string()],
+            rows: vec![],
+            rows_affected: 0,
+            last_insert_rowid: None,
+            column_origins: vec![],
+            stats: None,
+            is_write: false,
+        };
+        assert_eq!(rs.to_json(), Json::Array(vec![]));
+    }
+}
@@ -12,13 +12,16 @@
 )]
 
 pub mod statement;
-pub use statement::Statement;
+pub use statement::{PreparedStatement, Statement};
 
 pub mod proto;
-pub use proto::{BatchResult, Col, Value};
+pub use proto::{BatchResult, Col, DisplayValue, FixedBlob, SqlBool, Value};
+
+pub mod error;
+pub use error::Error;
 
-#[cfg(feature = "mapping_names_to_values_in_rows")]
 pub mod de;
+pub mod ser;
 
 #[cfg(feature = "workers_backend")]
 pub use worker;
@@ -32,6 +35,20 @@ pub struct Row {
 }
 
 impl<'a> Row {
+    pub(crate) fn new(#[allow(unused)] columns: &[String], values: Vec<Value>) -> Row {
+        #[cfg(feature = "mapping_names_to_values_in_rows")]
+        let value_map = columns
+            .iter()
+            .enumerate()
+            .map(|(i, c)| (c.to_string(), values[i].clone()))
+            .collect();
+        Row {
+            values,
+            #[cfg(feature = "mapping_names_to_values_in_rows")]
+            value_map,
+        }
+    }
+
     /// Try to get a value by index from this row and convert it to the desired type
     ///
     /// Will return an error if the index is invalid or if the value cannot be converted to the
@@ -61,10 +78,36 @@ impl<'a> Row {
         val.try_into().map_err(|x: String| anyhow::anyhow!(x))
     }
 
+    /// Returns `(column, value)` pairs of this row in the order the columns were selected,
+    /// naming values after `columns`, usually [`ResultSet::columns`].
+    ///
+    /// Unlike iterating over `value_map`, the order is stable, e.g. for printing rows
+    /// or diffing them. It doesn't need the `mapping_names_to_values_in_rows` feature.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 as z, 2 as a, 3 as m").unwrap();
+    /// let names: Vec<&str> = rs.rows[0]
+    ///     .ordered_pairs(&rs.columns)
+    ///     .into_iter()
+    ///     .map(|(name, _)| name)
+    ///     .collect();
+    /// assert_eq!(names, ["z", "a", "m"]);
+    /// ```
+    pub fn ordered_pairs<'c>(&'a self, columns: &'c [String]) -> Vec<(&'c str, &'a Value)> {
+        columns
+            .iter()
+            .map(String::as_str)
+            .zip(&self.values)
+            .collect()
+    }
+
     /// Try to get a value given a column name from this row and convert it to the desired type
     ///
     /// Will return an error if the column name is invalid or if the value cannot be converted to the
-    /// desired type
+    /// desired type. With [`DuplicateColumns::Qualify`], a name shared by several columns is
+    /// ambiguous, and the error lists the qualified names to use instead.
     ///
     /// # Examples
     /// ```
@@ -84,10 +127,57 @@ impl<'a> Row {
         &'a self,
         col: &str,
     ) -> anyhow::Result<V> {
-        let val = self
+        let Some(val) = self.value_map.get(col) else {
+            let suffix = format!(".{col}");
+            let mut candidates: Vec<&str> = self
+                .value_map
+                .keys()
+                .filter(|name| name.ends_with(&suffix))
+                .map(String::as_str)
+                .collect();
+            if candidates.len() > 1 {
+                candidates.sort_unstable();
+                anyhow::bail!(
+                    "column `{}` is ambiguous, use one of `{}`",
+                    col,
+                    candidates.join("`, `")
+                );
+            }
+            anyhow::bail!("column `{}` not present", col);
+        };
+        val.try_into().map_err(|x: String| anyhow::anyhow!(x))
+    }
+
+    /// Like [`Row::try_column()`], but matches the column name case-insensitively (ASCII only)
+    ///
+    /// Will return an error if no column matches, if more than one column matches, e.g. both
+    /// `count` and `COUNT`, or if the value cannot be converted to the desired type
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select count(*) as COUNT from (select 1)").unwrap();
+    /// let count: i64 = rs.rows[0].try_column_ci("count").unwrap();
+    /// assert_eq!(count, 1);
+    /// ```
+    #[cfg(feature = "mapping_names_to_values_in_rows")]
+    pub fn try_column_ci<V: TryFrom<&'a Value, Error = String>>(
+        &'a self,
+        col: &str,
+    ) -> anyhow::Result<V> {
+        let mut matches = self
             .value_map
-            .get(col)
+            .iter()
+            .filter(|(name, _)| name.eq_ignore_ascii_case(col));
+        let (_, val) = matches
+            .next()
             .ok_or(anyhow::anyhow!("column `{}` not present", col))?;
+        if matches.next().is_some() {
+            anyhow::bail!(
+                "column `{}` matches more than one column ignoring case",
+                col
+            );
+        }
         val.try_into().map_err(|x: String| anyhow::anyhow!(x))
     }
 }
@@ -112,7 +202,8 @@ impl<'a> Row {
 /// # }
 /// ```
 pub struct ResultSet {
-    /// name of the columns present in this `ResultSet`.
+    /// name of the columns present in this `ResultSet`. Columns with no name reported
+    /// by the server are named by their position, e.g. `"0"`.
     pub columns: Vec<String>,
     /// One entry per row returned from the database. See [Row] for details.
     pub rows: Vec<Row>,
@@ -121,52 +212,260 @@ pub struct ResultSet {
     /// the rowid for last insertion. See <https://www.sqlite.org/c3ref/last_insert_rowid.html> for
     /// details
     pub last_insert_rowid: Option<i64>,
+    /// Origin `(table, column)` of each result column, see [`ResultSet::column_origin()`].
+    /// Only reported by the local backend, empty otherwise.
+    #[serde(default)]
+    pub column_origins: Vec<Option<(String, String)>>,
+    /// Execution statistics of the statement, only collected when enabled with
+    /// [`Config::with_metrics()`]. See [QueryStats] for details.
+    #[serde(default)]
+    pub stats: Option<QueryStats>,
+    #[serde(default)]
+    is_write: bool,
+}
+
+/// Execution statistics of a statement, see [`Config::with_metrics()`]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+pub struct QueryStats {
+    /// Time it took to execute the statement and receive its result, measured by the client.
+    /// Always zero on wasm32 targets, which have no clock.
+    pub elapsed: std::time::Duration,
+    /// Number of rows the statement read. Only reported by the local backend - the protocol
+    /// of remote backends does not carry it.
+    pub rows_read: Option<u64>,
+    /// Number of rows the statement wrote, reported like `rows_read`
+    pub rows_written: Option<u64>,
+}
+
+impl ResultSet {
+    /// Creates a result with given columns and rows, reported as coming from a read, e.g. to
+    /// queue in a mock backend. The other fields are left empty and can be set afterwards.
+    ///
+    /// # Examples
+    /// ```
+    /// use libsql_client::{ResultSet, Value};
+    ///
+    /// let mut rs = ResultSet::new(vec!["id".into()], vec![vec![Value::from(1)]]);
+    /// rs.last_insert_rowid = Some(1);
+    /// assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+    /// assert!(!rs.is_write());
+    /// ```
+    pub fn new(columns: Vec<String>, rows: Vec<Vec<Value>>) -> Self {
+        let rows = rows
+            .into_iter()
+            .map(|values| Row::new(&columns, values))
+            .collect();
+        Self {
+            columns,
+            rows,
+            rows_affected: 0,
+            last_insert_rowid: None,
+            column_origins: vec![],
+            stats: None,
+            is_write: false,
+        }
+    }
+
+    /// Returns true if the statement which produced this `ResultSet` was a write.
+    ///
+    /// Unlike checking `rows_affected > 0`, this tells a write which changed no rows,
+    /// e.g. an `UPDATE` matching nothing, apart from a read. The kind is derived from the
+    /// statement as in [`Statement::is_read_only()`], so anything not known to be a read,
+    /// including transaction control, counts as a write.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// db.execute("CREATE TABLE users(id INTEGER, name TEXT)").unwrap();
+    /// let rs = db.execute("UPDATE users SET name = 'Ann' WHERE id = 1").unwrap();
+    /// assert_eq!(rs.rows_affected, 0);
+    /// assert!(rs.is_write());
+    /// assert!(!db.execute("SELECT * FROM users").unwrap().is_write());
+    /// ```
+    pub fn is_write(&self) -> bool {
+        self.is_write
+    }
+
+    /// Returns the table and column the result column at given index was read from,
+    /// even if it was aliased in the query.
+    ///
+    /// Returns `None` for columns computed from expressions, and for results of
+    /// backends that don't report column metadata - only the local one does.
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// db.execute("CREATE TABLE users(id INTEGER, name TEXT)").unwrap();
+    /// let rs = db.execute("SELECT name AS author, 1 + 1 FROM users").unwrap();
+    /// assert_eq!(rs.column_origin(0), Some(("users", "name")));
+    /// assert_eq!(rs.column_origin(1), None);
+    /// ```
+    pub fn column_origin(&self, idx: usize) -> Option<(&str, &str)> {
+        self.column_origins
+            .get(idx)?
+            .as_ref()
+            .map(|(table, column)| (table.as_str(), column.as_str()))
+    }
+}
+
+#[cfg(feature = "mapping_names_to_values_in_rows")]
+impl ResultSet {
+    // Rebuilds value maps of rows according to the policy for columns sharing a name
+    pub(crate) fn map_duplicate_columns(
+        mut self,
+        policy: client::DuplicateColumns,
+    ) -> anyhow::Result<Self> {
+        use client::DuplicateColumns;
+        use std::collections::HashSet;
+
+        let mut seen = HashSet::new();
+        let duplicate = self.columns.iter().find(|c| !seen.insert(c.as_str()));
+        match (duplicate, policy) {
+            (None, _) | (_, DuplicateColumns::KeepLast) => return Ok(self),
+            (Some(name), DuplicateColumns::Error) => {
+                return Err(Error::Misuse(format!(
+                    "Duplicate column name {name:?}, alias the columns to make them unique"
+                ))
+                .into())
+            }
+            _ => (),
+        }
+        let names: Vec<String> = match policy {
+            DuplicateColumns::Suffix | DuplicateColumns::Qualify => {
+                let duplicates: HashSet<&str> = {
+                    let mut seen = HashSet::new();
+                    self.columns
+                        .iter()
+                        .filter(|c| !seen.insert(c.as_str()))
+                        .map(String::as_str)
+                        .collect()
+                };
+                // Suffixed names must not clash with other columns either
+                let mut taken: HashSet<String> = self.columns.iter().cloned().collect();
+                let mut first = HashSet::new();
+                let columns = self.columns.iter().enumerate().map(|(i, c)| {
+                    let origin = self.column_origins.get(i).and_then(Option::as_ref);
+                    match (policy, origin) {
+                        (DuplicateColumns::Qualify, Some((table, _)))
+                            if duplicates.contains(c.as_str()) =>
+                        {
+                            format!("{table}.{c}")
+                        }
+                        _ => c.clone(),
+                    }
+                });
+                columns
+                    .map(|c| {
+                        if first.insert(c.clone()) {
+                            taken.insert(c.clone());
+                            return c;
+                        }
+                        let name = (1..)
+                            .map(|i| format!("{c}_{i}"))
+                            .find(|name| !taken.contains(name))
+                            .unwrap();
+                        taken.insert(name.clone());
+                        name
+                    })
+                    .collect()
+            }
+            _ => self.columns.clone(),
+        };
+        for row in &mut self.rows {
+            row.value_map.clear();
+            for (name, value) in names.iter().zip(&row.values) {
+                // Only KeepFirst, Suffix and Qualify get here, and the latter produce unique names
+                row.value_map
+                    .entry(name.clone())
+                    .or_insert_with(|| value.clone());
+            }
+        }
+        Ok(self)
+    }
 }
 
 impl std::convert::From<proto::StmtResult> for ResultSet {
     fn from(value: proto::StmtResult) -> Self {
+        // Columns without a name, e.g. when the server omits them, are named by their position
         let columns: Vec<String> = value
             .cols
             .into_iter()
-            .map(|c| c.name.unwrap_or_default())
+            .enumerate()
+            .map(|(i, c)| c.name.unwrap_or_else(|| i.to_string()))
             .collect();
         let rows = value
             .rows
             .into_iter()
-            .map(|values| {
-                #[cfg(feature = "mapping_names_to_values_in_rows")]
-                let value_map = columns
-                    .iter()
-                    .enumerate()
-                    .map(|(i, c)| (c.to_string(), values[i].clone()))
-                    .collect();
-                Row {
-                    values,
-                    #[cfg(feature = "mapping_names_to_values_in_rows")]
-                    value_map,
-                }
-            })
+            .map(|values| Row::new(&columns, values))
             .collect();
         ResultSet {
             columns,
             rows,
             rows_affected: value.affected_row_count,
             last_insert_rowid: value.last_insert_rowid,
+            column_origins: vec![],
+            stats: None,
+            is_write: false,
         }
     }
 }
 
+impl IntoIterator for ResultSet {
+    type Item = Row;
+    type IntoIter = std::vec::IntoIter<Row>;
+
+    /// Iterates over rows of this `ResultSet`
+    ///
+    /// # Examples
+    /// ```
+    /// let db = libsql_client::SyncClient::in_memory().unwrap();
+    /// let rs = db.execute("select 1 union all select 2").unwrap();
+    /// let mut sum = 0;
+    /// for row in rs {
+    ///     sum += row.try_get::<i64>(0).unwrap();
+    /// }
+    /// assert_eq!(sum, 3);
+    /// ```
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.into_iter()
+    }
+}
+
+impl<'a> IntoIterator for &'a ResultSet {
+    type Item = &'a Row;
+    type IntoIter = std::slice::Iter<'a, Row>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.rows.iter()
+    }
+}
+
 pub mod client;
-pub use client::{Client, Config, SyncClient};
+pub use client::{
+    AuthToken, Backend, BatchOpts, Client, ColumnInfo, Config, DuplicateColumns, StatsAge,
+    SyncClient, TextDecoding,
+};
 
 #[cfg(any(
     feature = "reqwest_backend",
     feature = "workers_backend",
     feature = "spin_backend",
+    feature = "fetch_backend",
 ))]
 pub mod http;
+pub mod migrate;
+pub mod spatial;
 pub mod transaction;
-pub use transaction::{SyncTransaction, Transaction};
+pub use transaction::{OwnedTransaction, SyncTransaction, Transaction, TxBehavior, TxSummary};
+
+mod cache;
+mod csv;
+
+#[cfg(feature = "json")]
+pub mod json;
+
+#[cfg(feature = "vector")]
+pub mod vector;
 
 #[cfg(feature = "workers_backend")]
 pub mod workers;
@@ -180,22 +479,107 @@ pub mod local;
 #[cfg(feature = "spin_backend")]
 pub mod spin;
 
+#[cfg(feature = "fetch_backend")]
+pub mod fetch;
+
 #[cfg(feature = "hrana_backend")]
 pub mod hrana;
+#[cfg(all(
+    feature = "test-util",
+    any(
+        feature = "reqwest_backend",
+        feature = "workers_backend",
+        feature = "spin_backend",
+        feature = "fetch_backend",
+    )
+))]
+mod mock;
 mod utils;
 
+/// SQL `NULL`, for binding it explicitly, e.g. with [args!].
+///
+/// # Example
+///
+/// ```rust
+///   # fn f() -> anyhow::Result<()> {
+///   # use crate::libsql_client::{Statement, args, NULL};
+///   let db = libsql_client::SyncClient::in_memory()?;
+///   db.execute("CREATE TABLE users(name TEXT, email TEXT, age INTEGER)")?;
+///   db.execute(Statement::with_args("INSERT INTO users VALUES (?, ?, ?)",
+///       args!("Jane", NULL, 30)))?;
+///   let rs = db.execute("SELECT email IS NULL FROM users")?;
+///   assert_eq!(rs.rows[0].try_get::<i64>(0)?, 1);
+///   # Ok(())
+///   # }
+///   # f().unwrap();
+/// ```
+pub const NULL: Value = Value::Null;
+
+/// Quotes an SQL identifier, e.g. a table or column name, for SQL built at runtime.
+///
+/// Identifiers can't be bound as parameters, so a user-supplied name has to be quoted instead.
+/// The name is wrapped in double quotes, with embedded double quotes doubled, so it can't
+/// end the identifier early. Empty names and names containing NUL bytes fail with
+/// [`Error::Misuse`].
+///
+/// # Example
+///
+/// ```rust
+///   # fn f() -> anyhow::Result<()> {
+///   use libsql_client::quote_identifier;
+///   let db = libsql_client::SyncClient::in_memory()?;
+///   let table = quote_identifier(r#"my "odd" table"#)?;
+///   assert_eq!(table, r#""my ""odd"" table""#);
+///   db.execute(format!("CREATE TABLE {table}(x)"))?;
+///   db.execute(format!("INSERT INTO {table} VALUES (1)"))?;
+///   # Ok(())
+///   # }
+///   # f().unwrap();
+/// ```
+pub fn quote_identifier(name: &str) -> anyhow::Result<String> {
+    if name.is_empty() || name.contains('\0') {
+        return Err(Error::Misuse(format!("Invalid identifier: {name:?}")).into());
+    }
+    Ok(utils::quote_ident(name))
+}
+
+/// Quotes a string as an SQL literal, doubling embedded single quotes.
+///
+/// Prefer binding values as parameters, see [`Statement::with_args()`]; this is for the places
+/// where SQLite doesn't accept them, like the default value in `CREATE TABLE`.
+/// Strings containing NUL bytes fail with [`Error::Misuse`], since SQLite would cut them short.
+///
+/// # Example
+///
+/// ```rust
+///   # fn f() -> anyhow::Result<()> {
+///   use libsql_client::quote_string_literal;
+///   assert_eq!(quote_string_literal("it's")?, "'it''s'");
+///   # Ok(())
+///   # }
+///   # f().unwrap();
+/// ```
+pub fn quote_string_literal(value: &str) -> anyhow::Result<String> {
+    if value.contains('\0') {
+        return Err(Error::Misuse(format!("Invalid string literal: {value:?}")).into());
+    }
+    Ok(format!("'{}'", value.replace('\'', "''")))
+}
+
 /// A macro for passing parameters to statements without having to manually
 /// define their types.
 ///
+/// Use [NULL] to bind a SQL `NULL`.
+///
 /// # Example
 ///
 /// ```rust,no_run
 ///   # async fn f() -> anyhow::Result<()> {
-///   # use crate::libsql_client::{Statement, args};
+///   # use crate::libsql_client::{Statement, args, NULL};
 ///   let db = libsql_client::Client::from_env().await?;
 ///   db.execute(
-///       Statement::with_args("INSERT INTO cart(product_id, product_name, quantity, price) VALUES (?, ?, ?, ?)",
-///       args!(64, "socks", 2, 4.5)),
+///       Statement::with_args("INSERT INTO cart(product_id, product_name, quantity, price, discount) VALUES (?, ?, ?, ?, ?)",
+///       args!(64, "socks", 2, 4.5, NULL)),
 ///   ).await?;
 ///   # Ok(())
 ///   # }
@@ -207,3 +591,74 @@ macro_rules! args {
         &[$($param.into()),+] as &[libsql_client::Value]
     };
 }
+
+/// A macro for passing named parameters to statements without having to manually
+/// define their types. Produces `(String, Value)` pairs for [`Statement::with_named_args()`].
+///
+/// # Example
+///
+/// ```rust
+///   # fn f() -> anyhow::Result<()> {
+///   # use crate::libsql_client::{Statement, named_args};
+///   let db = libsql_client::SyncClient::in_memory()?;
+///   db.execute("CREATE TABLE users(id INTEGER, name TEXT)")?;
+///   db.execute(
+///       Statement::with_named_args("INSERT INTO users(id, name) VALUES (:id, :name)",
+///       named_args!{ ":id" => 5, ":name" => "Jane" }),
+///   )?;
+///   let rs = db.execute("SELECT id, name FROM users")?;
+///   assert_eq!(rs.rows[0].try_get::<i64>(0)?, 5);
+///   assert_eq!(rs.rows[0].try_get::<&str>(1)?, "Jane");
+///   # Ok(())
+///   # }
+///   # f().unwrap();
+/// ```
+#[macro_export]
+macro_rules! named_args {
+    () => { &[] as &[(String, libsql_client::Value)] };
+    ($($name:expr => $param:expr),+ $(,)?) => {
+        &[$(($name.to_string(), $param.into())),+] as &[(String, libsql_client::Value)]
+    };
+}
+
+#[cfg(all(test, feature = "mapping_names_to_values_in_rows"))]
+mod tests {
+    use super::*;
+
+    fn row(columns: &[&str]) -> Row {
+        let columns: Vec<String> = columns.iter().map(|c| c.to_string()).collect();
+        let values = (0..columns.len() as i64).map(Value::from).collect();
+        Row::new(&columns, values)
+    }
+
+    #[test]
+    fn try_column_ci() {
+        let row = row(&["Id", "COUNT", "user_Name"]);
+        assert_eq!(row.try_column_ci::<i64>("id").unwrap(), 0);
+        assert_eq!(row.try_column_ci::<i64>("count").unwrap(), 1);
+        assert_eq!(row.try_column_ci::<i64>("USER_NAME").unwrap(), 2);
+        assert!(row.try_column::<i64>("count").is_err());
+        let err = row.try_column_ci::<i64>("missing").unwrap_err();
+        assert_eq!(err.to_string(), "column `missing` not present");
+    }
+
+    #[test]
+    fn ordered_pairs() {
+        let columns = ["zeta", "alpha", "mu", "beta"].map(String::from);
+        let row = Row::new(&columns, (0..4).map(Value::from).collect());
+        let pairs: Vec<(&str, i64)> = row
+            .ordered_pairs(&columns)
+            .into_iter()
+            .map(|(name, value)| (name, value.try_into().unwrap()))
+            .collect();
+        assert_eq!(pairs, [("zeta", 0), ("alpha", 1), ("mu", 2), ("beta", 3)]);
+    }
+
+    #[test]
+    fn try_column_ci_ambiguous() {
+        let row = row(&["count", "COUNT"]);
+        assert_eq!(row.try_column::<i64>("COUNT").unwrap(), 1);
+        let err = row.try_column_ci::<i64>("Count").unwrap_err();
+        assert!(err.to_string().contains("more than one column"));
+    }
+}
@@ -1,23 +1,62 @@
-use crate::{proto, proto::StmtResult, BatchResult, Col, ResultSet, Statement, Value};
+use crate::{
+    proto, proto::StmtResult, BatchResult, Col, ResultSet, Row, Statement, TextDecoding, Value,
+};
 use anyhow::Result;
-use sqlite3_parser::ast::{Cmd, Stmt};
+#[cfg(feature = "sql_parsing")]
+use sqlite3_parser::ast::{Cmd, Name, QualifiedName, Stmt};
+#[cfg(feature = "sql_parsing")]
 use sqlite3_parser::lexer::sql::Parser;
 
+#[cfg(feature = "sql_parsing")]
 use fallible_iterator::FallibleIterator;
 
 /// Database client. This is the main structure used to
 /// communicate with the database.
 pub struct Client {
+    path: String,
     db: libsql::Database,
+    // Statements kept prepared for PreparedStatement, by SQL. Declared before the connection,
+    // so that they're finalized before it closes.
+    statements: std::sync::Mutex<std::collections::HashMap<String, libsql::Statement>>,
     conn: libsql::Connection,
+    pub(crate) state: std::sync::Arc<crate::client::ClientState>,
 }
 
 impl std::fmt::Debug for Client {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        f.debug_struct("local::Client").finish()
+        f.debug_struct("local::Client")
+            .field("path", &self.path)
+            .finish()
     }
 }
 
+/// Converts a libsql error to [crate::Error], keeping the SQLite result code if there is one
+fn database_error(e: libsql::Error) -> anyhow::Error {
+    let code = match &e {
+        libsql::Error::PrepareFailed(code, ..)
+        | libsql::Error::FetchRowFailed(code, _)
+        | libsql::Error::LibError(code, _) => crate::error::sqlite_code_name(*code),
+        _ => None,
+    };
+    crate::Error::from_code(e.to_string(), code.map(str::to_string)).into()
+}
+
+// Table and column a result column was read from, None for expressions
+type ColumnOrigin = Option<(String, String)>;
+
+// Identifiers of libSQL's statement counters, LIBSQL_STMTSTATUS_ROWS_READ and
+// LIBSQL_STMTSTATUS_ROWS_WRITTEN, for sqlite3_stmt_status()
+const STMTSTATUS_ROWS_READ: i32 = 1025;
+const STMTSTATUS_ROWS_WRITTEN: i32 = 1026;
+
+// Details of a successfully executed statement which don't fit in the protocol's StmtResult
+struct StepMeta {
+    origins: Vec<ColumnOrigin>,
+    rows_read: u64,
+    rows_written: u64,
+    is_write: bool,
+}
+
 struct ValueWrapper(Value);
 
 impl From<ValueWrapper> for libsql::Value {
@@ -44,22 +83,560 @@ impl From<libsql::Value> for ValueWrapper {
     }
 }
 
+// Reads a value of the current row. TEXT is read as raw bytes, because libsql
+// would silently replace invalid UTF-8 - see Config::with_text_decoding()
+fn read_value(row: &libsql::Row, idx: usize, decoding: TextDecoding) -> Result<Value> {
+    let idx = idx as i32;
+    if let Ok(libsql::ValueType::Text) = row.column_type(idx) {
+        if let libsql::ValueRef::Text(bytes) = row.get_ref(idx).map_err(database_error)? {
+            let value = match decoding {
+                TextDecoding::Strict => std::str::from_utf8(bytes)
+                    .map_err(|e| anyhow::anyhow!("Invalid UTF-8 in TEXT column {idx}: {e}"))?
+                    .to_string(),
+                TextDecoding::Lossy => String::from_utf8_lossy(bytes).into_owned(),
+            };
+            return Ok(Value::Text { value });
+        }
+    }
+    row.get_value(idx)
+        .map(|v| ValueWrapper::from(v).0)
+        .map_err(database_error)
+}
+
+/// Run-time limit of a connection, see [`Client::set_limit()`].
+///
+/// Each variant maps to the `SQLITE_LIMIT_*` constant of the same name,
+/// see <https://www.sqlite.org/c3ref/c_limit_attached.html>.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum SqliteLimit {
+    /// Maximum size of a string or BLOB, or of a row, in bytes
+    Length,
+    /// Maximum length of an SQL statement, in bytes
+    SqlLength,
+    /// Maximum number of columns of a table, index or view, or in a result set
+    Column,
+    /// Maximum depth of the parse tree of an expression
+    ExprDepth,
+    /// Maximum number of terms in a compound `SELECT`
+    CompoundSelect,
+    /// Maximum number of virtual machine instructions of a statement
+    VdbeOp,
+    /// Maximum number of arguments of a function
+    FunctionArg,
+    /// Maximum number of attached databases
+    Attached,
+    /// Maximum length of the pattern of a `LIKE` or `GLOB` operator
+    LikePatternLength,
+    /// Maximum index of a bound parameter
+    VariableNumber,
+    /// Maximum depth of recursion of triggers
+    TriggerDepth,
+}
+
+impl SqliteLimit {
+    fn id(self) -> std::os::raw::c_int {
+        use libsql::ffi;
+
+        (match self {
+            Self::Length => ffi::SQLITE_LIMIT_LENGTH,
+            Self::SqlLength => ffi::SQLITE_LIMIT_SQL_LENGTH,
+            Self::Column => ffi::SQLITE_LIMIT_COLUMN,
+            Self::ExprDepth => ffi::SQLITE_LIMIT_EXPR_DEPTH,
+            Self::CompoundSelect => ffi::SQLITE_LIMIT_COMPOUND_SELECT,
+            Self::VdbeOp => ffi::SQLITE_LIMIT_VDBE_OP,
+            Self::FunctionArg => ffi::SQLITE_LIMIT_FUNCTION_ARG,
+            Self::Attached => ffi::SQLITE_LIMIT_ATTACHED,
+            Self::LikePatternLength => ffi::SQLITE_LIMIT_LIKE_PATTERN_LENGTH,
+            Self::VariableNumber => ffi::SQLITE_LIMIT_VARIABLE_NUMBER,
+            Self::TriggerDepth => ffi::SQLITE_LIMIT_TRIGGER_DEPTH,
+        }) as std::os::raw::c_int
+    }
+}
+
+/// Outcome of [`Client::flush()`]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncResult {
+    /// Number of frames fetched from the primary and applied locally
+    pub frames_synced: usize,
+}
+
+/// Handle controlling a sync loop created with [`Client::background_sync()`].
+///
+/// It can be cloned to control the loop from multiple places. The loop stops once
+/// [`SyncHandle::stop()`] is called or all handles are dropped.
+#[derive(Clone, Debug)]
+pub struct SyncHandle {
+    commands: futures::channel::mpsc::UnboundedSender<SyncCommand>,
+}
+
+#[derive(Debug)]
+enum SyncCommand {
+    Now,
+    Stop,
+}
+
+impl SyncHandle {
+    /// Makes the loop sync right away instead of waiting for the rest of the interval
+    pub fn sync_now(&self) {
+        self.commands.unbounded_send(SyncCommand::Now).ok();
+    }
+
+    /// Stops the loop after the sync in progress, if any, is done
+    pub fn stop(&self) {
+        self.commands.unbounded_send(SyncCommand::Stop).ok();
+    }
+}
+
+// Calls `sync` every `interval`, or right away when asked to, until stopped
+async fn sync_loop<F, Fut>(
+    interval: std::time::Duration,
+    mut commands: futures::channel::mpsc::UnboundedReceiver<SyncCommand>,
+    mut sync: F,
+) where
+    F: FnMut() -> Fut,
+    Fut: std::future::Future<Output = Result<usize>>,
+{
+    use futures::future::Either;
+    use futures::StreamExt;
+
+    loop {
+        let tick = std::pin::pin!(crate::utils::sleep(interval));
+        match futures::future::select(commands.next(), tick).await {
+            Either::Left((None | Some(SyncCommand::Stop), _)) => break,
+            Either::Left((Some(SyncCommand::Now), _)) | Either::Right(_) => (),
+        }
+        match sync().await {
+            Ok(frames) => tracing::debug!("Background sync applied {frames} frames"),
+            Err(e) => tracing::warn!("Background sync failed: {e}"),
+        }
+    }
+    tracing::debug!("Background sync stopped");
+}
+
+/// Progress of a sync, reported by [`Client::sync_progress()`] after each batch of frames
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct SyncProgress {
+    /// Number of frames fetched and applied in this batch
+    pub frames: usize,
+    /// Number of frames fetched and applied since the sync started
+    pub total_frames: usize,
+    /// Approximate number of bytes transferred since the sync started
+    pub total_bytes: u64,
+}
+
+// Size of a replicated frame: a 24-byte header and a 4KiB page
+const FRAME_SIZE: u64 = 24 + 4096;
+
+// Parameters of a statement, bound once it's prepared so that positional
+// and named ones can be mixed
+struct Bindings {
+    positional: Vec<libsql::Value>,
+    named: Vec<(String, libsql::Value)>,
+}
+
+impl Bindings {
+    fn bind(self, stmt: &libsql::Statement) -> Result<()> {
+        if !self.positional.is_empty() && !self.named.is_empty() {
+            self.check_mixed(stmt)?;
+        }
+        stmt.bind(&libsql::Params::Positional(self.positional));
+        stmt.bind(&libsql::Params::Named(self.named));
+        Ok(())
+    }
+
+    // Positional arguments take the first placeholders in order, so none of them
+    // may be a named one, and every named argument needs a placeholder of its own
+    fn check_mixed(&self, stmt: &libsql::Statement) -> Result<()> {
+        let count = stmt.parameter_count();
+        let is_named = |name: &str| name.starts_with([':', '@', '$']);
+        if self.positional.len() > count {
+            return Err(crate::Error::Misuse(format!(
+                "{} positional arguments given for {count} parameters",
+                self.positional.len()
+            ))
+            .into());
+        }
+        for idx in 1..=self.positional.len() {
+            if let Some(name) = stmt.parameter_name(idx as i32).filter(|n| is_named(n)) {
+                return Err(crate::Error::Misuse(format!(
+                    "positional argument {idx} would bind named parameter {name}"
+                ))
+                .into());
+            }
+        }
+        for (name, _) in &self.named {
+            let idx = (1..=count).find(|&i| stmt.parameter_name(i as i32) == Some(name));
+            match idx {
+                Some(idx) if idx > self.positional.len() => (),
+                Some(_) => {
+                    return Err(crate::Error::Misuse(format!(
+                        "named parameter {name} is already bound by a positional argument"
+                    ))
+                    .into())
+                }
+                None => {
+                    return Err(
+                        crate::Error::Misuse(format!("no such named parameter: {name}")).into(),
+                    )
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+// Maximum number of statements kept prepared for PreparedStatement
+const STATEMENT_CACHE_SIZE: usize = 64;
+
+// A prepared statement which goes back to the client's cache when dropped, if it came from one.
+// Taking it out of the cache while it runs keeps concurrent executions from sharing it.
+struct StatementGuard<'a> {
+    client: &'a Client,
+    sql: Option<String>,
+    stmt: libsql::Statement,
+}
+
+impl std::ops::Deref for StatementGuard<'_> {
+    type Target = libsql::Statement;
+
+    fn deref(&self) -> &Self::Target {
+        &self.stmt
+    }
+}
+
+impl Drop for StatementGuard<'_> {
+    fn drop(&mut self) {
+        let Some(sql) = self.sql.take() else {
+            return;
+        };
+        // Bindings survive a reset, so clear them for the next execution
+        self.stmt.reset();
+        for idx in 1..=self.stmt.parameter_count() {
+            self.stmt.bind_value(idx as i32, libsql::ValueRef::Null);
+        }
+        let mut statements = self.client.statements.lock().unwrap();
+        if statements.len() < STATEMENT_CACHE_SIZE {
+            statements.insert(sql, self.stmt.clone());
+        }
+    }
+}
+
+/// Iterator over rows of a statement executed with [`Client::execute_lazy()`].
+///
+/// Rows are fetched from the database one at a time, as the iterator advances.
+/// The iterator owns the underlying prepared statement, so it does not borrow the client;
+/// the statement is finalized once the iterator is dropped.
+pub struct RowIter {
+    columns: Vec<String>,
+    rows: libsql::Rows,
+    text_decoding: TextDecoding,
+    done: bool,
+}
+
+impl RowIter {
+    /// Names of the columns returned by the statement
+    pub fn columns(&self) -> &[String] {
+        &self.columns
+    }
+}
+
+impl Iterator for RowIter {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if self.done {
+            return None;
+        }
+        let row = match self.rows.next() {
+            Ok(Some(row)) => row,
+            Ok(None) => {
+                self.done = true;
+                return None;
+            }
+            Err(e) => {
+                self.done = true;
+                return Some(Err(database_error(e)));
+            }
+        };
+        let values = (0..self.columns.len())
+            .map(|i| read_value(&row, i, self.text_decoding))
+            .collect::<Result<Vec<_>>>();
+        Some(values.map(|values| Row::new(&self.columns, values)))
+    }
+}
+
+impl std::fmt::Debug for RowIter {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("local::RowIter")
+            .field("columns", &self.columns)
+            .field("done", &self.done)
+            .finish()
+    }
+}
+
+/// Handle for incremental I/O on a single BLOB value, opened with [`Client::open_blob()`].
+///
+/// Implements [std::io::Read], [std::io::Write] and [std::io::Seek], so large blobs can be
+/// streamed without loading them into memory. With the `async_blob` feature it also implements
+/// the [futures::io] counterparts, which perform the same (blocking) I/O.
+/// The size of a blob is fixed - writes cannot extend it, so preallocate it with `zeroblob(N)`.
+/// If the row is modified or deleted while the handle is open, further I/O fails with
+/// `SQLITE_ABORT`.
+pub struct Blob<'a> {
+    handle: *mut libsql::ffi::sqlite3_blob,
+    client: &'a Client,
+    len: u64,
+    pos: u64,
+}
+
+impl Blob<'_> {
+    /// Size of the blob in bytes
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+
+    /// Returns true if the blob is empty
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    fn io_error(&self, rc: std::os::raw::c_int) -> std::io::Error {
+        std::io::Error::other(self.client.ffi_error(rc))
+    }
+}
+
+impl std::io::Read for Blob<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let n = (buf.len() as u64).min(self.len.saturating_sub(self.pos)) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        // SAFETY: the handle is open and buf holds at least n bytes
+        let rc = unsafe {
+            libsql::ffi::sqlite3_blob_read(
+                self.handle,
+                buf.as_mut_ptr() as *mut std::ffi::c_void,
+                n as std::os::raw::c_int,
+                self.pos as std::os::raw::c_int,
+            )
+        };
+        if rc != libsql::ffi::SQLITE_OK as std::os::raw::c_int {
+            return Err(self.io_error(rc));
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl std::io::Write for Blob<'_> {
+    fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+        let n = (buf.len() as u64).min(self.len.saturating_sub(self.pos)) as usize;
+        if n == 0 {
+            return Ok(0);
+        }
+        // SAFETY: the handle is open and buf holds at least n bytes
+        let rc = unsafe {
+            libsql::ffi::sqlite3_blob_write(
+                self.handle,
+                buf.as_ptr() as *const std::ffi::c_void,
+                n as std::os::raw::c_int,
+                self.pos as std::os::raw::c_int,
+            )
+        };
+        if rc != libsql::ffi::SQLITE_OK as std::os::raw::c_int {
+            return Err(self.io_error(rc));
+        }
+        self.pos += n as u64;
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> std::io::Result<()> {
+        Ok(())
+    }
+}
+
+impl std::io::Seek for Blob<'_> {
+    fn seek(&mut self, pos: std::io::SeekFrom) -> std::io::Result<u64> {
+        let pos = match pos {
+            std::io::SeekFrom::Start(offset) => Some(offset),
+            std::io::SeekFrom::End(offset) => self.len.checked_add_signed(offset),
+            std::io::SeekFrom::Current(offset) => self.pos.checked_add_signed(offset),
+        };
+        match pos {
+            Some(pos) if pos <= self.len => {
+                self.pos = pos;
+                Ok(pos)
+            }
+            _ => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                "seek outside of the blob",
+            )),
+        }
+    }
+}
+
+#[cfg(feature = "async_blob")]
+impl futures::io::AsyncRead for Blob<'_> {
+    fn poll_read(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &mut [u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        std::task::Poll::Ready(std::io::Read::read(self.get_mut(), buf))
+    }
+}
+
+#[cfg(feature = "async_blob")]
+impl futures::io::AsyncWrite for Blob<'_> {
+    fn poll_write(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        buf: &[u8],
+    ) -> std::task::Poll<std::io::Result<usize>> {
+        std::task::Poll::Ready(std::io::Write::write(self.get_mut(), buf))
+    }
+
+    fn poll_flush(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+
+    fn poll_close(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+    ) -> std::task::Poll<std::io::Result<()>> {
+        std::task::Poll::Ready(Ok(()))
+    }
+}
+
+#[cfg(feature = "async_blob")]
+impl futures::io::AsyncSeek for Blob<'_> {
+    fn poll_seek(
+        self: std::pin::Pin<&mut Self>,
+        _cx: &mut std::task::Context<'_>,
+        pos: std::io::SeekFrom,
+    ) -> std::task::Poll<std::io::Result<u64>> {
+        std::task::Poll::Ready(std::io::Seek::seek(self.get_mut(), pos))
+    }
+}
+
+impl Drop for Blob<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the handle is open, and it's not used after closing
+        unsafe { libsql::ffi::sqlite3_blob_close(self.handle) };
+    }
+}
+
+impl std::fmt::Debug for Blob<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("local::Blob")
+            .field("len", &self.len)
+            .field("pos", &self.pos)
+            .finish()
+    }
+}
+
 impl Client {
     /// Establishes a database client.
     ///
     /// # Arguments
     /// * `path` - path of the local database
     pub fn new(path: impl Into<String>) -> anyhow::Result<Self> {
-        let db = libsql::Database::open(path.into())?;
+        let path = path.into();
+        let db = libsql::Database::open(path.clone())?;
         let conn = db.connect()?;
-        Ok(Self { db, conn })
+        Ok(Self {
+            path,
+            db,
+            statements: Default::default(),
+            conn,
+            state: Default::default(),
+        })
+    }
+
+    /// Connects to an existing database in read-only mode, with `SQLITE_OPEN_READONLY`.
+    ///
+    /// Statements which would write to the database fail with `SQLITE_READONLY` instead,
+    /// and so does opening a database file which doesn't exist, since it won't be created.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use libsql_client::local::Client;
+    ///
+    /// Client::new("/tmp/example_read_only.db")?.execute("CREATE TABLE IF NOT EXISTS t(x)")?;
+    /// let db = Client::open_read_only("/tmp/example_read_only.db")?;
+    /// db.execute("SELECT * FROM t")?;
+    /// assert!(db.execute("INSERT INTO t VALUES (1)").is_err());
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn open_read_only(path: impl Into<String>) -> anyhow::Result<Self> {
+        let path = path.into();
+        let c_path = std::ffi::CString::new(path.as_str())?;
+        let mut raw = std::ptr::null_mut();
+        let rc = unsafe {
+            libsql::ffi::sqlite3_open_v2(
+                c_path.as_ptr(),
+                &mut raw,
+                libsql::ffi::SQLITE_OPEN_READONLY as std::os::raw::c_int,
+                std::ptr::null(),
+            )
+        };
+        // The handle is allocated even if opening fails, and is closed once the connection drops
+        let client = Self {
+            db: libsql::Database::open(path.clone())?,
+            path,
+            statements: Default::default(),
+            conn: libsql::Connection::from_handle(raw),
+            state: Default::default(),
+        };
+        if rc != libsql::ffi::SQLITE_OK as std::os::raw::c_int {
+            return Err(client.ffi_error(rc).into());
+        }
+        Ok(client)
     }
 
     /// Establishes a new in-memory database and connects to it.
     pub fn in_memory() -> anyhow::Result<Self> {
-        let db = libsql::Database::open(":memory:")?;
-        let conn = db.connect()?;
-        Ok(Self { db, conn })
+        Self::new(":memory:")
+    }
+
+    /// Connects to a named in-memory database, shared by all connections opened with the same name
+    /// in this process, e.g. to test how several clients interact.
+    ///
+    /// The database lives as long as at least one connection to it stays open - once the last one
+    /// is dropped, its contents are gone. The name may not contain `?`, `#`, `%` or `/`,
+    /// since it becomes part of a `file:{name}?mode=memory&cache=shared` URI.
+    /// When linked against a system library (see the README), it must support URI filenames.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use libsql_client::local::Client;
+    ///
+    /// let writer = Client::in_memory_named("example")?;
+    /// let reader = Client::in_memory_named("example")?;
+    /// writer.execute("CREATE TABLE t(x)")?;
+    /// writer.execute("INSERT INTO t VALUES (42)")?;
+    /// let rs = reader.execute("SELECT x FROM t")?;
+    /// assert_eq!(rs.rows[0].try_get::<i64>(0)?, 42);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn in_memory_named(name: &str) -> anyhow::Result<Self> {
+        if name.is_empty() || name.contains(['?', '#', '%', '/']) {
+            return Err(
+                crate::Error::Misuse(format!("Invalid in-memory database name {name:?}")).into(),
+            );
+        }
+        Self::new(format!("file:{name}?mode=memory&cache=shared"))
     }
 
     pub fn from_env() -> anyhow::Result<Self> {
@@ -73,10 +650,386 @@ impl Client {
         Self::new(path)
     }
 
+    /// Returns the path of the local database
+    pub fn endpoint(&self) -> &str {
+        &self.path
+    }
+
+    /// Closes the connection to the database.
+    ///
+    /// Statements kept prepared for [PreparedStatement](crate::PreparedStatement) are finalized
+    /// first, so that nothing keeps the connection open. It's the same as dropping the client,
+    /// but happens at a well-defined point.
+    pub fn close(self) -> Result<()> {
+        self.statements.lock().unwrap().clear();
+        drop(self);
+        Ok(())
+    }
+
+    /// Returns the version of the SQLite library this backend is linked against.
+    ///
+    /// When built with `LIBSQL_LIB_DIR` or a similar variable this is the version of the
+    /// system library, otherwise it's the one bundled with the `libsql` crate.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let db = libsql_client::local::Client::in_memory().unwrap();
+    /// assert!(!db.sqlite_version().unwrap().is_empty());
+    /// ```
+    pub fn sqlite_version(&self) -> Result<String> {
+        let rs = self.execute("SELECT sqlite_version()")?;
+        let row = rs
+            .rows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("sqlite_version() returned no rows"))?;
+        row.try_get::<&str>(0).map(|v| v.to_string())
+    }
+
+    /// Returns the size of the page cache of the main database, see [`Client::set_cache_size()`]
+    /// for the meaning of the sign.
+    pub fn cache_size(&self) -> Result<i64> {
+        let rs = self.execute("PRAGMA cache_size")?;
+        let row = rs
+            .rows
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("PRAGMA cache_size returned no rows"))?;
+        row.try_get(0)
+    }
+
+    /// Sets the size of the page cache of the main database for this connection.
+    ///
+    /// A positive size is a number of pages, so the memory used depends on the page size
+    /// (4 KiB by default). A negative size is an amount of memory in KiB, regardless
+    /// of the page size, e.g. `-65536` is a 64 MiB cache. SQLite reports the size back
+    /// the way it was set. The default is `-2000`, around 2 MB.
+    ///
+    /// Larger caches help read-heavy workloads keep their hot pages in memory.
+    /// See also [`Config::with_cache_size()`](crate::Config::with_cache_size).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.set_cache_size(-64 * 1024)?; // 64 MiB
+    /// assert_eq!(db.cache_size()?, -65536);
+    /// db.set_cache_size(1000)?; // 1000 pages
+    /// assert_eq!(db.cache_size()?, 1000);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn set_cache_size(&self, size: i64) -> Result<()> {
+        self.execute(format!("PRAGMA cache_size = {size}"))
+            .map(|_| ())
+    }
+
+    /// Returns the current value of a run-time limit of this connection
+    pub fn limit(&self, limit: SqliteLimit) -> i32 {
+        unsafe { libsql::ffi::sqlite3_limit(self.conn.handle(), limit.id(), -1) }
+    }
+
+    /// Lowers a run-time limit of this connection, e.g. to harden it against untrusted SQL,
+    /// and returns its previous value.
+    ///
+    /// Statements going over the limit fail once they're prepared. SQLite silently caps
+    /// the value at the limit it was compiled with, so it can't be raised past that;
+    /// a negative value fails with [`Error::Misuse`](crate::Error::Misuse).
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use libsql_client::local::SqliteLimit;
+    ///
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.set_limit(SqliteLimit::Attached, 0)?;
+    /// assert!(db.execute("ATTACH ':memory:' AS other").is_err());
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn set_limit(&self, limit: SqliteLimit, value: i32) -> Result<i32> {
+        if value < 0 {
+            return Err(
+                crate::Error::Misuse(format!("Invalid value of limit {limit:?}: {value}")).into(),
+            );
+        }
+        Ok(unsafe { libsql::ffi::sqlite3_limit(self.conn.handle(), limit.id(), value) })
+    }
+
+    /// Returns true if the database was opened as an embedded replica of a remote primary,
+    /// so it can be synced with it
+    pub fn is_replica(&self) -> bool {
+        self.db.replication_ctx.is_some()
+    }
+
+    /// Creates a loop syncing the replica with the primary every `interval`,
+    /// logging the number of applied frames and errors, which don't stop the loop.
+    ///
+    /// Returns a [SyncHandle] to control the loop and the loop itself, which only runs
+    /// while it's polled - e.g. spawn it with `tokio::task::spawn_local()`, or run it
+    /// concurrently with the rest of the application with `futures::join!`.
+    ///
+    /// Fails with [`crate::Error::Misuse`] if the database is not a replica.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() {
+    /// let db = libsql_client::local::Client::in_memory().unwrap();
+    /// // Not a replica, there's nothing to sync with
+    /// assert!(db.background_sync(std::time::Duration::from_secs(1)).is_err());
+    /// # }
+    /// # f();
+    /// ```
+    pub fn background_sync(
+        &self,
+        interval: std::time::Duration,
+    ) -> Result<(SyncHandle, impl std::future::Future<Output = ()> + '_)> {
+        if !self.is_replica() {
+            return Err(crate::Error::Misuse(
+                "Background sync needs a database opened as an embedded replica".into(),
+            )
+            .into());
+        }
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let handle = SyncHandle { commands: tx };
+        Ok((handle, sync_loop(interval, rx, move || self.sync())))
+    }
+
     pub async fn sync(&self) -> anyhow::Result<usize> {
         self.db.sync().await.map_err(|e| anyhow::anyhow!("{}", e))
     }
 
+    /// Triggers a sync with the primary and waits until it's applied locally,
+    /// e.g. right before a graceful shutdown.
+    ///
+    /// Once this returns `Ok`, all frames the primary had handed out to the replicator
+    /// are written to the local database file and will be visible to subsequent reads,
+    /// including after a restart. Replication only goes from the primary to the replica:
+    /// writes executed directly against the local database are not sent anywhere,
+    /// so flushing does not make them durable on the primary.
+    ///
+    /// Fails if the database was not opened as a replica.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// let db = libsql_client::local::Client::in_memory().unwrap();
+    /// // Not a replica, there's nothing to sync with
+    /// assert!(db.flush().await.is_err());
+    /// # }
+    /// ```
+    pub async fn flush(&self) -> Result<SyncResult> {
+        let frames_synced = self.sync().await?;
+        tracing::debug!("Flushed {frames_synced} frames from the primary");
+        Ok(SyncResult { frames_synced })
+    }
+
+    /// Syncs with the primary until caught up, reporting progress after each batch of frames,
+    /// e.g. to show a progress bar while a new replica downloads the initial snapshot.
+    ///
+    /// The stream ends once a batch brings no new frames, or right after yielding an error.
+    /// The replicator does not report the total amount of data left, so progress is only
+    /// known in terms of what has already been transferred.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # async fn f() {
+    /// use futures::StreamExt;
+    /// let db = libsql_client::local::Client::in_memory().unwrap();
+    /// let mut progress = std::pin::pin!(db.sync_progress());
+    /// while let Some(progress) = progress.next().await {
+    ///     match progress {
+    ///         Ok(p) => println!("{} frames, {} bytes", p.total_frames, p.total_bytes),
+    ///         // Not a replica, there's nothing to sync with
+    ///         Err(e) => println!("Sync failed: {e}"),
+    ///     }
+    /// }
+    /// # }
+    /// # futures::executor::block_on(f());
+    /// ```
+    pub fn sync_progress(&self) -> impl futures::Stream<Item = Result<SyncProgress>> + '_ {
+        futures::stream::unfold(Some(0usize), move |total_frames| async move {
+            let total_frames = total_frames?;
+            match self.sync().await {
+                Ok(0) => None,
+                Ok(frames) => {
+                    let total_frames = total_frames + frames;
+                    let progress = SyncProgress {
+                        frames,
+                        total_frames,
+                        total_bytes: total_frames as u64 * FRAME_SIZE,
+                    };
+                    Some((Ok(progress), Some(total_frames)))
+                }
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+
+    /// Attaches another database file to this connection under `alias`,
+    /// so that its tables can be queried as `alias.table`, e.g. in joins with the main database.
+    ///
+    /// `path` is either a plain file path or a `file:///` URL, like the one used to open the main
+    /// database. The alias must be a plain identifier (letters, digits and underscores, not starting
+    /// with a digit) and can't be `main` or `temp`.
+    ///
+    /// Attached databases participate in transactions like the main one: a transaction that writes
+    /// to several of them commits atomically, unless the main database is in WAL mode or `:memory:`,
+    /// in which case each database commits atomically on its own.
+    /// Databases can't be attached or detached inside a transaction.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.attach(":memory:", "aux")?;
+    /// db.execute("CREATE TABLE aux.t(x)")?;
+    /// db.detach("aux")?;
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn attach(&self, path: &str, alias: &str) -> Result<()> {
+        validate_alias(alias)?;
+        let path = path.strip_prefix("file:///").unwrap_or(path);
+        self.execute(Statement::with_args(
+            format!("ATTACH DATABASE ? AS \"{alias}\""),
+            &[path],
+        ))
+        .map(|_| ())
+    }
+
+    /// Detaches a database previously attached with [`Client::attach()`]
+    pub fn detach(&self, alias: &str) -> Result<()> {
+        validate_alias(alias)?;
+        self.execute(format!("DETACH DATABASE \"{alias}\""))
+            .map(|_| ())
+    }
+
+    /// Opens a BLOB for incremental I/O, without loading it into memory.
+    ///
+    /// The blob is located in column `column` of the row with given `rowid` in `table`
+    /// of the main database. Pass `readonly = false` to be able to write to it.
+    /// The handle borrows the client and must be dropped before the connection is used for
+    /// statements that modify the row.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use std::io::Read;
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.execute("CREATE TABLE files(name TEXT, data BLOB)")?;
+    /// let rs = db.execute("INSERT INTO files VALUES ('empty', zeroblob(4096))")?;
+    /// let mut blob = db.open_blob("files", "data", rs.last_insert_rowid.unwrap(), true)?;
+    /// let mut chunk = [1u8; 1024];
+    /// blob.read_exact(&mut chunk)?;
+    /// assert_eq!(chunk, [0u8; 1024]);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn open_blob(
+        &self,
+        table: &str,
+        column: &str,
+        rowid: i64,
+        readonly: bool,
+    ) -> Result<Blob<'_>> {
+        let table = std::ffi::CString::new(table)?;
+        let column = std::ffi::CString::new(column)?;
+        let mut handle = std::ptr::null_mut();
+        // SAFETY: the connection handle is valid for the lifetime of the client,
+        // and all strings are NUL-terminated
+        let rc = unsafe {
+            libsql::ffi::sqlite3_blob_open(
+                self.conn.handle(),
+                c"main".as_ptr(),
+                table.as_ptr(),
+                column.as_ptr(),
+                rowid,
+                if readonly { 0 } else { 1 },
+                &mut handle,
+            )
+        };
+        if rc != libsql::ffi::SQLITE_OK as std::os::raw::c_int {
+            // The handle is set to NULL on failure, so there's nothing to close
+            return Err(self.ffi_error(rc).into());
+        }
+        // SAFETY: the handle was just opened
+        let len = unsafe { libsql::ffi::sqlite3_blob_bytes(handle) } as u64;
+        Ok(Blob {
+            handle,
+            client: self,
+            len,
+            pos: 0,
+        })
+    }
+
+    // Error for a failed FFI call, with the message stored in the connection
+    fn ffi_error(&self, rc: std::os::raw::c_int) -> crate::Error {
+        // SAFETY: sqlite3_errmsg always returns a valid NUL-terminated string
+        let message =
+            unsafe { std::ffi::CStr::from_ptr(libsql::ffi::sqlite3_errmsg(self.conn.handle())) };
+        crate::Error::from_code(
+            message.to_string_lossy().into_owned(),
+            crate::error::sqlite_code_name(rc).map(str::to_string),
+        )
+    }
+
+    /// Executes a statement, aborting it if it runs longer than `timeout`.
+    ///
+    /// The deadline is checked by SQLite itself every few virtual machine instructions,
+    /// so it also stops queries that never yield a row, like huge joins or sorts.
+    /// An aborted statement fails with [`crate::Error::Timeout`]; if it was a write
+    /// outside of an explicit transaction, its changes are rolled back.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use std::time::Duration;
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// let err = db
+    ///     .execute_with_timeout(
+    ///         "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c",
+    ///         Duration::from_millis(100),
+    ///     )
+    ///     .unwrap_err();
+    /// assert!(matches!(err.downcast_ref(), Some(libsql_client::Error::Timeout(_))));
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn execute_with_timeout(
+        &self,
+        stmt: impl Into<Statement> + Send,
+        timeout: std::time::Duration,
+    ) -> Result<ResultSet> {
+        let deadline = std::time::Instant::now() + timeout;
+        let handler = ProgressHandler::install(self, &deadline);
+        let result = self.execute(stmt);
+        drop(handler);
+        result.map_err(|e| {
+            let interrupted = matches!(
+                e.downcast_ref::<crate::Error>().and_then(|e| e.code()),
+                Some("SQLITE_INTERRUPT")
+            );
+            if interrupted && std::time::Instant::now() >= deadline {
+                crate::Error::Timeout(format!("statement did not finish within {timeout:?}")).into()
+            } else {
+                e
+            }
+        })
+    }
+
     /// Executes a batch of SQL statements.
     /// Each statement is going to run in its own transaction,
     /// unless they're wrapped in BEGIN and END
@@ -97,59 +1050,75 @@ impl Client {
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement>>,
     ) -> anyhow::Result<BatchResult> {
+        let stmts: Vec<Statement> = stmts.into_iter().map(Into::into).collect();
+        let span = crate::utils::QuerySpan::new("local", &stmts, self.state.span_sql);
+        let result = span.span.in_scope(|| {
+            self.raw_batch_with_meta(stmts)
+                .map(|(batch_result, _)| batch_result)
+        });
+        span.finish(&result, crate::utils::batch_rows);
+        result
+    }
+
+    // Like raw_batch, but also returns the origin (table, column) of each result column
+    // and the rows read and written by each successful step
+    fn raw_batch_with_meta(
+        &self,
+        stmts: impl IntoIterator<Item = impl Into<Statement>>,
+    ) -> anyhow::Result<(BatchResult, Vec<StepMeta>)> {
         let mut step_results = vec![];
         let mut step_errors = vec![];
+        let mut step_meta = vec![];
         for stmt in stmts {
-            let stmt = stmt.into();
-            let sql_string = &stmt.sql;
-            let params: libsql::Params = stmt
-                .args
-                .into_iter()
-                .map(ValueWrapper)
-                .map(libsql::Value::from)
-                .collect::<Vec<_>>()
-                .into();
-            let stmt = self.conn.prepare(sql_string)?;
-            let cols: Vec<Col> = stmt
-                .columns()
-                .into_iter()
+            let stmt: Statement = stmt.into();
+            let cached = stmt.cached;
+            let is_write = stmt.is_write();
+            let (sql_string, bindings) = Self::into_params(stmt);
+            let sql_string = &sql_string;
+            let stmt = self.prepare(sql_string, cached)?;
+            // Counters of a reused statement include its previous executions
+            let counters_before = (
+                stmt.get_status(STMTSTATUS_ROWS_READ),
+                stmt.get_status(STMTSTATUS_ROWS_WRITTEN),
+            );
+            let columns = stmt.columns();
+            let cols: Vec<Col> = columns
+                .iter()
                 .map(|c| Col {
                     name: Some(c.name().to_string()),
                 })
                 .collect();
+            let origins: Vec<ColumnOrigin> = columns
+                .iter()
+                .map(|c| Some((c.table_name()?.to_string(), c.origin_name()?.to_string())))
+                .collect();
             let mut rows = Vec::new();
-            let input_rows = match stmt.query(&params) {
+            #[cfg(not(feature = "sql_parsing"))]
+            let before = (self.total_changes(), self.conn.last_insert_rowid());
+            let input_rows = match bindings
+                .bind(&stmt)
+                .map_err(|e| e.to_string())
+                .and_then(|()| stmt.query(&libsql::Params::None).map_err(|e| e.to_string()))
+            {
                 Ok(rows) => rows,
-                Err(e) => {
+                Err(message) => {
                     step_results.push(None);
-                    step_errors.push(Some(proto::Error {
-                        message: e.to_string(),
-                    }));
+                    step_errors.push(Some(proto::Error { message }));
                     break;
                 }
             };
-            while let Some(row) = input_rows.next()? {
+            // Rows are collected for any statement with result columns,
+            // including INSERT, UPDATE and DELETE with a RETURNING clause
+            while let Some(row) = input_rows.next().map_err(database_error)? {
                 let cells = (0..cols.len())
-                    .map(|i| ValueWrapper::from(row.get_value(i as i32).unwrap()).0)
-                    .collect();
+                    .map(|i| read_value(&row, i, self.state.text_decoding))
+                    .collect::<Result<_>>()?;
                 rows.push(cells)
             }
-            let parser = Parser::new(sql_string.as_bytes());
-            let cmd = parser.last();
-
-            let last_insert_rowid = match cmd {
-                Ok(Some(Cmd::Stmt(Stmt::Insert { .. }))) => Some(self.conn.last_insert_rowid()),
-                _ => None,
-            };
-
-            let affected_row_count = match cmd {
-                Ok(Some(
-                    Cmd::Stmt(Stmt::Insert { .. })
-                    | Cmd::Stmt(Stmt::Update { .. })
-                    | Cmd::Stmt(Stmt::Delete { .. }),
-                )) => self.conn.changes(),
-                _ => 0,
-            };
+            #[cfg(feature = "sql_parsing")]
+            let (affected_row_count, last_insert_rowid) = self.classify_parsed(sql_string, true)?;
+            #[cfg(not(feature = "sql_parsing"))]
+            let (affected_row_count, last_insert_rowid) = self.classify_by_changes(before);
 
             let stmt_result = StmtResult {
                 cols,
@@ -159,10 +1128,177 @@ impl Client {
             };
             step_results.push(Some(stmt_result));
             step_errors.push(None);
+            step_meta.push(StepMeta {
+                origins,
+                rows_read: (stmt.get_status(STMTSTATUS_ROWS_READ) - counters_before.0) as u64,
+                rows_written: (stmt.get_status(STMTSTATUS_ROWS_WRITTEN) - counters_before.1) as u64,
+                is_write,
+            });
         }
-        Ok(BatchResult {
-            step_results,
-            step_errors,
+        Ok((
+            BatchResult {
+                step_results,
+                step_errors,
+            },
+            step_meta,
+        ))
+    }
+
+    // Rows affected by the statement which just ran and, if asked for, the rowid it inserted,
+    // based on the kind of its last command
+    #[cfg(feature = "sql_parsing")]
+    fn classify_parsed(&self, sql: &str, with_rowid: bool) -> Result<(u64, Option<i64>)> {
+        let parser = Parser::new(sql.as_bytes());
+        let cmd = parser.last();
+
+        let affected_row_count = match cmd {
+            Ok(Some(
+                Cmd::Stmt(Stmt::Insert { .. })
+                | Cmd::Stmt(Stmt::Update { .. })
+                | Cmd::Stmt(Stmt::Delete { .. }),
+            )) => self.conn.changes(),
+            _ => 0,
+        };
+
+        let last_insert_rowid = match &cmd {
+            Ok(Some(Cmd::Stmt(Stmt::Insert { tbl_name, .. })))
+                if with_rowid && affected_row_count > 0 =>
+            {
+                self.inserted_rowid(tbl_name)?
+            }
+            _ => None,
+        };
+        Ok((affected_row_count, last_insert_rowid))
+    }
+
+    // Same as classify_parsed, without parsing: changes() is only reported if the statement
+    // changed any rows at all, since it otherwise keeps the count of an earlier INSERT,
+    // UPDATE or DELETE. The rowid is only reported if it moved, which it doesn't for
+    // tables declared WITHOUT ROWID - nor for an insert reusing the previous rowid.
+    #[cfg(not(feature = "sql_parsing"))]
+    fn classify_by_changes(&self, (total_changes, rowid): (i64, i64)) -> (u64, Option<i64>) {
+        if self.total_changes() == total_changes {
+            return (0, None);
+        }
+        let last_insert_rowid = self.conn.last_insert_rowid();
+        (
+            self.conn.changes(),
+            (last_insert_rowid != rowid).then_some(last_insert_rowid),
+        )
+    }
+
+    #[cfg(not(feature = "sql_parsing"))]
+    fn total_changes(&self) -> i64 {
+        unsafe { libsql::ffi::sqlite3_total_changes64(self.conn.handle()) }
+    }
+
+    // Rowid of the row inserted by the last statement. Tables declared WITHOUT ROWID
+    // have none, and the connection's last_insert_rowid() would be a stale value.
+    #[cfg(feature = "sql_parsing")]
+    fn inserted_rowid(&self, tbl_name: &QualifiedName) -> Result<Option<i64>> {
+        let unquote = |name: &Name| {
+            let name = name.0.as_str();
+            match name.as_bytes().first() {
+                Some(b'"' | b'\'' | b'`' | b'[') => name[1..name.len() - 1].to_string(),
+                _ => name.to_string(),
+            }
+        };
+        let schema = match &tbl_name.db_name {
+            Some(db_name) => libsql::Value::Text(unquote(db_name)),
+            None => libsql::Value::Null,
+        };
+        let params =
+            libsql::Params::Positional(vec![libsql::Value::Text(unquote(&tbl_name.name)), schema]);
+        let without_rowid = self
+            .conn
+            .prepare(
+                "SELECT wr FROM pragma_table_list WHERE name = ?1 AND schema = coalesce(?2, schema) \
+                 ORDER BY schema = 'temp' DESC LIMIT 1",
+            )
+            .map_err(database_error)?
+            .query(&params)
+            .map_err(database_error)?
+            .next()
+            .map_err(database_error)?
+            .map(|row| matches!(row.get_value(0), Ok(libsql::Value::Integer(1))))
+            .unwrap_or(false);
+        Ok((!without_rowid).then(|| self.conn.last_insert_rowid()))
+    }
+
+    // Prepares a statement. A cached one reuses the statement kept from its previous execution,
+    // and is kept again once the returned guard drops.
+    fn prepare(&self, sql: &str, cached: bool) -> Result<StatementGuard<'_>> {
+        let kept = cached
+            .then(|| self.statements.lock().unwrap().remove(sql))
+            .flatten();
+        let stmt = match kept {
+            Some(stmt) => stmt,
+            None => self.conn.prepare(sql).map_err(database_error)?,
+        };
+        Ok(StatementGuard {
+            client: self,
+            sql: cached.then(|| sql.to_string()),
+            stmt,
+        })
+    }
+
+    fn into_params(stmt: Statement) -> (String, Bindings) {
+        let bindings = Bindings {
+            positional: stmt
+                .args
+                .into_iter()
+                .map(|v| libsql::Value::from(ValueWrapper(v)))
+                .collect(),
+            named: stmt
+                .named_args
+                .into_iter()
+                .map(|(name, v)| (name, libsql::Value::from(ValueWrapper(v))))
+                .collect(),
+        };
+        (stmt.sql, bindings)
+    }
+
+    /// Executes a single SQL statement and returns its rows lazily.
+    ///
+    /// Unlike [`Client::execute()`], rows are not buffered in memory - each call to
+    /// [`Iterator::next()`] steps the underlying statement once. Use it for large `SELECT`s.
+    /// Note that until the iterator is exhausted or dropped, the statement keeps
+    /// its read transaction open.
+    ///
+    /// # Arguments
+    /// * `stmt` - the SQL statement
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.execute("CREATE TABLE t(id)")?;
+    /// db.execute("INSERT INTO t VALUES (1), (2), (3)")?;
+    /// let mut sum = 0;
+    /// for row in db.execute_lazy("SELECT id FROM t")? {
+    ///     sum += row?.try_get::<i64>(0)?;
+    /// }
+    /// assert_eq!(sum, 6);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn execute_lazy(&self, stmt: impl Into<Statement>) -> Result<RowIter> {
+        let (sql, bindings) = Self::into_params(stmt.into());
+        let stmt = self.conn.prepare(sql).map_err(database_error)?;
+        bindings.bind(&stmt)?;
+        let columns = stmt
+            .columns()
+            .into_iter()
+            .map(|c| c.name().to_string())
+            .collect();
+        let rows = stmt.query(&libsql::Params::None).map_err(database_error)?;
+        Ok(RowIter {
+            columns,
+            rows,
+            text_decoding: self.state.text_decoding,
+            done: false,
         })
     }
 
@@ -176,11 +1312,15 @@ impl Client {
         &self,
         stmts: impl IntoIterator<Item = impl Into<Statement> + Send> + Send,
     ) -> Result<Vec<ResultSet>> {
-        let batch_results = self.raw_batch(
-            std::iter::once(Statement::new("BEGIN"))
-                .chain(stmts.into_iter().map(|s| s.into()))
-                .chain(std::iter::once(Statement::new("END"))),
-        )?;
+        let (batch_results, step_meta) = self
+            .raw_batch_with_meta(
+                std::iter::once(Statement::new("BEGIN"))
+                    .chain(stmts.into_iter().map(|s| s.into()))
+                    .chain(std::iter::once(Statement::new("END"))),
+            )
+            .inspect_err(|_| {
+                self.execute("ROLLBACK").ok();
+            })?;
         let step_error: Option<proto::Error> = batch_results
             .step_errors
             .into_iter()
@@ -188,15 +1328,22 @@ impl Client {
             .find(|e| e.is_some())
             .flatten();
         if let Some(error) = step_error {
+            // The batch stopped midway, leave the wrapping transaction
+            self.execute("ROLLBACK").ok();
             return Err(anyhow::anyhow!(error.message));
         }
         let mut step_results: Vec<Result<ResultSet>> = batch_results
             .step_results
             .into_iter()
+            .zip(step_meta)
             .skip(1) // BEGIN is not counted in the result, it's implicitly ignored
-            .map(|maybe_rs| {
+            .map(|(maybe_rs, meta)| {
                 maybe_rs
-                    .map(ResultSet::from)
+                    .map(|rs| ResultSet {
+                        column_origins: meta.origins,
+                        is_write: meta.is_write,
+                        ..ResultSet::from(rs)
+                    })
                     .ok_or_else(|| anyhow::anyhow!("Unexpected missing result set"))
             })
             .collect();
@@ -208,14 +1355,72 @@ impl Client {
     /// # Arguments
     /// * `stmt` - the SQL statement
     pub fn execute(&self, stmt: impl Into<Statement> + Send) -> Result<ResultSet> {
-        let results = self.raw_batch(std::iter::once(stmt))?;
+        let stmt = stmt.into();
+        let span = crate::utils::QuerySpan::new("local", [&stmt], self.state.span_sql);
+        let result = span.span.in_scope(|| self.execute_unspanned(stmt));
+        span.finish(&result, |rs| rs.rows.len());
+        result
+    }
+
+    fn execute_unspanned(&self, stmt: Statement) -> Result<ResultSet> {
+        let (results, mut meta) = self.raw_batch_with_meta(std::iter::once(stmt))?;
         match (results.step_results.first(), results.step_errors.first()) {
-            (Some(Some(result)), Some(None)) => Ok(ResultSet::from(result.clone())),
+            (Some(Some(result)), Some(None)) => {
+                let meta = meta
+                    .pop()
+                    .expect("missing metadata of an executed statement");
+                // Elapsed time is measured by the top-level client, which covers all backends
+                let stats = self.state.metrics.then_some(crate::QueryStats {
+                    elapsed: std::time::Duration::ZERO,
+                    rows_read: Some(meta.rows_read),
+                    rows_written: Some(meta.rows_written),
+                });
+                Ok(ResultSet {
+                    column_origins: meta.origins,
+                    stats,
+                    is_write: meta.is_write,
+                    ..ResultSet::from(result.clone())
+                })
+            }
             (Some(None), Some(Some(err))) => Err(anyhow::anyhow!(err.message.clone())),
             _ => unreachable!(),
         }
     }
 
+    /// Executes a statement and returns only the number of rows it changed.
+    ///
+    /// Rows returned by the statement, e.g. by a `RETURNING` clause, are stepped through
+    /// but never read, so no [ResultSet] is built - which makes it a cheaper alternative to
+    /// [`Client::execute()`] for writes whose results aren't needed.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// let db = libsql_client::local::Client::in_memory()?;
+    /// db.execute("CREATE TABLE t(x)")?;
+    /// assert_eq!(db.execute_affected("INSERT INTO t VALUES (1), (2)")?, 2);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn execute_affected(&self, stmt: impl Into<Statement> + Send) -> Result<u64> {
+        let stmt: Statement = stmt.into();
+        let cached = stmt.cached;
+        let (sql_string, bindings) = Self::into_params(stmt);
+        let stmt = self.prepare(&sql_string, cached)?;
+        bindings.bind(&stmt)?;
+        #[cfg(not(feature = "sql_parsing"))]
+        let before = (self.total_changes(), self.conn.last_insert_rowid());
+        let rows = stmt.query(&libsql::Params::None).map_err(database_error)?;
+        while rows.next().map_err(database_error)?.is_some() {}
+        #[cfg(feature = "sql_parsing")]
+        let (affected_row_count, _) = self.classify_parsed(&sql_string, false)?;
+        #[cfg(not(feature = "sql_parsing"))]
+        let (affected_row_count, _) = self.classify_by_changes(before);
+        Ok(affected_row_count)
+    }
+
     pub fn execute_in_transaction(&self, _tx_id: u64, stmt: Statement) -> Result<ResultSet> {
         self.execute(stmt)
     }
@@ -227,4 +1432,537 @@ impl Client {
     pub fn rollback_transaction(&self, _tx_id: u64) -> Result<()> {
         self.execute("ROLLBACK").map(|_| ())
     }
+
+    // True if the connection is inside a transaction, e.g. an interactive one
+    pub(crate) fn in_transaction(&self) -> bool {
+        !self.conn.is_autocommit()
+    }
+
+    pub fn drop_transaction(&self, tx_id: u64) {
+        if self.conn.is_autocommit() {
+            return;
+        }
+        tracing::debug!("Transaction {tx_id} dropped without commit or rollback, rolling back");
+        if let Err(e) = self.rollback_transaction(tx_id) {
+            tracing::warn!("Failed to roll back dropped transaction {tx_id}: {e}");
+        }
+    }
+}
+
+// Number of virtual machine instructions between deadline checks
+const PROGRESS_HANDLER_PERIOD: std::os::raw::c_int = 1000;
+
+// Interrupts statements of a connection once a deadline passes, until dropped
+struct ProgressHandler<'a> {
+    client: &'a Client,
+}
+
+impl<'a> ProgressHandler<'a> {
+    fn install(client: &'a Client, deadline: &'a std::time::Instant) -> Self {
+        unsafe extern "C" fn past_deadline(
+            deadline: *mut std::os::raw::c_void,
+        ) -> std::os::raw::c_int {
+            // SAFETY: the pointer is the deadline borrowed by the handler, which outlives it
+            let deadline = unsafe { &*(deadline as *const std::time::Instant) };
+            (std::time::Instant::now() >= *deadline) as std::os::raw::c_int
+        }
+        // SAFETY: the connection handle is valid for the lifetime of the client,
+        // and the handler is unregistered on drop, before the deadline goes out of scope
+        unsafe {
+            libsql::ffi::sqlite3_progress_handler(
+                client.conn.handle(),
+                PROGRESS_HANDLER_PERIOD,
+                Some(past_deadline),
+                deadline as *const std::time::Instant as *mut std::os::raw::c_void,
+            )
+        };
+        Self { client }
+    }
+}
+
+impl Drop for ProgressHandler<'_> {
+    fn drop(&mut self) {
+        // SAFETY: the connection handle is valid for the lifetime of the client
+        unsafe {
+            libsql::ffi::sqlite3_progress_handler(
+                self.client.conn.handle(),
+                0,
+                None,
+                std::ptr::null_mut(),
+            )
+        };
+    }
+}
+
+// Checks that an alias for an attached database is a plain identifier
+fn validate_alias(alias: &str) -> Result<()> {
+    if !crate::utils::is_identifier(alias) {
+        return Err(crate::Error::Misuse(format!("Invalid database alias: {alias:?}")).into());
+    }
+    if alias.eq_ignore_ascii_case("main") || alias.eq_ignore_ascii_case("temp") {
+        return Err(crate::Error::Misuse(format!("Database alias {alias:?} is reserved")).into());
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn execute_affected() {
+        let db = Client::in_memory().unwrap();
+        assert_eq!(db.execute_affected("CREATE TABLE t(x)").unwrap(), 0);
+        assert_eq!(
+            db.execute_affected("INSERT INTO t VALUES (1), (2), (3)")
+                .unwrap(),
+            3
+        );
+        assert_eq!(db.execute_affected("SELECT * FROM t").unwrap(), 0);
+        assert_eq!(
+            db.execute_affected("UPDATE t SET x = x * 10 WHERE x > 1 RETURNING x")
+                .unwrap(),
+            2
+        );
+        assert_eq!(
+            db.execute_affected(Statement::with_args("DELETE FROM t WHERE x = ?", &[30]))
+                .unwrap(),
+            1
+        );
+        let rs = db.execute("SELECT x FROM t ORDER BY x").unwrap();
+        let xs: Vec<i64> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(xs, [1, 20]);
+
+        let err = db
+            .execute_affected("INSERT INTO missing VALUES (1)")
+            .unwrap_err();
+        assert!(err.downcast_ref::<crate::Error>().is_some(), "{err}");
+    }
+
+    #[test]
+    fn read_only() {
+        let path = "/tmp/local_read_only.db";
+        let db = Client::new(path).unwrap();
+        db.execute("CREATE TABLE IF NOT EXISTS t(x)").unwrap();
+        db.execute("DELETE FROM t").unwrap();
+        db.execute("INSERT INTO t VALUES (1)").unwrap();
+
+        let ro = Client::open_read_only(path).unwrap();
+        let rs = ro.execute("SELECT x FROM t").unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+        for sql in [
+            "INSERT INTO t VALUES (2)",
+            "CREATE TABLE u(y)",
+            "DELETE FROM t",
+        ] {
+            let err = ro.execute(sql).unwrap_err();
+            assert_eq!(
+                err.downcast_ref::<crate::Error>()
+                    .and_then(crate::Error::code),
+                Some("SQLITE_READONLY"),
+                "{sql}: {err}"
+            );
+        }
+        assert_eq!(db.execute("SELECT * FROM t").unwrap().rows.len(), 1);
+
+        let err = Client::open_read_only("/tmp/local_read_only_missing.db").unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>()
+                .and_then(crate::Error::code),
+            Some("SQLITE_CANTOPEN"),
+            "{err}"
+        );
+    }
+
+    #[test]
+    fn in_memory_named() {
+        let first = Client::in_memory_named("in_memory_named").unwrap();
+        let second = Client::in_memory_named("in_memory_named").unwrap();
+        let other = Client::in_memory_named("in_memory_named_other").unwrap();
+        first.execute("CREATE TABLE t(x)").unwrap();
+        first.execute("INSERT INTO t VALUES (1), (2)").unwrap();
+        let rs = second.execute("SELECT sum(x) FROM t").unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 3);
+        assert!(other.execute("SELECT * FROM t").is_err());
+
+        // The database is gone once its last connection is closed
+        drop(first);
+        drop(second);
+        let third = Client::in_memory_named("in_memory_named").unwrap();
+        assert!(third.execute("SELECT * FROM t").is_err());
+
+        let err = Client::in_memory_named("a?mode=rw").unwrap_err();
+        assert!(matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))));
+    }
+
+    #[test]
+    fn rows_affected_by_statement_kind() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, v)")
+            .unwrap();
+        let rs = db
+            .execute("INSERT INTO t(v) VALUES ('a'), ('b'), ('c')")
+            .unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (3, Some(3)));
+
+        // Neither reads nor schema changes report the count of the previous insert
+        let rs = db.execute("SELECT * FROM t").unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (0, None));
+        let rs = db.execute("CREATE INDEX t_v ON t(v)").unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (0, None));
+
+        let rs = db
+            .execute("UPDATE t SET v = upper(v) WHERE id > 1 RETURNING id")
+            .unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (2, None));
+        assert_eq!(rs.rows.len(), 2);
+        let rs = db.execute("DELETE FROM t WHERE id = 42").unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (0, None));
+        let rs = db.execute("DELETE FROM t").unwrap();
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (3, None));
+    }
+
+    #[test]
+    fn last_insert_rowid_without_rowid() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, v)")
+            .unwrap();
+        db.execute(
+            "CREATE TABLE counter(country TEXT, city TEXT, value INT, PRIMARY KEY(country, city)) WITHOUT ROWID",
+        )
+        .unwrap();
+
+        let rs = db.execute("INSERT INTO t VALUES (42, 'a')").unwrap();
+        assert_eq!(rs.last_insert_rowid, Some(42));
+
+        let rs = db
+            .execute("INSERT INTO \"counter\" VALUES ('PL', 'Warsaw', 1)")
+            .unwrap();
+        assert_eq!(rs.rows_affected, 1);
+        assert_eq!(rs.last_insert_rowid, None);
+
+        let rs = db
+            .execute("INSERT OR IGNORE INTO t VALUES (42, 'b')")
+            .unwrap();
+        assert_eq!(rs.rows_affected, 0);
+        assert_eq!(rs.last_insert_rowid, None);
+    }
+
+    #[test]
+    fn column_origins() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE authors(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute("CREATE TABLE books(author_id INTEGER, title TEXT)")
+            .unwrap();
+        let rs = db
+            .batch([
+                "SELECT b.title AS t, a.name AS n, count(*) FROM books b JOIN authors a ON a.id = b.author_id",
+            ])
+            .unwrap()
+            .remove(0);
+        assert_eq!(rs.columns, ["t", "n", "count(*)"]);
+        assert_eq!(rs.column_origin(0), Some(("books", "title")));
+        assert_eq!(rs.column_origin(1), Some(("authors", "name")));
+        assert_eq!(rs.column_origin(2), None);
+        assert_eq!(rs.column_origin(3), None);
+    }
+
+    #[test]
+    fn blob_streaming() {
+        use std::io::{Read, Seek, SeekFrom, Write};
+
+        const SIZE: usize = 1024 * 1024;
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE files(data BLOB)").unwrap();
+        let rowid = db
+            .execute(format!("INSERT INTO files VALUES (zeroblob({SIZE}))"))
+            .unwrap()
+            .last_insert_rowid
+            .unwrap();
+        let data: Vec<u8> = (0..SIZE).map(|i| (i % 251) as u8).collect();
+
+        let mut blob = db.open_blob("files", "data", rowid, false).unwrap();
+        assert_eq!(blob.len(), SIZE as u64);
+        for chunk in data.chunks(10_000) {
+            blob.write_all(chunk).unwrap();
+        }
+        assert_eq!(blob.write(&[1]).unwrap(), 0);
+        drop(blob);
+
+        let mut blob = db.open_blob("files", "data", rowid, true).unwrap();
+        let mut read = Vec::new();
+        let mut chunk = vec![0; 64 * 1024];
+        loop {
+            let n = blob.read(&mut chunk).unwrap();
+            if n == 0 {
+                break;
+            }
+            read.extend_from_slice(&chunk[..n]);
+        }
+        assert!(read == data);
+
+        blob.seek(SeekFrom::End(-3)).unwrap();
+        let mut tail = [0; 3];
+        blob.read_exact(&mut tail).unwrap();
+        assert_eq!(tail, data[SIZE - 3..]);
+        assert!(blob.seek(SeekFrom::Current(1)).is_err());
+        // Opened read-only
+        blob.rewind().unwrap();
+        assert!(blob.write(&[1]).is_err());
+        drop(blob);
+
+        let err = db.open_blob("files", "data", rowid + 1, true).unwrap_err();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>().unwrap().code(),
+            Some("SQLITE_ERROR")
+        );
+    }
+
+    #[test]
+    fn attach_and_join() {
+        let path = std::env::temp_dir().join("libsql_client_attach_and_join.db");
+        std::fs::remove_file(&path).ok();
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute("INSERT INTO users VALUES (1, 'John'), (2, 'Jane')")
+            .unwrap();
+
+        let url = format!("file:///{}", path.display());
+        db.attach(&url, "other").unwrap();
+        db.execute("CREATE TABLE other.orders(user_id INT, item TEXT)")
+            .unwrap();
+        db.execute("INSERT INTO other.orders VALUES (2, 'book'), (2, 'pen')")
+            .unwrap();
+        let rs = db
+            .execute(
+                "SELECT name, count(*) FROM users JOIN other.orders ON users.id = orders.user_id GROUP BY name",
+            )
+            .unwrap();
+        assert_eq!(rs.rows.len(), 1);
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "Jane");
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), 2);
+
+        db.detach("other").unwrap();
+        assert!(db.execute("SELECT * FROM other.orders").is_err());
+        std::fs::remove_file(&path).ok();
+    }
+
+    #[test]
+    fn attach_validates_alias() {
+        let db = Client::in_memory().unwrap();
+        for alias in ["", "1db", "a b", "x\"; DROP TABLE t; --", "main", "TEMP"] {
+            let err = db.attach(":memory:", alias).unwrap_err();
+            assert!(
+                matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))),
+                "{alias}"
+            );
+        }
+    }
+
+    #[test]
+    fn execute_with_timeout() {
+        let db = Client::in_memory().unwrap();
+        let start = std::time::Instant::now();
+        let err = db
+            .execute_with_timeout(
+                "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c) SELECT count(*) FROM c",
+                std::time::Duration::from_millis(50),
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(crate::Error::Timeout(_))),
+            "{err}"
+        );
+        assert!(start.elapsed() < std::time::Duration::from_secs(5));
+
+        // The deadline has passed, but the handler is gone once the statement is done
+        db.execute("WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 100000) SELECT count(*) FROM c")
+            .unwrap();
+        let rs = db
+            .execute_with_timeout(
+                "WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 1000) SELECT count(*) FROM c",
+                std::time::Duration::from_secs(10),
+            )
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1000);
+    }
+
+    #[test]
+    fn sync_loop_fires() {
+        use std::cell::Cell;
+        use std::time::Duration;
+
+        // A fake primary, failing every other sync
+        let syncs = Cell::new(0);
+        let sync = || {
+            syncs.set(syncs.get() + 1);
+            let n = syncs.get();
+            async move {
+                match n % 2 {
+                    0 => Err(anyhow::anyhow!("primary unavailable")),
+                    _ => Ok(n),
+                }
+            }
+        };
+
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let handle = SyncHandle { commands: tx };
+        futures::executor::block_on(futures::future::join(
+            sync_loop(Duration::from_millis(5), rx, sync),
+            async {
+                crate::utils::sleep(Duration::from_millis(100)).await;
+                handle.stop();
+            },
+        ));
+        assert!(syncs.get() >= 2, "{} syncs", syncs.get());
+
+        // Syncs requested with sync_now() don't wait for the interval
+        syncs.set(0);
+        let (tx, rx) = futures::channel::mpsc::unbounded();
+        let handle = SyncHandle { commands: tx };
+        handle.sync_now();
+        handle.sync_now();
+        handle.stop();
+        handle.sync_now();
+        futures::executor::block_on(sync_loop(Duration::from_secs(3600), rx, sync));
+        assert_eq!(syncs.get(), 2);
+
+        // Dropping the handle stops the loop as well
+        let (tx, rx) = futures::channel::mpsc::unbounded::<SyncCommand>();
+        drop(tx);
+        futures::executor::block_on(sync_loop(Duration::from_secs(3600), rx, sync));
+        assert_eq!(syncs.get(), 2);
+
+        let db = Client::in_memory().unwrap();
+        assert!(!db.is_replica());
+        let Err(err) = db.background_sync(Duration::from_secs(1)) else {
+            panic!("background sync of a database which is not a replica");
+        };
+        assert!(matches!(err.downcast_ref(), Some(crate::Error::Misuse(_))));
+    }
+
+    #[test]
+    fn sync_progress_without_replica() {
+        use futures::StreamExt;
+        let db = Client::in_memory().unwrap();
+        let progress: Vec<_> = futures::executor::block_on(db.sync_progress().collect());
+        assert_eq!(progress.len(), 1);
+        assert!(progress[0].is_err());
+    }
+
+    #[test]
+    fn insert_returning() {
+        let db = Client::in_memory().unwrap();
+        db.execute(
+            "CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT, created_at TEXT DEFAULT '2023-01-01')",
+        )
+        .unwrap();
+
+        let rs = db
+            .execute("INSERT INTO t(name) VALUES ('a'), ('b') RETURNING id, created_at")
+            .unwrap();
+        assert_eq!(rs.columns, ["id", "created_at"]);
+        assert_eq!(rs.rows.len(), 2);
+        assert_eq!(rs.rows[1].try_get::<i64>(0).unwrap(), 2);
+        assert_eq!(rs.rows[1].try_get::<&str>(1).unwrap(), "2023-01-01");
+        assert_eq!(rs.rows_affected, 2);
+        assert_eq!(rs.last_insert_rowid, Some(2));
+
+        let rs = db
+            .batch(["UPDATE t SET name = 'c' WHERE id = 1 RETURNING name"])
+            .unwrap();
+        assert_eq!(rs[0].rows[0].try_get::<&str>(0).unwrap(), "c");
+        assert_eq!(rs[0].rows_affected, 1);
+
+        let rs = db.execute("DELETE FROM t RETURNING id").unwrap();
+        assert_eq!(rs.rows.len(), 2);
+        assert_eq!(rs.rows_affected, 2);
+    }
+
+    #[test]
+    fn cache_size_from_config() {
+        let db = Client::in_memory().unwrap();
+        assert_eq!(db.cache_size().unwrap(), -2000);
+
+        let config = crate::Config::new("file:////tmp/cache_size_from_config.db")
+            .unwrap()
+            .with_cache_size(-8192);
+        let db = futures::executor::block_on(crate::Client::from_config(config)).unwrap();
+        let crate::Client::Local(db) = db else {
+            panic!("Expected a local client");
+        };
+        assert_eq!(db.cache_size().unwrap(), -8192);
+        db.set_cache_size(100).unwrap();
+        assert_eq!(db.cache_size().unwrap(), 100);
+    }
+
+    #[test]
+    fn sql_length_limit() {
+        let db = Client::in_memory().unwrap();
+        let default = db.limit(SqliteLimit::SqlLength);
+        assert_eq!(db.set_limit(SqliteLimit::SqlLength, 16).unwrap(), default);
+        assert_eq!(db.limit(SqliteLimit::SqlLength), 16);
+        db.execute("SELECT 1").unwrap();
+        assert!(db.execute("SELECT 1, 2, 3, 4, 5, 6, 7").is_err());
+        assert!(db.set_limit(SqliteLimit::SqlLength, -1).is_err());
+        assert_eq!(db.limit(SqliteLimit::SqlLength), 16);
+    }
+
+    #[test]
+    fn expected_param_count_matches_sqlite() {
+        let db = Client::in_memory().unwrap();
+        for sql in [
+            "SELECT 1",
+            "SELECT ?",
+            "SELECT ?, ?, ?",
+            "SELECT ?3, ?",
+            "SELECT :a, @a, :a, $b, ?",
+            "SELECT '?' AS [?] -- ?",
+        ] {
+            let stmt = db.conn.prepare(sql).unwrap();
+            assert_eq!(
+                Statement::new(sql).expected_param_count(),
+                Some(stmt.parameter_count()),
+                "{sql}"
+            );
+        }
+    }
+
+    #[test]
+    fn prepared_statement() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER, name TEXT)").unwrap();
+        let insert = crate::PreparedStatement::new("INSERT INTO t VALUES (?, ?)");
+        for (id, name) in [(1, "Ann"), (2, "Bob"), (3, "Cid")] {
+            let rs = db.execute(insert.bind(crate::args!(id, name))).unwrap();
+            assert_eq!(rs.rows_affected, 1);
+        }
+        // Parameters left out aren't carried over from the previous execution
+        db.execute(insert.bind(&[4])).unwrap();
+        let (batch, meta) = db
+            .raw_batch_with_meta([insert.bind(crate::args!(5, "Dan"))])
+            .unwrap();
+        assert!(batch.step_errors[0].is_none());
+        assert_eq!(meta[0].rows_written, 1);
+        assert_eq!(db.statements.lock().unwrap().len(), 1);
+
+        let rs = db.execute("SELECT id, name FROM t ORDER BY id").unwrap();
+        let names: Vec<Option<String>> = rs
+            .rows
+            .iter()
+            .map(|r| r.try_get::<&str>(1).ok().map(str::to_string))
+            .collect();
+        assert_eq!(
+            names,
+            [
+                Some("Ann".to_string()),
+                Some("Bob".to_string()),
+                Some("Cid".to_string()),
+                None,
+                Some("Dan".to_string())
+            ]
+        );
+    }
 }
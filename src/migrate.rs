@@ -0,0 +1,406 @@
+//! `migrate` applies ordered SQL migrations, keeping track of the applied ones in the database.
+
+use anyhow::Result;
+
+use crate::{Client, Error, Statement};
+
+/// Name of the table tracking applied migrations
+pub const MIGRATIONS_TABLE: &str = "_migrations";
+
+/// Applies migrations which were not applied to the database yet.
+///
+/// Each migration is a version and the SQL to run, possibly consisting of multiple
+/// statements separated by semicolons. Versions of applied migrations are recorded in the
+/// [`_migrations`](MIGRATIONS_TABLE) table, created on first run, so running the same
+/// migrations again only applies the new ones.
+///
+/// By default each migration runs in its own transaction, together with recording its version,
+/// so a failing migration leaves no trace. Statements which cannot run in a transaction,
+/// like `VACUUM` or changing `PRAGMA journal_mode`, need [`Migrator::without_transaction()`].
+///
+/// # Examples
+///
+/// ```
+/// # async fn f() -> anyhow::Result<()> {
+/// use libsql_client::migrate::Migrator;
+///
+/// let db = libsql_client::Client::in_memory()?;
+/// let migrations = [
+///     (1, "CREATE TABLE users(id INTEGER PRIMARY KEY, name TEXT)"),
+///     (2, "ALTER TABLE users ADD COLUMN email TEXT; CREATE INDEX users_email ON users(email)"),
+/// ];
+/// assert_eq!(Migrator::new(&db).run(&migrations).await?, [1, 2]);
+/// assert!(Migrator::new(&db).run(&migrations).await?.is_empty());
+/// # Ok(())
+/// # }
+/// # futures::executor::block_on(f()).unwrap();
+/// ```
+pub struct Migrator<'a> {
+    client: &'a Client,
+    transactional: bool,
+}
+
+impl<'a> Migrator<'a> {
+    /// Creates a migrator running each migration in a transaction
+    pub fn new(client: &'a Client) -> Self {
+        Self {
+            client,
+            transactional: true,
+        }
+    }
+
+    /// Runs migration statements one by one, outside of a transaction.
+    ///
+    /// If a migration fails, the statements preceding the failing one stay applied,
+    /// and the migration is not recorded - so it needs to be safe to re-run.
+    pub fn without_transaction(mut self) -> Self {
+        self.transactional = false;
+        self
+    }
+
+    /// Applies pending migrations in order, returning the versions which were applied.
+    ///
+    /// Migrations must be sorted by strictly increasing versions. Applying stops
+    /// at the first failing migration, returning its error.
+    pub async fn run(&self, migrations: &[(i64, &str)]) -> Result<Vec<i64>> {
+        if let Some(w) = migrations.windows(2).find(|w| w[0].0 >= w[1].0) {
+            return Err(Error::Misuse(format!(
+                "Migrations must be sorted by strictly increasing versions, found {} before {}",
+                w[0].0, w[1].0
+            ))
+            .into());
+        }
+        self.client
+            .execute(format!(
+                "CREATE TABLE IF NOT EXISTS {MIGRATIONS_TABLE}(version INTEGER PRIMARY KEY, applied_at TEXT NOT NULL DEFAULT CURRENT_TIMESTAMP)"
+            ))
+            .await?;
+        let applied = self.applied().await?;
+
+        let mut ran = Vec::new();
+        for &(version, sql) in migrations {
+            if applied.contains(&version) {
+                continue;
+            }
+            tracing::debug!("Applying migration {version}");
+            self.apply(version, sql).await?;
+            ran.push(version);
+        }
+        Ok(ran)
+    }
+
+    /// Returns the versions of applied migrations, in increasing order
+    pub async fn applied(&self) -> Result<Vec<i64>> {
+        let rs = self
+            .client
+            .execute(format!(
+                "SELECT version FROM {MIGRATIONS_TABLE} ORDER BY version"
+            ))
+            .await?;
+        rs.rows.iter().map(|row| row.try_get(0)).collect()
+    }
+
+    async fn apply(&self, version: i64, sql: &str) -> Result<()> {
+        let record = Statement::with_args(
+            format!("INSERT INTO {MIGRATIONS_TABLE}(version) VALUES (?)"),
+            &[version],
+        );
+        if !self.transactional {
+            for stmt in split_statements(sql) {
+                self.client.execute(stmt).await?;
+            }
+            return self.client.execute(record).await.map(|_| ());
+        }
+        let tx = self.client.transaction().await?;
+        for stmt in split_statements(sql) {
+            tx.execute(stmt).await?;
+        }
+        tx.execute(record).await?;
+        tx.commit().await
+    }
+}
+
+/// Splits SQL into statements, skipping empty ones and ones holding only comments.
+///
+/// With the `sql_parsing` feature, statements are delimited by the SQL parser. SQL it rejects,
+/// e.g. because of a syntax error, is split by [split_scanned()] instead, leaving it to the
+/// database to report the error.
+pub(crate) fn split_statements(sql: &str) -> Vec<&str> {
+    #[cfg(feature = "sql_parsing")]
+    if let Some(statements) = split_parsed(sql) {
+        return statements;
+    }
+    split_scanned(sql)
+}
+
+// Statements as delimited by the parser, or None if it failed to parse any of them
+#[cfg(feature = "sql_parsing")]
+fn split_parsed(sql: &str) -> Option<Vec<&str>> {
+    use fallible_iterator::FallibleIterator;
+
+    // The parser only reports its position as a line and a column, counted in bytes
+    let line_starts: Vec<usize> = std::iter::once(0)
+        .chain(sql.match_indices('\n').map(|(i, _)| i + 1))
+        .collect();
+    let mut parser = sqlite3_parser::lexer::sql::Parser::new(sql.as_bytes());
+    let mut statements = Vec::new();
+    let mut start = 0;
+    while parser.next().ok()?.is_some() {
+        // Right past the semicolon ending the statement, or the end of input
+        let end = line_starts.get(parser.line() as usize - 1)? + parser.column() - 1;
+        let stmt = sql
+            .get(start..end)?
+            .trim_start_matches(|c: char| c == ';' || c.is_whitespace())
+            .trim_end();
+        statements.push(stmt.strip_suffix(';').unwrap_or(stmt).trim_end());
+        start = end;
+    }
+    Some(statements)
+}
+
+/// Splits SQL into statements on semicolons, skipping the ones in quotes, comments
+/// and `CREATE TRIGGER` bodies. Empty statements are skipped.
+fn split_scanned(sql: &str) -> Vec<&str> {
+    let bytes = sql.as_bytes();
+    let mut statements = Vec::new();
+    let mut start = 0;
+    let mut i = 0;
+    // Trigger bodies are delimited with BEGIN and END, and contain semicolons
+    let mut in_trigger = false;
+    // Whether the last token is an END closing the trigger body, rather than a CASE expression
+    let mut trigger_end = false;
+    let mut case_depth = 0;
+    // Whether the words seen so far (comments aside) are CREATE [TEMP | TEMPORARY]
+    let mut create_prefix = false;
+    let mut first_word = true;
+    // Fragments holding only comments are not statements
+    let mut has_tokens = false;
+    while i < bytes.len() {
+        match bytes[i] {
+            quote @ (b'\'' | b'"' | b'`') => {
+                has_tokens = true;
+                i += 1;
+                while i < bytes.len() && bytes[i] != quote {
+                    i += 1;
+                }
+            }
+            b'[' => {
+                has_tokens = true;
+                while i < bytes.len() && bytes[i] != b']' {
+                    i += 1;
+                }
+            }
+            b'-' if bytes.get(i + 1) == Some(&b'-') => {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            }
+            b'/' if bytes.get(i + 1) == Some(&b'*') => {
+                i += 2;
+                while i < bytes.len() && !bytes[i..].starts_with(b"*/") {
+                    i += 1;
+                }
+                i += 1;
+            }
+            b';' if !in_trigger || trigger_end => {
+                if has_tokens {
+                    statements.push(sql[start..i].trim());
+                }
+                start = i + 1;
+                has_tokens = false;
+                in_trigger = false;
+                trigger_end = false;
+                first_word = true;
+            }
+            b if b.is_ascii_alphanumeric() || b == b'_' => {
+                has_tokens = true;
+                let word_start = i;
+                while i < bytes.len() && (bytes[i].is_ascii_alphanumeric() || bytes[i] == b'_') {
+                    i += 1;
+                }
+                let word = &sql[word_start..i];
+                trigger_end = false;
+                if in_trigger && word.eq_ignore_ascii_case("CASE") {
+                    case_depth += 1;
+                } else if in_trigger && word.eq_ignore_ascii_case("END") {
+                    if case_depth > 0 {
+                        case_depth -= 1;
+                    } else {
+                        trigger_end = true;
+                    }
+                }
+                if first_word {
+                    create_prefix = word.eq_ignore_ascii_case("CREATE");
+                    first_word = false;
+                } else if create_prefix && word.eq_ignore_ascii_case("TRIGGER") {
+                    in_trigger = true;
+                    create_prefix = false;
+                } else if !word.eq_ignore_ascii_case("TEMP")
+                    && !word.eq_ignore_ascii_case("TEMPORARY")
+                {
+                    create_prefix = false;
+                }
+                continue;
+            }
+            b if b.is_ascii_whitespace() => (),
+            _ => {
+                has_tokens = true;
+                trigger_end = false;
+                create_prefix = false;
+                first_word = false;
+            }
+        }
+        i += 1;
+    }
+    if has_tokens {
+        statements.push(sql[start..].trim());
+    }
+    statements
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split() {
+        let splitters: [fn(&str) -> Vec<&str>; 2] = [split_statements, split_scanned];
+        for split_statements in splitters {
+            split_with(split_statements);
+        }
+        // SQL the parser rejects is still split
+        assert_eq!(
+            split_statements("SELECT 1; SELEKT 2"),
+            ["SELECT 1", "SELEKT 2"]
+        );
+    }
+
+    fn split_with(split_statements: fn(&str) -> Vec<&str>) {
+        assert_eq!(split_statements(""), Vec::<&str>::new());
+        assert_eq!(
+            split_statements("SELECT 1; SELECT 2;\n\n"),
+            ["SELECT 1", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("SELECT ';' AS \"a;b\"; -- c;\nSELECT /* ; */ 2"),
+            ["SELECT ';' AS \"a;b\"", "-- c;\nSELECT /* ; */ 2"]
+        );
+        let trigger = "CREATE TEMP TRIGGER t AFTER INSERT ON a BEGIN\n  INSERT INTO b VALUES (1);\n  DELETE FROM c;\nEND";
+        assert_eq!(
+            split_statements(&format!("{trigger};\nSELECT 1")),
+            [trigger, "SELECT 1"]
+        );
+        // Leading comments do not hide the start of a trigger
+        for comment in ["-- v3\n", "/* v3 */ "] {
+            let commented = format!("{comment}{trigger}");
+            assert_eq!(
+                split_statements(&format!("{commented};\nSELECT 1")),
+                [commented.as_str(), "SELECT 1"]
+            );
+        }
+        // END closing a CASE expression does not end the trigger body
+        let case_trigger = "CREATE TRIGGER t AFTER INSERT ON a BEGIN\n  INSERT INTO b VALUES (CASE WHEN new.x THEN 1 ELSE 0 END);\n  SELECT CASE new.x WHEN 1 THEN CASE WHEN 1 THEN 2 END END;\nEND";
+        assert_eq!(
+            split_statements(&format!("{case_trigger}; SELECT 1; SELECT 2")),
+            [case_trigger, "SELECT 1", "SELECT 2"]
+        );
+        // Comment-only fragments are dropped
+        assert_eq!(
+            split_statements("SELECT 1; -- done\n/* really */"),
+            ["SELECT 1"]
+        );
+        // A column named trigger does not start a trigger body
+        assert_eq!(
+            split_statements("SELECT trigger FROM t; SELECT 2"),
+            ["SELECT trigger FROM t", "SELECT 2"]
+        );
+        assert_eq!(
+            split_statements("SELECT 1;; SELECT 2"),
+            ["SELECT 1", "SELECT 2"]
+        );
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn migrations_are_applied_once() {
+        let db = Client::in_memory().unwrap();
+        let migrations = [
+            (1, "CREATE TABLE t(x); INSERT INTO t VALUES (1)"),
+            (2, "INSERT INTO t VALUES (2);"),
+        ];
+        assert_eq!(Migrator::new(&db).run(&migrations).await.unwrap(), [1, 2]);
+        assert!(Migrator::new(&db)
+            .run(&migrations)
+            .await
+            .unwrap()
+            .is_empty());
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 2);
+
+        let migrations = [
+            migrations[0],
+            migrations[1],
+            (3, "INSERT INTO t VALUES (3)"),
+        ];
+        assert_eq!(Migrator::new(&db).run(&migrations).await.unwrap(), [3]);
+        assert_eq!(Migrator::new(&db).applied().await.unwrap(), [1, 2, 3]);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn migration_with_trigger() {
+        let db = Client::in_memory().unwrap();
+        let migrations = [(
+            1,
+            "CREATE TABLE t(x); CREATE TABLE log(kind);
+            CREATE TRIGGER t_log AFTER INSERT ON t BEGIN
+                INSERT INTO log VALUES (CASE WHEN new.x > 0 THEN 'positive' ELSE 'other' END);
+            END;
+            INSERT INTO t VALUES (1), (-1);",
+        )];
+        assert_eq!(Migrator::new(&db).run(&migrations).await.unwrap(), [1]);
+        let rs = db.execute("SELECT kind FROM log").await.unwrap();
+        let kinds: Vec<&str> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(kinds, ["positive", "other"]);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn failed_migration_is_rolled_back() {
+        let db = Client::in_memory().unwrap();
+        let migrations = [
+            (1, "CREATE TABLE t(x)"),
+            (
+                2,
+                "INSERT INTO t VALUES (1); INSERT INTO missing VALUES (1)",
+            ),
+        ];
+        assert!(Migrator::new(&db).run(&migrations).await.is_err());
+        assert_eq!(Migrator::new(&db).applied().await.unwrap(), [1]);
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 0);
+
+        let unsorted = [(2, "SELECT 1"), (1, "SELECT 1")];
+        let err = Migrator::new(&db).run(&unsorted).await.unwrap_err();
+        assert!(matches!(
+            err.downcast_ref::<Error>(),
+            Some(Error::Misuse(_))
+        ));
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[tokio::test]
+    async fn migration_without_transaction() {
+        let db = Client::in_memory().unwrap();
+        let migrations = [(1, "CREATE TABLE t(x)"), (2, "VACUUM")];
+        assert!(Migrator::new(&db).run(&migrations).await.is_err());
+        assert_eq!(
+            Migrator::new(&db)
+                .without_transaction()
+                .run(&migrations)
+                .await
+                .unwrap(),
+            [2]
+        );
+    }
+}
@@ -0,0 +1,362 @@
+//! Scripted [HttpBackend] for testing code built on top of the HTTP [Client](crate::Client)
+//! without a running server, enabled with the `test-util` feature.
+
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+use anyhow::Result;
+
+use crate::http::HttpBackend;
+use crate::{ResultSet, Value};
+
+// A queued answer to a single request
+#[derive(Debug)]
+enum MockResponse {
+    // Statement result in the Hrana format
+    Result(serde_json::Value),
+    Error {
+        message: String,
+        code: Option<String>,
+    },
+    Raw(String),
+    Transport(String),
+}
+
+/// A request received by a [MockBackend]
+#[derive(Clone, Debug, PartialEq)]
+pub struct MockRequest {
+    /// URL the request was sent to
+    pub url: String,
+    /// Value of the `Authorization` header
+    pub auth: String,
+    /// The [`pipeline::ClientMsg`](crate::proto::pipeline::ClientMsg), as JSON
+    pub body: serde_json::Value,
+    /// Value of the [REQUEST_ID_HEADER](crate::http::REQUEST_ID_HEADER) header, if sent
+    pub request_id: Option<String>,
+}
+
+impl MockRequest {
+    /// Returns the SQL of all statements in the request, in order,
+    /// including the steps of batches.
+    pub fn sql(&self) -> Vec<&str> {
+        let requests = self.body["requests"].as_array().into_iter().flatten();
+        requests
+            .flat_map(|request| match request["type"].as_str() {
+                Some("execute") => vec![&request["stmt"]],
+                Some("batch") => request["batch"]["steps"]
+                    .as_array()
+                    .into_iter()
+                    .flatten()
+                    .map(|step| &step["stmt"])
+                    .collect(),
+                _ => vec![],
+            })
+            .filter_map(|stmt| stmt["sql"].as_str())
+            .collect()
+    }
+}
+
+/// [HttpBackend] answering requests with queued responses, in order, and recording
+/// the requests for later inspection.
+///
+/// Each response answers a whole request: [`MockBackend::push_result()`] answers a single
+/// statement with given result, or each step of a batch with it, and
+/// [`MockBackend::push_error()`] fails the request the way sqld reports a failed statement.
+/// Requests sent while the queue is empty fail, except ones only closing a stream, which
+/// don't use the queue. Requests that keep their stream open, e.g. in interactive
+/// transactions, get a baton.
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use std::sync::Arc;
+/// use libsql_client::{http::MockBackend, Client, Config, Error};
+///
+/// let backend = Arc::new(MockBackend::new());
+/// let db = Client::from_config_with_http_backend(
+///     Config::new("http://mock")?,
+///     backend.clone(),
+/// )?;
+///
+/// backend.push_error("UNIQUE constraint failed: t.x", Some("SQLITE_CONSTRAINT_UNIQUE"));
+/// let err = futures::executor::block_on(db.execute("INSERT INTO t VALUES (1)")).unwrap_err();
+/// assert!(matches!(
+///     err.downcast_ref::<Error>(),
+///     Some(Error::Database { code: Some(code), .. }) if code == "SQLITE_CONSTRAINT_UNIQUE"
+/// ));
+/// assert_eq!(backend.requests()[0].sql(), ["INSERT INTO t VALUES (1)"]);
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Debug, Default)]
+pub struct MockBackend {
+    responses: Mutex<VecDeque<MockResponse>>,
+    requests: Mutex<Vec<MockRequest>>,
+}
+
+impl MockBackend {
+    /// Creates a backend with no queued responses
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a successful response with given result, e.g. one built with [`ResultSet::new()`].
+    /// Whether it's a write is derived from the executed statement, as with real servers.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use libsql_client::{http::MockBackend, Client, Config, ResultSet, Value};
+    ///
+    /// let backend = std::sync::Arc::new(MockBackend::new());
+    /// let db = Client::from_config_with_http_backend(Config::new("http://mock")?, backend.clone())?;
+    ///
+    /// let mut rs = ResultSet::new(vec!["id".into()], vec![]);
+    /// rs.rows_affected = 1;
+    /// rs.last_insert_rowid = Some(5);
+    /// backend.push_result(&rs);
+    /// let rs = futures::executor::block_on(db.execute("INSERT INTO t VALUES (5) RETURNING id"))?;
+    /// assert_eq!(rs.last_insert_rowid, Some(5));
+    /// assert!(rs.is_write());
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn push_result(&self, result: &ResultSet) {
+        self.push(MockResponse::Result(stmt_result(
+            &result.columns,
+            result.rows.iter().map(|row| &row.values),
+            result.rows_affected,
+            result.last_insert_rowid,
+        )));
+    }
+
+    /// Queues a successful response with given columns and rows
+    pub fn push_rows(&self, columns: &[&str], rows: Vec<Vec<Value>>) {
+        self.push(MockResponse::Result(stmt_result(columns, &rows, 0, None)));
+    }
+
+    /// Queues a successful response with no rows, e.g. for a write,
+    /// reporting given number of affected rows
+    pub fn push_affected(&self, rows_affected: u64) {
+        self.push(MockResponse::Result(stmt_result::<&str, Vec<Value>>(
+            &[],
+            [],
+            rows_affected,
+            None,
+        )));
+    }
+
+    /// Queues a failed statement, reported with `message` and an optional SQLite result
+    /// `code`, e.g. `SQLITE_BUSY`. It's surfaced as [`Error::Database`](crate::Error::Database)
+    /// or [`Error::Storage`](crate::Error::Storage).
+    pub fn push_error(&self, message: impl Into<String>, code: Option<&str>) {
+        self.push(MockResponse::Error {
+            message: message.into(),
+            code: code.map(str::to_string),
+        });
+    }
+
+    /// Queues a raw [`pipeline::ServerMsg`](crate::proto::pipeline::ServerMsg) body,
+    /// sent back as it is, e.g. to test handling of malformed responses
+    pub fn push_raw(&self, body: impl Into<String>) {
+        self.push(MockResponse::Raw(body.into()));
+    }
+
+    /// Queues a failure to send the request, e.g. a lost connection
+    pub fn push_transport_error(&self, message: impl Into<String>) {
+        self.push(MockResponse::Transport(message.into()));
+    }
+
+    /// Returns the requests received so far
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.requests.lock().unwrap().clone()
+    }
+
+    /// Returns and forgets the requests received so far
+    pub fn take_requests(&self) -> Vec<MockRequest> {
+        std::mem::take(&mut *self.requests.lock().unwrap())
+    }
+
+    /// Returns how many queued responses weren't used yet
+    pub fn pending(&self) -> usize {
+        self.responses.lock().unwrap().len()
+    }
+
+    fn push(&self, response: MockResponse) {
+        self.responses.lock().unwrap().push_back(response);
+    }
+
+    fn respond(&self, request: &MockRequest) -> Result<String> {
+        let requests = request.body["requests"].as_array().map_or(&[][..], |r| r);
+        // Closing a stream needs no script
+        if let [close] = requests {
+            if close["type"].as_str() == Some("close") {
+                return Ok(r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"close"}}]}"#.to_string());
+            }
+        }
+        let response = self.responses.lock().unwrap().pop_front();
+        let Some(response) = response else {
+            anyhow::bail!("MockBackend: no response queued for {:?}", request.sql());
+        };
+        let result = match response {
+            MockResponse::Raw(body) => return Ok(body),
+            MockResponse::Transport(message) => anyhow::bail!(message),
+            MockResponse::Error { message, code } => {
+                serde_json::json!({"type": "error", "error": {"message": message, "code": code}})
+            }
+            MockResponse::Result(result) => {
+                let response = match request.body["requests"][0]["type"].as_str() {
+                    Some("batch") => {
+                        let steps = request.body["requests"][0]["batch"]["steps"]
+                            .as_array()
+                            .map_or(0, Vec::len);
+                        serde_json::json!({"type": "batch", "result": {
+                            "step_results": vec![result; steps],
+                            "step_errors": vec![serde_json::Value::Null; steps],
+                        }})
+                    }
+                    _ => serde_json::json!({"type": "execute", "result": result}),
+                };
+                serde_json::json!({"type": "ok", "response": response})
+            }
+        };
+
+        let mut results = vec![result];
+        let closes = requests
+            .last()
+            .is_some_and(|r| r["type"].as_str() == Some("close"));
+        if closes && requests.len() > 1 {
+            results.push(serde_json::json!({"type": "ok", "response": {"type": "close"}}));
+        }
+        let baton = (!closes).then(|| format!("mock-{}", self.requests.lock().unwrap().len()));
+        Ok(serde_json::json!({"baton": baton, "base_url": null, "results": results}).to_string())
+    }
+}
+
+// Builds a statement result in the Hrana format
+fn stmt_result<C: AsRef<str>, R: serde::Serialize>(
+    columns: &[C],
+    rows: impl IntoIterator<Item = R>,
+    rows_affected: u64,
+    last_insert_rowid: Option<i64>,
+) -> serde_json::Value {
+    let cols: Vec<_> = columns
+        .iter()
+        .map(|name| serde_json::json!({ "name": name.as_ref() }))
+        .collect();
+    let rows: Vec<_> = rows.into_iter().collect();
+    serde_json::json!({
+        "cols": cols,
+        "rows": rows,
+        "affected_row_count": rows_affected,
+        "last_insert_rowid": last_insert_rowid.map(|id| id.to_string()),
+    })
+}
+
+impl HttpBackend for MockBackend {
+    fn send<'a>(
+        &'a self,
+        url: String,
+        auth: String,
+        body: String,
+        request_id: Option<&'a str>,
+    ) -> futures::future::BoxFuture<'a, Result<String>> {
+        Box::pin(async move {
+            let request = MockRequest {
+                url,
+                auth,
+                body: serde_json::from_str(&body)?,
+                request_id: request_id.map(str::to_string),
+            };
+            let response = self.respond(&request);
+            self.requests.lock().unwrap().push(request);
+            response
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Config;
+    use std::sync::Arc;
+
+    fn mock_client() -> (Arc<MockBackend>, crate::Client) {
+        let backend = Arc::new(MockBackend::new());
+        let config = Config::new("http://mock").unwrap();
+        let db = crate::Client::from_config_with_http_backend(config, backend.clone()).unwrap();
+        (backend, db)
+    }
+
+    #[test]
+    fn queued_results() {
+        let (backend, db) = mock_client();
+        backend.push_rows(&["x"], vec![vec![Value::from(42)], vec![Value::Null]]);
+        let mut rs = ResultSet::new(vec!["y".into()], vec![vec![Value::from("text")]]);
+        rs.rows_affected = 1;
+        rs.last_insert_rowid = Some(7);
+        backend.push_result(&rs);
+        backend.push_affected(0);
+
+        let rs = futures::executor::block_on(db.execute("SELECT 42 AS x")).unwrap();
+        assert_eq!(rs.columns, ["x"]);
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 42);
+        assert!(matches!(rs.rows[1].values[0], Value::Null));
+        let rs = futures::executor::block_on(db.execute("INSERT INTO t VALUES (1)")).unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "text");
+        assert_eq!((rs.rows_affected, rs.last_insert_rowid), (1, Some(7)));
+
+        let results = futures::executor::block_on(db.batch(["SELECT 1", "SELECT 2"])).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results.iter().all(|rs| rs.rows.is_empty()));
+
+        let requests = backend.take_requests();
+        assert_eq!(requests.len(), 3);
+        assert_eq!(requests[0].url, "http://mock/v2/pipeline");
+        assert_eq!(requests[0].sql(), ["SELECT 42 AS x"]);
+        assert_eq!(requests[2].sql(), ["BEGIN", "SELECT 1", "SELECT 2", "END"]);
+        assert_eq!(backend.pending(), 0);
+    }
+
+    #[test]
+    fn queued_failures() {
+        let (backend, db) = mock_client();
+        backend.push_error("disk I/O error", Some("SQLITE_IOERR_WRITE"));
+        backend.push_transport_error("connection reset");
+        backend.push_raw("not json");
+
+        let err = futures::executor::block_on(db.execute("SELECT 1")).unwrap_err();
+        assert!(
+            matches!(err.downcast_ref(), Some(crate::Error::Storage { code, .. }) if code == "SQLITE_IOERR_WRITE"),
+            "{err}"
+        );
+        let err = futures::executor::block_on(db.execute("SELECT 1")).unwrap_err();
+        assert_eq!(err.to_string(), "connection reset");
+        assert!(futures::executor::block_on(db.execute("SELECT 1")).is_err());
+        // Nothing left in the queue
+        let err = futures::executor::block_on(db.execute("SELECT 1")).unwrap_err();
+        assert!(err.to_string().contains("no response queued"), "{err}");
+        assert_eq!(backend.requests().len(), 4);
+    }
+
+    #[test]
+    fn interactive_transaction() {
+        let (backend, db) = mock_client();
+        for _ in 0..3 {
+            backend.push_affected(0);
+        }
+        let tx = futures::executor::block_on(db.transaction()).unwrap();
+        futures::executor::block_on(tx.execute("INSERT INTO t VALUES (1)")).unwrap();
+        futures::executor::block_on(tx.commit()).unwrap();
+        let sql: Vec<_> = backend
+            .requests()
+            .iter()
+            .flat_map(|r| r.sql().into_iter().map(str::to_string).collect::<Vec<_>>())
+            .collect();
+        assert_eq!(sql, ["BEGIN", "INSERT INTO t VALUES (1)", "COMMIT"]);
+    }
+}
@@ -10,3 +10,350 @@ pub use hrana_client_proto::{
     pipeline, Batch, BatchReq, BatchResp, BatchResult, ClientMsg, Col, Error, ExecuteReq,
     ExecuteResp, OpenStreamReq, Request, Response, ServerMsg, Stmt, StmtResult, Value,
 };
+
+/// Displays a [Value] like SQLite's command line shell does, as opposed to the JSON-like
+/// `Display` implementation of [Value] itself.
+///
+/// NULL is rendered as `NULL`, integers and text as they are (text unquoted), floats with all
+/// significant digits and a fractional part (`2.0`, `0.1`, `1e300`), and blobs as `x'..'`
+/// hex literals. Width, fill and alignment flags are respected, so it works in tables.
+/// It serializes to the same string.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::{DisplayValue, Value};
+///
+/// let values = [Value::Null, Value::from(2.0), Value::from("it's"), Value::from(vec![0u8, 255])];
+/// let cells: Vec<String> = values.iter().map(|v| format!("{:>6}", DisplayValue(v))).collect();
+/// assert_eq!(cells, ["  NULL", "   2.0", "  it's", "x'00ff'"]);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct DisplayValue<'a>(pub &'a Value);
+
+impl std::fmt::Display for DisplayValue<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.0 {
+            Value::Null => f.pad("NULL"),
+            Value::Integer { value } => f.pad(&value.to_string()),
+            Value::Float { value } if value.is_infinite() => {
+                f.pad(if *value > 0.0 { "Inf" } else { "-Inf" })
+            }
+            // Debug keeps the fractional part of whole numbers, unlike Display
+            Value::Float { value } => f.pad(&format!("{value:?}")),
+            Value::Text { value } => f.pad(value),
+            Value::Blob { .. } => f.pad(&crate::utils::sql_literal(self.0)),
+        }
+    }
+}
+
+impl serde::Serialize for DisplayValue<'_> {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Converts an integer of any width to a [Value], failing if it doesn't fit into `i64`,
+/// the type of SQLite integers.
+///
+/// Integers which always fit - `i8` to `i64` and `u8` to `u32` - as well as `f32` and `f64`
+/// convert with [`From`] and can be bound directly. `u64`, `i128` and `u128` have no
+/// such conversion, since their values may not fit: bind them with this function, which returns
+/// an error instead of silently truncating. `usize` and `isize` do convert with [`From`],
+/// but a `usize` above `i64::MAX` wraps around to a negative number, so values which may get
+/// that large should go through this function too.
+///
+/// # Examples
+///
+/// ```
+/// use libsql_client::{proto::try_integer, Statement};
+///
+/// let stmt = Statement::with_args("SELECT ?, ?", &[try_integer(42u64).unwrap(), 7i8.into()]);
+/// assert!(try_integer(u64::MAX).is_err());
+/// ```
+pub fn try_integer<T>(value: T) -> anyhow::Result<Value>
+where
+    T: TryInto<i64> + std::fmt::Display + Copy,
+{
+    match value.try_into() {
+        Ok(value) => Ok(Value::Integer { value }),
+        Err(_) => anyhow::bail!("Integer {value} does not fit into a 64-bit signed integer"),
+    }
+}
+
+/// A BLOB of exactly `N` bytes, e.g. a hash or a key, read without allocating.
+///
+/// Converting a [Value] fails unless it's a blob of exactly `N` bytes, so the size is checked
+/// once when reading the row. Arrays can't be read with [`Row::try_get()`](crate::Row::try_get)
+/// directly, because neither the trait nor the types involved are defined in this crate.
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use libsql_client::FixedBlob;
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// let rs = db.execute("SELECT zeroblob(32)")?;
+/// let FixedBlob(hash) = rs.rows[0].try_get::<FixedBlob<32>>(0)?;
+/// assert_eq!(hash, [0; 32]);
+/// assert!(rs.rows[0].try_get::<FixedBlob<16>>(0).is_err());
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct FixedBlob<const N: usize>(pub [u8; N]);
+
+impl<const N: usize> From<FixedBlob<N>> for Value {
+    fn from(blob: FixedBlob<N>) -> Self {
+        Value::Blob {
+            value: blob.0.to_vec(),
+        }
+    }
+}
+
+impl<const N: usize> TryFrom<&Value> for FixedBlob<N> {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Blob { value } => value.as_slice().try_into().map(Self).map_err(|_| {
+                format!(
+                    "Cannot convert a blob of {} bytes to one of {N} bytes",
+                    value.len()
+                )
+            }),
+            _ => Err(format!("Cannot convert {value} to a blob of {N} bytes")),
+        }
+    }
+}
+
+/// A boolean, stored the way SQLite has none: as an integer `0` or `1`.
+///
+/// Converting a [Value] also accepts the text forms `'true'`, `'false'` (in any case), `'0'`
+/// and `'1'`, commonly stored by other tools, and fails for anything else, including `NULL`
+/// and integers other than 0 and 1 - read an `Option<SqlBool>` from nullable columns instead.
+/// Like [FixedBlob], it's a wrapper because `bool` can't be read with
+/// [`Row::try_get()`](crate::Row::try_get) directly. [`de::from_row()`](crate::de::from_row)
+/// reads `bool` fields the same way.
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use libsql_client::SqlBool;
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// let rs = db.execute("SELECT 1, 'false', 2")?;
+/// assert!(rs.rows[0].try_get::<SqlBool>(0)?.0);
+/// assert!(!rs.rows[0].try_get::<SqlBool>(1)?.0);
+/// assert!(rs.rows[0].try_get::<SqlBool>(2).is_err());
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub struct SqlBool(pub bool);
+
+impl From<SqlBool> for Value {
+    fn from(value: SqlBool) -> Self {
+        Value::Integer {
+            value: value.0 as i64,
+        }
+    }
+}
+
+impl TryFrom<&Value> for SqlBool {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Integer { value: 0 } => Ok(SqlBool(false)),
+            Value::Integer { value: 1 } => Ok(SqlBool(true)),
+            Value::Text { value } if value == "0" || value.eq_ignore_ascii_case("false") => {
+                Ok(SqlBool(false))
+            }
+            Value::Text { value } if value == "1" || value.eq_ignore_ascii_case("true") => {
+                Ok(SqlBool(true))
+            }
+            _ => Err(format!("Cannot convert {value} to a boolean")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn display(value: Value) -> String {
+        DisplayValue(&value).to_string()
+    }
+
+    #[test]
+    fn display_value() {
+        assert_eq!(display(Value::Null), "NULL");
+        assert_eq!(display(Value::Integer { value: -42 }), "-42");
+        assert_eq!(display(Value::Float { value: 2.0 }), "2.0");
+        assert_eq!(display(Value::Float { value: 0.1 }), "0.1");
+        assert_eq!(
+            display(Value::Float {
+                value: std::f64::consts::PI
+            }),
+            "3.141592653589793"
+        );
+        assert_eq!(display(Value::Float { value: 1e300 }), "1e300");
+        assert_eq!(
+            display(Value::Float {
+                value: f64::NEG_INFINITY
+            }),
+            "-Inf"
+        );
+        assert_eq!(
+            display(Value::Text {
+                value: "it's".into()
+            }),
+            "it's"
+        );
+        assert_eq!(display(Value::Blob { value: vec![] }), "x''");
+        assert_eq!(
+            display(Value::Blob {
+                value: vec![1, 171]
+            }),
+            "x'01ab'"
+        );
+        assert_eq!(format!("|{:<5}|", DisplayValue(&Value::Null)), "|NULL |");
+    }
+
+    #[test]
+    fn integer_widths() {
+        let ints = [
+            Value::from(i8::MIN),
+            Value::from(i16::MIN),
+            Value::from(i32::MIN),
+            Value::from(i64::MIN),
+            Value::from(u8::MAX),
+            Value::from(u16::MAX),
+            Value::from(u32::MAX),
+            try_integer(i64::MAX as u64).unwrap(),
+            try_integer(-1i128).unwrap(),
+            try_integer(1u128).unwrap(),
+            try_integer(usize::MIN).unwrap(),
+        ];
+        let expected = [
+            i8::MIN as i64,
+            i16::MIN as i64,
+            i32::MIN as i64,
+            i64::MIN,
+            u8::MAX as i64,
+            u16::MAX as i64,
+            u32::MAX as i64,
+            i64::MAX,
+            -1,
+            1,
+            0,
+        ];
+        for (value, expected) in ints.iter().zip(expected) {
+            assert!(
+                matches!(value, Value::Integer { value } if *value == expected),
+                "{value} != {expected}"
+            );
+        }
+
+        assert!(try_integer(i64::MAX as u64 + 1).is_err());
+        assert!(try_integer(u64::MAX).is_err());
+        assert!(try_integer(i128::MIN).is_err());
+        let err = try_integer(u128::MAX).unwrap_err();
+        assert!(err.to_string().contains(&u128::MAX.to_string()), "{err}");
+    }
+
+    #[test]
+    fn float_widths() {
+        assert!(matches!(Value::from(0.5f32), Value::Float { value } if value == 0.5));
+        assert!(matches!(Value::from(0.1f64), Value::Float { value } if value == 0.1));
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn bind_widths() {
+        use crate::Statement;
+
+        let db = crate::SyncClient::in_memory().unwrap();
+        let stmt = Statement::with_args(
+            "SELECT ?, ?, ?, ?, ?, ?, ?, ?, ?",
+            &[
+                Value::from(-8i8),
+                Value::from(-16i16),
+                Value::from(-32i32),
+                Value::from(8u8),
+                Value::from(16u16),
+                Value::from(u32::MAX),
+                try_integer(64u64).unwrap(),
+                try_integer(-128i128).unwrap(),
+                Value::from(1.5f32),
+            ],
+        );
+        let rs = db.execute(stmt).unwrap();
+        let row = &rs.rows[0];
+        assert_eq!(row.try_get::<i8>(0).unwrap(), -8);
+        assert_eq!(row.try_get::<i16>(1).unwrap(), -16);
+        assert_eq!(row.try_get::<i32>(2).unwrap(), -32);
+        assert_eq!(row.try_get::<u8>(3).unwrap(), 8);
+        assert_eq!(row.try_get::<u16>(4).unwrap(), 16);
+        assert_eq!(row.try_get::<u32>(5).unwrap(), u32::MAX);
+        assert_eq!(row.try_get::<u64>(6).unwrap(), 64);
+        assert_eq!(row.try_get::<i64>(7).unwrap(), -128);
+        assert_eq!(row.try_get::<f64>(8).unwrap(), 1.5);
+    }
+
+    #[test]
+    fn serialize_display_value() {
+        let values = [Value::Null, Value::Blob { value: vec![255] }];
+        let json: Vec<_> = values.iter().map(DisplayValue).collect();
+        assert_eq!(serde_json::to_string(&json).unwrap(), r#"["NULL","x'ff'"]"#);
+    }
+
+    #[test]
+    fn sql_bool() {
+        let to_bool = |value: Value| SqlBool::try_from(&value).map(|b| b.0);
+        assert_eq!(to_bool(Value::from(0)), Ok(false));
+        assert_eq!(to_bool(Value::from(1)), Ok(true));
+        for text in ["true", "TRUE", "True", "1"] {
+            assert_eq!(to_bool(Value::from(text)), Ok(true), "{text}");
+        }
+        for text in ["false", "FALSE", "0"] {
+            assert_eq!(to_bool(Value::from(text)), Ok(false), "{text}");
+        }
+        for value in [
+            Value::from(2),
+            Value::from(-1),
+            Value::from("yes"),
+            Value::from(""),
+            Value::from(1.0),
+            Value::Null,
+        ] {
+            assert!(to_bool(value.clone()).is_err(), "{value}");
+        }
+        assert!(matches!(
+            Value::from(SqlBool(true)),
+            Value::Integer { value: 1 }
+        ));
+    }
+
+    #[test]
+    fn fixed_blob() {
+        let blob = |len: usize| Value::Blob {
+            value: (0..len as u8).collect(),
+        };
+        let FixedBlob(bytes) = FixedBlob::<4>::try_from(&blob(4)).unwrap();
+        assert_eq!(bytes, [0, 1, 2, 3]);
+        let err = FixedBlob::<4>::try_from(&blob(3)).unwrap_err();
+        assert_eq!(err, "Cannot convert a blob of 3 bytes to one of 4 bytes");
+        let err = FixedBlob::<4>::try_from(&blob(5)).unwrap_err();
+        assert_eq!(err, "Cannot convert a blob of 5 bytes to one of 4 bytes");
+        assert!(FixedBlob::<4>::try_from(&Value::from("abcd")).is_err());
+        assert!(FixedBlob::<0>::try_from(&blob(0)).is_ok());
+        let value = Value::from(FixedBlob([0, 1, 2, 3]));
+        assert!(matches!(value, Value::Blob { value } if value == [0, 1, 2, 3]));
+    }
+}
@@ -1,5 +1,6 @@
 use anyhow::Result;
 
+use crate::client::Config;
 use crate::proto::pipeline;
 
 #[derive(Clone, Debug)]
@@ -14,27 +15,77 @@ impl HttpClient {
         }
     }
 
+    /// Creates an HTTP client sending requests through a preconfigured `reqwest::Client`,
+    /// e.g. one with custom DNS resolution, proxies or timeouts.
+    pub fn with_connector(connector: reqwest::Client) -> Self {
+        Self { inner: connector }
+    }
+
+    /// Creates an HTTP client with TLS and connection settings taken from [Config]:
+    /// extra root certificates, optionally disabled certificate validation,
+    /// the idle timeout of pooled connections and TCP keep-alive.
+    pub fn from_config(config: &Config) -> Result<Self> {
+        let mut builder = reqwest::Client::builder();
+        if let Some(timeout) = config.pool_idle_timeout {
+            builder = builder.pool_idle_timeout(timeout);
+        }
+        if let Some(interval) = config.tcp_keepalive {
+            builder = builder.tcp_keepalive(interval);
+        }
+        for pem in &config.tls_root_certificates {
+            let cert = reqwest::Certificate::from_pem(pem)
+                .map_err(|e| anyhow::anyhow!("Invalid root certificate: {e}"))?;
+            builder = builder.add_root_certificate(cert);
+        }
+        #[cfg(feature = "danger_accept_invalid_certs")]
+        if config.danger_accept_invalid_certs {
+            tracing::warn!("TLS certificate validation is disabled");
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        Ok(Self {
+            inner: builder.build()?,
+        })
+    }
+
     pub async fn send(
         &self,
         url: String,
         auth: String,
         body: String,
     ) -> Result<pipeline::ServerMsg> {
-        let response = self
+        let response = self.send_raw(url, auth, body).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Sends the request and returns the raw response body
+    pub async fn send_raw(&self, url: String, auth: String, body: String) -> Result<String> {
+        self.send_raw_with_request_id(url, auth, body, None).await
+    }
+
+    /// Like [`HttpClient::send_raw()`], tagging the request with
+    /// an [`X-Idempotency-Key`](crate::http::REQUEST_ID_HEADER) header if `request_id` is set
+    pub async fn send_raw_with_request_id(
+        &self,
+        url: String,
+        auth: String,
+        body: String,
+        request_id: Option<&str>,
+    ) -> Result<String> {
+        let mut request = self
             .inner
             .post(url)
             .body(body)
-            .header("Authorization", auth)
-            .send()
-            .await?;
+            .header("Authorization", auth);
+        if let Some(request_id) = request_id {
+            request = request.header(crate::http::REQUEST_ID_HEADER, request_id);
+        }
+        let response = request.send().await?;
         if response.status() != reqwest::StatusCode::OK {
             let status = response.status();
             let txt = response.text().await.unwrap_or_default();
             anyhow::bail!("{status}: {txt}");
         }
-        let resp: String = response.text().await?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&resp)?;
-        Ok(response)
+        Ok(response.text().await?)
     }
 }
 
@@ -43,3 +94,369 @@ impl Default for HttpClient {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+    use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+    use crate::{Backend, Client, Config};
+
+    const EXECUTE_RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+    // Reads a single HTTP request, responds with given pipeline response and returns
+    // the request's (lowercased) headers and body
+    async fn handle(
+        mut stream: impl AsyncRead + AsyncWrite + Unpin,
+        response: &str,
+    ) -> (String, String) {
+        let mut request = Vec::new();
+        let mut buf = vec![0; 64 * 1024];
+        let body_start = loop {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+            if let Some(pos) = request.windows(4).position(|w| w == b"\r\n\r\n") {
+                break pos + 4;
+            }
+        };
+        let headers = String::from_utf8_lossy(&request[..body_start]).to_lowercase();
+        let content_length: usize = headers
+            .lines()
+            .find_map(|l| l.strip_prefix("content-length:"))
+            .map(|l| l.trim().parse().unwrap())
+            .unwrap_or(0);
+        while request.len() < body_start + content_length {
+            let n = stream.read(&mut buf).await.unwrap();
+            request.extend_from_slice(&buf[..n]);
+        }
+        let response = format!(
+            "HTTP/1.1 200 OK\r\ncontent-length: {}\r\n\r\n{response}",
+            response.len()
+        );
+        stream.write_all(response.as_bytes()).await.unwrap();
+        stream.shutdown().await.ok();
+        let body = String::from_utf8(request[body_start..].to_vec()).unwrap();
+        (headers, body)
+    }
+
+    // Serves a single canned pipeline response over plain HTTP, returning the request headers and body
+    async fn serve_once(
+        response: &'static str,
+    ) -> (u16, tokio::task::JoinHandle<(String, String)>) {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let handle = tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            handle(socket, response).await
+        });
+        (port, handle)
+    }
+
+    // Serves canned pipeline responses to consecutive requests, one connection each
+    async fn serve_sequence(responses: &'static [&'static str]) -> u16 {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            for response in responses {
+                let (socket, _) = listener.accept().await.unwrap();
+                handle(socket, response).await;
+            }
+        });
+        port
+    }
+
+    // Serves a single canned pipeline response over TLS with a self-signed certificate
+    async fn serve_tls_once(cert: &rcgen::Certificate) -> u16 {
+        let tls_config = tokio_rustls::rustls::ServerConfig::builder()
+            .with_safe_defaults()
+            .with_no_client_auth()
+            .with_single_cert(
+                vec![tokio_rustls::rustls::Certificate(
+                    cert.serialize_der().unwrap(),
+                )],
+                tokio_rustls::rustls::PrivateKey(cert.serialize_private_key_der()),
+            )
+            .unwrap();
+        let acceptor = tokio_rustls::TlsAcceptor::from(Arc::new(tls_config));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (socket, _) = listener.accept().await.unwrap();
+            if let Ok(stream) = acceptor.accept(socket).await {
+                handle(stream, EXECUTE_RESPONSE).await;
+            }
+        });
+        port
+    }
+
+    fn http_config(port: u16) -> Config {
+        Config::new(format!("http://localhost:{port}/").as_str())
+            .unwrap()
+            .with_backend(Backend::Reqwest)
+    }
+
+    #[tokio::test]
+    async fn self_signed_root_certificate() {
+        let cert = rcgen::generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+
+        let port = serve_tls_once(&cert).await;
+        let config = Config::new(format!("https://localhost:{port}/").as_str())
+            .unwrap()
+            .with_backend(Backend::Reqwest);
+        let db = Client::from_config(config).await.unwrap();
+        assert!(db.execute("SELECT 1").await.is_err());
+
+        let port = serve_tls_once(&cert).await;
+        let config = Config::new(format!("https://localhost:{port}/").as_str())
+            .unwrap()
+            .with_backend(Backend::Reqwest)
+            .with_root_certificate(cert.serialize_pem().unwrap());
+        let db = Client::from_config(config).await.unwrap();
+        db.execute("SELECT 1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn pool_idle_timeout_and_tcp_keepalive() {
+        let (port, _) = serve_once(EXECUTE_RESPONSE).await;
+        let config = http_config(port)
+            .with_pool_idle_timeout(std::time::Duration::from_secs(30))
+            .with_tcp_keepalive(std::time::Duration::from_secs(15));
+        assert_eq!(
+            config.pool_idle_timeout,
+            Some(std::time::Duration::from_secs(30))
+        );
+        assert_eq!(
+            config.tcp_keepalive,
+            Some(std::time::Duration::from_secs(15))
+        );
+        let db = Client::from_config(config).await.unwrap();
+        db.execute("SELECT 1").await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn custom_connector() {
+        let (port, _) = serve_once(EXECUTE_RESPONSE).await;
+        // The host only resolves through the connector
+        let connector = reqwest::Client::builder()
+            .resolve("db.test", ([127, 0, 0, 1], port).into())
+            .build()
+            .unwrap();
+        let config = Config::new(format!("http://db.test:{port}/").as_str()).unwrap();
+        let db = Client::from_config_with_connector(config, connector)
+            .await
+            .unwrap();
+        db.execute("SELECT 1").await.unwrap();
+
+        let config = Config::new("file:////tmp/custom_connector.db")
+            .unwrap()
+            .with_backend(Backend::Local);
+        assert!(
+            Client::from_config_with_connector(config, reqwest::Client::new())
+                .await
+                .is_err()
+        );
+    }
+
+    #[tokio::test]
+    async fn transaction_without_baton() {
+        const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+
+        let (port, _) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let err = db.transaction().await.err().unwrap();
+        assert!(matches!(
+            err.downcast_ref::<crate::Error>(),
+            Some(crate::Error::Unsupported(_))
+        ));
+    }
+
+    #[tokio::test]
+    async fn returning_columns() {
+        const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[{"name":"id"},{"name":null}],"rows":[[{"type":"integer","value":"7"},{"type":"text","value":"2023-01-01"}]],"affected_row_count":1,"last_insert_rowid":"7"}}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+        let (port, request) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let rs = db
+            .execute("INSERT INTO t(x) VALUES (1) RETURNING id, created_at")
+            .await
+            .unwrap();
+        let (_, body) = request.await.unwrap();
+        let request: serde_json::Value = serde_json::from_str(&body).unwrap();
+        assert_eq!(request["requests"][0]["stmt"]["want_rows"], true);
+
+        assert_eq!(rs.columns, ["id", "1"]);
+        assert_eq!(rs.rows_affected, 1);
+        assert_eq!(rs.last_insert_rowid, Some(7));
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 7);
+        assert_eq!(rs.rows[0].try_column::<i64>("id").unwrap(), 7);
+        assert_eq!(rs.rows[0].try_get::<&str>(1).unwrap(), "2023-01-01");
+    }
+
+    #[tokio::test]
+    async fn large_integers() {
+        // 2^53 + 1 and i64::MAX aren't representable as f64, so they must never go through one
+        const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[{"name":"id"},{"name":"max"}],"rows":[[{"type":"integer","value":"9007199254740993"},{"type":"integer","value":"9223372036854775807"}]],"affected_row_count":1,"last_insert_rowid":"9007199254740993"}}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+        let (port, request) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let stmt = crate::Statement::with_args(
+            "INSERT INTO t(id, max) VALUES (?, ?) RETURNING id, max",
+            &[9007199254740993i64, i64::MAX],
+        );
+        let rs = db.execute(stmt).await.unwrap();
+        let (_, body) = request.await.unwrap();
+        assert!(
+            body.contains(r#"{"type":"integer","value":"9007199254740993"}"#),
+            "{body}"
+        );
+        assert!(
+            body.contains(r#"{"type":"integer","value":"9223372036854775807"}"#),
+            "{body}"
+        );
+
+        assert_eq!(rs.last_insert_rowid, Some(9007199254740993));
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 9007199254740993);
+        assert_eq!(rs.rows[0].try_get::<i64>(1).unwrap(), i64::MAX);
+    }
+
+    #[tokio::test]
+    async fn error_code() {
+        const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"error","error":{"message":"UNIQUE constraint failed: t.x","code":"SQLITE_CONSTRAINT_UNIQUE"}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+        let (port, _) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let err = db.execute("INSERT INTO t VALUES (1)").await.unwrap_err();
+        let err = err.downcast_ref::<crate::Error>().unwrap();
+        assert_eq!(err.code(), Some("SQLITE_CONSTRAINT_UNIQUE"));
+        assert_eq!(err.to_string(), "UNIQUE constraint failed: t.x");
+    }
+
+    #[tokio::test]
+    async fn max_param_size() {
+        // Nothing listens on the port, so only a local failure can be a misuse error
+        let config = http_config(1).with_max_param_size(4);
+        let db = Client::from_config(config).await.unwrap();
+        let is_misuse = |err: anyhow::Error| {
+            matches!(
+                err.downcast_ref::<crate::Error>(),
+                Some(crate::Error::Misuse(msg)) if msg.contains("exceeds max size")
+            )
+        };
+
+        let stmt = crate::Statement::with_args("SELECT ?, ?", &["abc", "abcde"]);
+        let err = db.execute(stmt).await.unwrap_err();
+        assert_eq!(err.to_string(), "Misuse: parameter 2 exceeds max size");
+
+        let stmt = crate::Statement::with_args("SELECT ?", &[vec![0u8; 5]]);
+        assert!(is_misuse(db.execute(stmt).await.unwrap_err()));
+
+        let stmt = crate::Statement::with_args("SELECT ?", &["abcd"]);
+        assert!(!is_misuse(db.execute(stmt).await.unwrap_err()));
+    }
+
+    #[tokio::test]
+    async fn timings() {
+        let (port, _) = serve_once(EXECUTE_RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        assert_eq!(db.http_timings(), Some(Default::default()));
+
+        db.execute("SELECT 1").await.unwrap();
+        let timings = db.http_timings().unwrap();
+        assert_eq!(timings.requests, 1);
+        assert!(timings.network > std::time::Duration::ZERO);
+        assert!(timings.deserialize > std::time::Duration::ZERO);
+
+        assert_eq!(Client::in_memory().unwrap().http_timings(), None);
+    }
+
+    #[tokio::test]
+    async fn transaction_baton() {
+        const RESPONSE: &str = r#"{"baton":"b4t0n","base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+
+        let (port, _) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let tx = db.transaction().await.unwrap();
+        assert!(tx.id() > 0);
+        assert_eq!(tx.baton().as_deref(), Some("b4t0n"));
+
+        let local = Client::in_memory().unwrap();
+        assert_eq!(local.transaction().await.unwrap().baton(), None);
+    }
+
+    #[tokio::test]
+    async fn transaction_stream_expired() {
+        const WITH_BATON: &str = r#"{"baton":"b4t0n","base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+        const WITHOUT_BATON: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"execute","result":{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}}}]}"#;
+
+        let port = serve_sequence(&[WITH_BATON, WITHOUT_BATON]).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let tx = db.transaction().await.unwrap();
+        assert_eq!(tx.baton().as_deref(), Some("b4t0n"));
+
+        let err = tx.execute("SELECT 1").await.unwrap_err();
+        assert!(
+            matches!(
+                err.downcast_ref::<crate::Error>(),
+                Some(crate::Error::StreamExpired(_))
+            ),
+            "{err}"
+        );
+        // The stale baton is forgotten
+        assert_eq!(tx.baton(), None);
+    }
+
+    #[tokio::test]
+    async fn batch_request_id() {
+        const RESPONSE: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[null,null,null],"step_errors":[null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+        let (port, request) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        db.batch_opts(
+            ["SELECT 1"],
+            crate::BatchOpts::new().with_request_id("req-42"),
+        )
+        .await
+        .ok();
+        let (headers, _) = request.await.unwrap();
+        assert!(
+            headers.lines().any(|l| l == "x-idempotency-key: req-42"),
+            "{headers}"
+        );
+
+        let (port, request) = serve_once(RESPONSE).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        db.batch(["SELECT 1"]).await.ok();
+        let (headers, _) = request.await.unwrap();
+        assert!(!headers.contains("x-idempotency-key"), "{headers}");
+    }
+
+    #[tokio::test]
+    async fn batch_in_transaction_aligns_results() {
+        // One result per step, including BEGIN and END
+        const WITH_WRAPPERS: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"a"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"b"}],"rows":[[{"type":"integer","value":"2"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null,null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+        // Results of the user's statements only
+        const WITHOUT_WRAPPERS: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[{"name":"a"}],"rows":[[{"type":"integer","value":"1"}]],"affected_row_count":0,"last_insert_rowid":null},{"cols":[{"name":"b"}],"rows":[[{"type":"integer","value":"2"}]],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null,null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+        // Neither, so the results can't be matched to the statements
+        const MISALIGNED: &str = r#"{"baton":null,"base_url":null,"results":[{"type":"ok","response":{"type":"batch","result":{"step_results":[{"cols":[],"rows":[],"affected_row_count":0,"last_insert_rowid":null}],"step_errors":[null]}}},{"type":"ok","response":{"type":"close"}}]}"#;
+
+        for response in [WITH_WRAPPERS, WITHOUT_WRAPPERS] {
+            let (port, _) = serve_once(response).await;
+            let db = Client::from_config(http_config(port)).await.unwrap();
+            let results = db
+                .batch_in_transaction(["SELECT 1 AS a", "SELECT 2 AS b"])
+                .await
+                .unwrap();
+            assert_eq!(results.len(), 2);
+            assert_eq!(results[0].columns, ["a"]);
+            assert_eq!(results[1].columns, ["b"]);
+        }
+
+        let (port, _) = serve_once(MISALIGNED).await;
+        let db = Client::from_config(http_config(port)).await.unwrap();
+        let err = db
+            .batch_in_transaction(["SELECT 1 AS a", "SELECT 2 AS b"])
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("expected 2, got 1"), "{err}");
+    }
+}
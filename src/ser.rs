@@ -0,0 +1,533 @@
+//! libsql serialization utilities.
+
+use hrana_client_proto::Value;
+use serde::de::value::Error;
+use serde::ser::{
+    Error as _, Impossible, Serialize, SerializeSeq, SerializeStruct, SerializeTuple,
+    SerializeTupleStruct, Serializer,
+};
+
+/// Serialize any type `T` that implements [`serde::Serialize`] into statement arguments,
+/// one per field, in declaration order.
+///
+/// It's the counterpart of [`crate::de::from_row_positional()`]: structs, tuples and tuple structs
+/// are supported, and their fields can be of the following types:
+///
+/// - String, &str, char
+/// - Vec<u8>
+/// - integers fitting into i64, and bool (stored as `0` or `1`)
+/// - f64, f32
+/// - Option<T> (where T is any of the above)
+/// - ()
+/// - unit enum variants, stored as text with the variant name
+///
+/// # Example
+///
+/// ```
+/// # fn run() -> anyhow::Result<()> {
+/// use libsql_client::{ser, Statement};
+///
+/// #[derive(serde::Serialize)]
+/// struct User {
+///     name: String,
+///     age: i64,
+///     email: Option<String>,
+/// }
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// db.execute("CREATE TABLE users(name TEXT, age INTEGER, email TEXT)")?;
+/// let user = User { name: "alice".into(), age: 30, email: None };
+/// db.execute(Statement::with_args(
+///     "INSERT INTO users(name, age, email) VALUES (?, ?, ?)",
+///     &ser::to_params(&user)?,
+/// ))?;
+/// # Ok(())
+/// # }
+/// # run().unwrap();
+/// ```
+pub fn to_params<T: Serialize + ?Sized>(value: &T) -> anyhow::Result<Vec<Value>> {
+    value.serialize(ParamsSerializer).map_err(Into::into)
+}
+
+fn unsupported<T>(what: &str) -> Result<T, Error> {
+    Err(Error::custom(format!(
+        "Cannot serialize {what} into statement arguments"
+    )))
+}
+
+/// Serializes a whole value into a list of arguments, one per field
+struct ParamsSerializer;
+
+/// Collects fields of a struct, tuple or sequence
+struct ParamsCollector(Vec<Value>);
+
+impl ParamsCollector {
+    fn push<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.0.push(value.serialize(ValueSerializer)?);
+        Ok(())
+    }
+}
+
+impl SerializeSeq for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeTuple for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeTupleStruct for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.0)
+    }
+}
+
+impl SerializeStruct for ParamsCollector {
+    type Ok = Vec<Value>;
+    type Error = Error;
+
+    fn serialize_field<T: Serialize + ?Sized>(
+        &mut self,
+        _key: &'static str,
+        value: &T,
+    ) -> Result<(), Error> {
+        self.push(value)
+    }
+
+    fn end(self) -> Result<Vec<Value>, Error> {
+        Ok(self.0)
+    }
+}
+
+impl Serializer for ParamsSerializer {
+    type Ok = Vec<Value>;
+    type Error = Error;
+    type SerializeSeq = ParamsCollector;
+    type SerializeTuple = ParamsCollector;
+    type SerializeTupleStruct = ParamsCollector;
+    type SerializeTupleVariant = Impossible<Vec<Value>, Error>;
+    type SerializeMap = Impossible<Vec<Value>, Error>;
+    type SerializeStruct = ParamsCollector;
+    type SerializeStructVariant = Impossible<Vec<Value>, Error>;
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<ParamsCollector, Error> {
+        Ok(ParamsCollector(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, len: usize) -> Result<ParamsCollector, Error> {
+        Ok(ParamsCollector(Vec::with_capacity(len)))
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        len: usize,
+    ) -> Result<ParamsCollector, Error> {
+        Ok(ParamsCollector(Vec::with_capacity(len)))
+    }
+
+    fn serialize_struct(self, _name: &'static str, len: usize) -> Result<ParamsCollector, Error> {
+        Ok(ParamsCollector(Vec::with_capacity(len)))
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Vec<Value>, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_bool(self, _v: bool) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_i8(self, _v: i8) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_i16(self, _v: i16) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_i32(self, _v: i32) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_i64(self, _v: i64) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_u8(self, _v: u8) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_u16(self, _v: u16) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_u32(self, _v: u32) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_u64(self, _v: u64) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_f32(self, _v: f32) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_f64(self, _v: f64) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_char(self, _v: char) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_str(self, _v: &str) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_bytes(self, _v: &[u8]) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_none(self) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, _value: &T) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_unit(self) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+    ) -> Result<Vec<Value>, Error> {
+        unsupported("a single value")
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Vec<Value>, Error> {
+        unsupported("an enum")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("an enum")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("a map")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("an enum")
+    }
+}
+
+/// Serializes a single field into a value
+struct ValueSerializer;
+
+/// Collects a sequence of bytes, like a `Vec<u8>`, into a blob
+struct BlobCollector(Vec<u8>);
+
+impl SerializeSeq for BlobCollector {
+    type Ok = Value;
+    type Error = Error;
+
+    fn serialize_element<T: Serialize + ?Sized>(&mut self, value: &T) -> Result<(), Error> {
+        match value.serialize(ValueSerializer)? {
+            Value::Integer { value } if (0..=255).contains(&value) => {
+                self.0.push(value as u8);
+                Ok(())
+            }
+            _ => unsupported("a sequence of values other than bytes"),
+        }
+    }
+
+    fn end(self) -> Result<Value, Error> {
+        Ok(Value::Blob { value: self.0 })
+    }
+}
+
+impl Serializer for ValueSerializer {
+    type Ok = Value;
+    type Error = Error;
+    type SerializeSeq = BlobCollector;
+    type SerializeTuple = Impossible<Value, Error>;
+    type SerializeTupleStruct = Impossible<Value, Error>;
+    type SerializeTupleVariant = Impossible<Value, Error>;
+    type SerializeMap = Impossible<Value, Error>;
+    type SerializeStruct = Impossible<Value, Error>;
+    type SerializeStructVariant = Impossible<Value, Error>;
+
+    fn serialize_bool(self, v: bool) -> Result<Value, Error> {
+        Ok(Value::Integer { value: v as i64 })
+    }
+
+    fn serialize_i8(self, v: i8) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i16(self, v: i16) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i32(self, v: i32) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_i64(self, v: i64) -> Result<Value, Error> {
+        Ok(Value::Integer { value: v })
+    }
+
+    fn serialize_u8(self, v: u8) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u16(self, v: u16) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u32(self, v: u32) -> Result<Value, Error> {
+        self.serialize_i64(v.into())
+    }
+
+    fn serialize_u64(self, v: u64) -> Result<Value, Error> {
+        let v = i64::try_from(v)
+            .map_err(|_| Error::custom(format!("Integer {v} does not fit into i64")))?;
+        self.serialize_i64(v)
+    }
+
+    fn serialize_f32(self, v: f32) -> Result<Value, Error> {
+        self.serialize_f64(v.into())
+    }
+
+    fn serialize_f64(self, v: f64) -> Result<Value, Error> {
+        Ok(Value::Float { value: v })
+    }
+
+    fn serialize_char(self, v: char) -> Result<Value, Error> {
+        Ok(Value::Text {
+            value: v.to_string(),
+        })
+    }
+
+    fn serialize_str(self, v: &str) -> Result<Value, Error> {
+        Ok(Value::Text {
+            value: v.to_string(),
+        })
+    }
+
+    fn serialize_bytes(self, v: &[u8]) -> Result<Value, Error> {
+        Ok(Value::Blob { value: v.to_vec() })
+    }
+
+    fn serialize_none(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_some<T: Serialize + ?Sized>(self, value: &T) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_unit(self) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_struct(self, _name: &'static str) -> Result<Value, Error> {
+        Ok(Value::Null)
+    }
+
+    fn serialize_unit_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        variant: &'static str,
+    ) -> Result<Value, Error> {
+        self.serialize_str(variant)
+    }
+
+    fn serialize_newtype_struct<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        value: &T,
+    ) -> Result<Value, Error> {
+        value.serialize(self)
+    }
+
+    fn serialize_newtype_variant<T: Serialize + ?Sized>(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _value: &T,
+    ) -> Result<Value, Error> {
+        unsupported("an enum variant with data")
+    }
+
+    fn serialize_seq(self, len: Option<usize>) -> Result<BlobCollector, Error> {
+        Ok(BlobCollector(Vec::with_capacity(len.unwrap_or_default())))
+    }
+
+    fn serialize_tuple(self, _len: usize) -> Result<Self::SerializeTuple, Error> {
+        unsupported("a nested tuple")
+    }
+
+    fn serialize_tuple_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleStruct, Error> {
+        unsupported("a nested tuple struct")
+    }
+
+    fn serialize_tuple_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeTupleVariant, Error> {
+        unsupported("an enum variant with data")
+    }
+
+    fn serialize_map(self, _len: Option<usize>) -> Result<Self::SerializeMap, Error> {
+        unsupported("a nested map")
+    }
+
+    fn serialize_struct(
+        self,
+        _name: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStruct, Error> {
+        unsupported("a nested struct")
+    }
+
+    fn serialize_struct_variant(
+        self,
+        _name: &'static str,
+        _variant_index: u32,
+        _variant: &'static str,
+        _len: usize,
+    ) -> Result<Self::SerializeStructVariant, Error> {
+        unsupported("an enum variant with data")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(serde::Serialize)]
+    enum Role {
+        Admin,
+    }
+
+    #[derive(serde::Serialize)]
+    struct User {
+        name: String,
+        age: u32,
+        score: f64,
+        active: bool,
+        avatar: Vec<u8>,
+        email: Option<String>,
+        role: Role,
+    }
+
+    #[test]
+    fn struct_to_params() {
+        let user = User {
+            name: "alice".into(),
+            age: 30,
+            score: 4.5,
+            active: true,
+            avatar: vec![0, 255],
+            email: None,
+            role: Role::Admin,
+        };
+        let params = to_params(&user).unwrap();
+        assert_eq!(params.len(), 7);
+        assert!(matches!(&params[0], Value::Text { value } if value == "alice"));
+        assert!(matches!(params[1], Value::Integer { value: 30 }));
+        assert!(matches!(params[2], Value::Float { value } if value == 4.5));
+        assert!(matches!(params[3], Value::Integer { value: 1 }));
+        assert!(matches!(&params[4], Value::Blob { value } if value == &[0, 255]));
+        assert!(matches!(params[5], Value::Null));
+        assert!(matches!(&params[6], Value::Text { value } if value == "Admin"));
+    }
+
+    #[test]
+    fn tuples_to_params() {
+        let params = to_params(&(1, "a", Some(2.5))).unwrap();
+        assert_eq!(params.len(), 3);
+
+        #[derive(serde::Serialize)]
+        struct Pair(i64, String);
+        let params = to_params(&Pair(1, "b".into())).unwrap();
+        assert!(matches!(&params[1], Value::Text { value } if value == "b"));
+    }
+
+    #[test]
+    fn unsupported_values() {
+        assert!(to_params(&1).is_err());
+        assert!(to_params(&(1, (2, 3))).is_err());
+        assert!(to_params(&(u64::MAX,)).is_err());
+        assert!(to_params(&(vec![1, 256],)).is_err());
+        assert!(to_params(&std::collections::HashMap::<String, i64>::new()).is_err());
+    }
+}
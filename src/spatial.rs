@@ -0,0 +1,201 @@
+//! Helpers for geometries stored as WKB blobs and indexed with R*Tree tables.
+//!
+//! Spatial extensions like SpatiaLite store geometries in the Well-Known Binary format
+//! of the OGC Simple Features standard. A WKB geometry starts with a byte order marker,
+//! `0` for big-endian or `1` for little-endian, followed by the geometry type as a 32-bit
+//! integer in that byte order (`1` for a point, `3` for a polygon, etc.) and the coordinates.
+//! This crate doesn't interpret geometries beyond the header - use a geometry library
+//! like `geo` with `wkb` to build and parse them.
+
+use crate::{Statement, Value};
+
+/// A geometry in the Well-Known Binary format, stored in a BLOB column.
+///
+/// It converts to a [Value] as is, and can be read back from one with
+/// [`Row::try_get()`](crate::Row::try_get), which checks the header described in
+/// the [module docs](self).
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use libsql_client::{spatial::Wkb, Statement};
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// db.execute("CREATE TABLE places(name TEXT, geom BLOB)")?;
+///
+/// // POINT(1 2), little-endian
+/// let mut point = vec![1, 1, 0, 0, 0];
+/// point.extend(1f64.to_le_bytes());
+/// point.extend(2f64.to_le_bytes());
+/// db.execute(Statement::with_args("INSERT INTO places VALUES ('home', ?)", &[Wkb(point)]))?;
+///
+/// let rs = db.execute("SELECT geom FROM places")?;
+/// let geom: Wkb = rs.rows[0].try_get(0)?;
+/// assert_eq!(geom.geometry_type(), 1);
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Wkb(pub Vec<u8>);
+
+impl Wkb {
+    /// Size of the header: the byte order marker and the geometry type
+    const HEADER_SIZE: usize = 5;
+
+    /// Checks the header of the geometry and wraps it
+    pub fn new(bytes: Vec<u8>) -> Result<Self, String> {
+        if bytes.len() < Self::HEADER_SIZE {
+            return Err(format!("WKB of {} bytes is too short", bytes.len()));
+        }
+        if bytes[0] > 1 {
+            return Err(format!("Invalid WKB byte order marker {}", bytes[0]));
+        }
+        Ok(Self(bytes))
+    }
+
+    /// Returns true if the geometry is encoded in little-endian byte order
+    pub fn is_little_endian(&self) -> bool {
+        self.0.first() == Some(&1)
+    }
+
+    /// Returns the geometry type code from the header, e.g. `1` for a point or `3`
+    /// for a polygon. Codes of geometries with Z or M coordinates are offset
+    /// by 1000, 2000 or 3000 (ISO) or have high bits set (EWKB).
+    pub fn geometry_type(&self) -> u32 {
+        let bytes = self
+            .0
+            .get(1..Self::HEADER_SIZE)
+            .and_then(|b| <[u8; 4]>::try_from(b).ok())
+            .unwrap_or_default();
+        if self.is_little_endian() {
+            u32::from_le_bytes(bytes)
+        } else {
+            u32::from_be_bytes(bytes)
+        }
+    }
+
+    /// Returns the encoded geometry
+    pub fn as_bytes(&self) -> &[u8] {
+        &self.0
+    }
+
+    /// Consumes the wrapper, returning the encoded geometry
+    pub fn into_inner(self) -> Vec<u8> {
+        self.0
+    }
+}
+
+impl From<Wkb> for Value {
+    fn from(wkb: Wkb) -> Self {
+        Value::Blob { value: wkb.0 }
+    }
+}
+
+impl TryFrom<&Value> for Wkb {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Blob { value } => Self::new(value.clone()),
+            _ => Err(format!("Cannot convert {value} to WKB")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Wkb {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Blob { value } => Self::new(value),
+            _ => Err(format!("Cannot convert {value} to WKB")),
+        }
+    }
+}
+
+impl Statement {
+    /// Builds a statement selecting ids of the entries of an R*Tree table which lie entirely
+    /// within the bounding box from (`min_x`, `min_y`) to (`max_x`, `max_y`).
+    ///
+    /// The table must be a 2-dimensional R*Tree with the columns named like in the SQLite docs:
+    /// `CREATE VIRTUAL TABLE idx USING rtree(id, minX, maxX, minY, maxY)`, and may be qualified
+    /// with a schema name, e.g. `main.idx`. Join the ids with the table holding the geometries
+    /// to get them.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// # fn f() -> anyhow::Result<()> {
+    /// use libsql_client::Statement;
+    ///
+    /// let db = libsql_client::SyncClient::in_memory()?;
+    /// db.execute("CREATE VIRTUAL TABLE places_idx USING rtree(id, minX, maxX, minY, maxY)")?;
+    /// db.execute("INSERT INTO places_idx VALUES (1, 0, 1, 0, 1), (2, 5, 6, 5, 6)")?;
+    /// let rs = db.execute(Statement::within_bbox("places_idx", -1.0, -1.0, 2.0, 2.0))?;
+    /// assert_eq!(rs.rows.len(), 1);
+    /// assert_eq!(rs.rows[0].try_get::<i64>(0)?, 1);
+    /// # Ok(())
+    /// # }
+    /// # f().unwrap();
+    /// ```
+    pub fn within_bbox(rtree: &str, min_x: f64, min_y: f64, max_x: f64, max_y: f64) -> Statement {
+        Statement::with_args(
+            format!(
+                "SELECT id FROM {} WHERE minX >= ? AND maxX <= ? AND minY >= ? AND maxY <= ?",
+                crate::utils::quote_table(rtree)
+            ),
+            &[min_x, max_x, min_y, max_y],
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn header() {
+        let wkb = Wkb::new(vec![0, 0, 0, 0, 3, 0, 0, 0, 0]).unwrap();
+        assert!(!wkb.is_little_endian());
+        assert_eq!(wkb.geometry_type(), 3);
+        let wkb = Wkb::new(vec![1, 0xe9, 0x03, 0, 0]).unwrap();
+        assert!(wkb.is_little_endian());
+        assert_eq!(wkb.geometry_type(), 1001);
+
+        assert!(Wkb::new(vec![1, 1, 0]).is_err());
+        assert!(Wkb::new(vec![2, 1, 0, 0, 0]).is_err());
+    }
+
+    #[test]
+    fn value_conversions() {
+        let value = Value::from(Wkb(vec![1, 1, 0, 0, 0]));
+        assert!(matches!(&value, Value::Blob { value } if value == &[1, 1, 0, 0, 0]));
+        assert_eq!(Wkb::try_from(&value).unwrap().geometry_type(), 1);
+        assert_eq!(Wkb::try_from(value).unwrap().into_inner(), [1, 1, 0, 0, 0]);
+        assert!(Wkb::try_from(Value::Text {
+            value: "POINT(1 2)".into()
+        })
+        .is_err());
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn bbox_query() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE VIRTUAL TABLE idx USING rtree(id, minX, maxX, minY, maxY)")
+            .unwrap();
+        db.execute("INSERT INTO idx VALUES (1, 0, 1, 0, 1), (2, 0, 3, 0, 1), (3, -5, -4, 0, 1)")
+            .unwrap();
+        let rs = db
+            .execute(Statement::within_bbox("idx", 0.0, 0.0, 2.0, 2.0))
+            .unwrap();
+        let ids: Vec<i64> = rs.rows.iter().map(|r| r.try_get(0).unwrap()).collect();
+        assert_eq!(ids, [1]);
+        let rs = db
+            .execute(Statement::within_bbox("idx", -10.0, -10.0, 10.0, 10.0))
+            .unwrap();
+        assert_eq!(rs.rows.len(), 3);
+    }
+}
@@ -16,15 +16,35 @@ impl HttpClient {
         auth: String,
         body: String,
     ) -> Result<pipeline::ServerMsg> {
-        let req = http::Request::builder()
+        let response = self.send_raw(url, auth, body).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Sends the request and returns the raw response body
+    pub async fn send_raw(&self, url: String, auth: String, body: String) -> Result<String> {
+        self.send_raw_with_request_id(url, auth, body, None).await
+    }
+
+    /// Like [`HttpClient::send_raw()`], tagging the request with
+    /// an [`X-Idempotency-Key`](crate::http::REQUEST_ID_HEADER) header if `request_id` is set
+    pub async fn send_raw_with_request_id(
+        &self,
+        url: String,
+        auth: String,
+        body: String,
+        request_id: Option<&str>,
+    ) -> Result<String> {
+        let mut req = http::Request::builder()
             .uri(&url)
             .header("Authorization", &auth)
-            .method("POST")
-            .body(Some(bytes::Bytes::copy_from_slice(body.as_bytes())))?;
+            .method("POST");
+        if let Some(request_id) = request_id {
+            req = req.header(crate::http::REQUEST_ID_HEADER, request_id);
+        }
+        let req = req.body(Some(bytes::Bytes::copy_from_slice(body.as_bytes())))?;
 
         let response: http::Response<String> = spin_sdk::http::send(req).await?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&response.into_body())?;
-        Ok(response)
+        Ok(response.into_body())
     }
 }
 
@@ -7,9 +7,15 @@ use base64::Engine;
 use crate::Value;
 
 /// SQL statement, possibly with bound parameters
+#[derive(Clone, Debug)]
 pub struct Statement {
     pub(crate) sql: String,
     pub(crate) args: Vec<Value>,
+    pub(crate) named_args: Vec<(String, Value)>,
+    // Set for statements bound from a [PreparedStatement], which the local backend
+    // keeps prepared between executions
+    #[cfg(feature = "local_backend")]
+    pub(crate) cached: bool,
 }
 
 impl Statement {
@@ -24,11 +30,17 @@ impl Statement {
         Self {
             sql: q.into(),
             args: vec![],
+            named_args: vec![],
+            #[cfg(feature = "local_backend")]
+            cached: false,
         }
     }
 
     /// Creates a statement with bound parameters
     ///
+    /// Parameters are anything convertible into a [Value]. Integers which may not fit into
+    /// `i64`, like `u64`, need to be converted with [`crate::proto::try_integer()`] first.
+    ///
     /// # Examples
     ///
     /// ```
@@ -38,7 +50,301 @@ impl Statement {
         Self {
             sql: q.into(),
             args: params.iter().map(|p| p.clone().into()).collect(),
+            named_args: vec![],
+            #[cfg(feature = "local_backend")]
+            cached: false,
+        }
+    }
+
+    /// Creates a statement with parameters bound by name.
+    /// Names include the prefix used in the SQL text, e.g. `:id`, `@id` or `$id`.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::with_named_args(
+    ///     "UPDATE t SET x = :x WHERE key = :key",
+    ///     &[(":x", 3), (":key", 8)],
+    /// );
+    /// ```
+    pub fn with_named_args(
+        q: impl Into<String>,
+        params: &[(impl Into<String> + Clone, impl Into<Value> + Clone)],
+    ) -> Statement {
+        Self {
+            sql: q.into(),
+            args: vec![],
+            named_args: params
+                .iter()
+                .map(|(name, p)| (name.clone().into(), p.clone().into()))
+                .collect(),
+            #[cfg(feature = "local_backend")]
+            cached: false,
+        }
+    }
+
+    /// Creates a statement with both positional and named parameters.
+    ///
+    /// Positional parameters bind the first placeholders in order, named ones bind placeholders
+    /// by name, including the prefix. On a local database, a mix which doesn't line up with
+    /// the placeholders fails with [`crate::Error::Misuse`]; remote databases check it on
+    /// the server.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// let stmt = libsql_client::Statement::with_mixed_args(
+    ///     "UPDATE t SET x = ? WHERE key = :key",
+    ///     &[3],
+    ///     &[(":key", 8)],
+    /// );
+    /// ```
+    pub fn with_mixed_args(
+        q: impl Into<String>,
+        params: &[impl Into<Value> + Clone],
+        named_params: &[(impl Into<String> + Clone, impl Into<Value> + Clone)],
+    ) -> Statement {
+        Self {
+            named_args: named_params
+                .iter()
+                .map(|(name, p)| (name.clone().into(), p.clone().into()))
+                .collect(),
+            ..Self::with_args(q, params)
+        }
+    }
+
+    /// Returns the number of parameters the SQL expects, or `None` if it can't be told,
+    /// e.g. because of an unterminated string literal.
+    ///
+    /// Like SQLite, it counts up to the highest parameter index: each `?` takes the next index,
+    /// `?NNN` takes index `NNN`, and every distinct `:name`, `@name` or `$name` takes the next
+    /// index once. Placeholders inside literals, quoted identifiers and comments are ignored.
+    /// The SQL is only scanned, not parsed, so no database is needed - comparing the result
+    /// with the bound parameters catches a missing or extra one before the statement is sent.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_client::Statement;
+    ///
+    /// assert_eq!(Statement::new("SELECT 1").expected_param_count(), Some(0));
+    /// assert_eq!(
+    ///     Statement::new("SELECT * FROM users WHERE id = ? AND name != '?'").expected_param_count(),
+    ///     Some(1)
+    /// );
+    /// assert_eq!(
+    ///     Statement::new("UPDATE users SET name = :name WHERE id = ?3").expected_param_count(),
+    ///     Some(3)
+    /// );
+    /// assert_eq!(Statement::new("SELECT 'oops").expected_param_count(), None);
+    /// ```
+    pub fn expected_param_count(&self) -> Option<usize> {
+        let sql = self.sql.as_bytes();
+        let is_name_char = |c: u8| c.is_ascii_alphanumeric() || c == b'_' || c >= 0x80;
+        let mut names = std::collections::HashSet::new();
+        let mut count = 0;
+        let mut pos = 0;
+        while pos < sql.len() {
+            let c = sql[pos];
+            pos += 1;
+            match c {
+                b'\'' | b'"' | b'`' | b'[' => {
+                    let end = if c == b'[' { b']' } else { c };
+                    loop {
+                        pos += sql.get(pos..)?.iter().position(|&b| b == end)? + 1;
+                        // Doubled quotes are escaped, brackets can't be escaped
+                        if c == b'[' || sql.get(pos) != Some(&end) {
+                            break;
+                        }
+                        pos += 1;
+                    }
+                }
+                b'-' if sql.get(pos) == Some(&b'-') => {
+                    pos += sql[pos..]
+                        .iter()
+                        .position(|&b| b == b'\n')
+                        .unwrap_or(sql.len() - pos);
+                }
+                b'/' if sql.get(pos) == Some(&b'*') => {
+                    pos += sql[pos + 1..].windows(2).position(|w| w == b"*/")? + 3;
+                }
+                b'?' => {
+                    let digits = sql[pos..].iter().take_while(|b| b.is_ascii_digit()).count();
+                    count = if digits == 0 {
+                        count + 1
+                    } else {
+                        let index: usize = self.sql[pos..pos + digits].parse().ok()?;
+                        count.max(index)
+                    };
+                    pos += digits;
+                }
+                b':' | b'@' | b'$' => {
+                    let len = sql[pos..].iter().take_while(|&&b| is_name_char(b)).count();
+                    if len > 0 && names.insert(&sql[pos - 1..pos + len]) {
+                        count += 1;
+                    }
+                    pos += len;
+                }
+                _ => (),
+            }
         }
+        Some(count)
+    }
+
+    /// Returns true if the statement only reads data, so it can be routed to a replica.
+    ///
+    /// `SELECT` (including the ones with a `WITH` clause), `EXPLAIN` and pragmas which only
+    /// query a setting or the schema are read-only. `INSERT`, `UPDATE`, `DELETE`, schema changes,
+    /// transaction control and pragmas which assign a value are writes. The check is static,
+    /// so a `SELECT` calling a function with side effects still counts as a read.
+    /// If the SQL can't be parsed, or contains several statements of which any is a write,
+    /// the statement is conservatively considered a write.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use libsql_client::Statement;
+    ///
+    /// assert!(Statement::new("SELECT * FROM users").is_read_only());
+    /// assert!(Statement::new("PRAGMA table_info(users)").is_read_only());
+    /// assert!(!Statement::new("UPDATE users SET name = 'Ann'").is_read_only());
+    /// assert!(!Statement::new("SELEKT * FROM users").is_read_only());
+    /// ```
+    #[cfg(feature = "sql_parsing")]
+    pub fn is_read_only(&self) -> bool {
+        use fallible_iterator::FallibleIterator;
+        use sqlite3_parser::ast::{Cmd, PragmaBody, Stmt};
+
+        // Pragmas with no argument which do more than report a value
+        const SIDE_EFFECT_PRAGMAS: &[&str] = &[
+            "incremental_vacuum",
+            "optimize",
+            "shrink_memory",
+            "wal_checkpoint",
+        ];
+        // Pragmas taking an argument in parentheses which only query the schema
+        const QUERY_PRAGMAS: &[&str] = &[
+            "foreign_key_check",
+            "foreign_key_list",
+            "index_info",
+            "index_list",
+            "index_xinfo",
+            "integrity_check",
+            "quick_check",
+            "table_info",
+            "table_xinfo",
+        ];
+
+        let mut parser = sqlite3_parser::lexer::sql::Parser::new(self.sql.as_bytes());
+        let mut any = false;
+        loop {
+            let cmd = match parser.next() {
+                Ok(Some(cmd)) => cmd,
+                Ok(None) => return any,
+                Err(_) => return false,
+            };
+            let read_only = match cmd {
+                Cmd::Explain(_) | Cmd::ExplainQueryPlan(_) => true,
+                Cmd::Stmt(Stmt::Select(_)) => true,
+                Cmd::Stmt(Stmt::Pragma(name, body)) => {
+                    let name = name
+                        .name
+                        .0
+                        .trim_matches(|c| matches!(c, '"' | '\'' | '`' | '[' | ']'));
+                    let is =
+                        |pragmas: &[&str]| pragmas.iter().any(|p| p.eq_ignore_ascii_case(name));
+                    match body {
+                        None => !is(SIDE_EFFECT_PRAGMAS),
+                        Some(PragmaBody::Call(_)) => is(QUERY_PRAGMAS),
+                        Some(PragmaBody::Equals(_)) => false,
+                    }
+                }
+                Cmd::Stmt(_) => false,
+            };
+            if !read_only {
+                return false;
+            }
+            any = true;
+        }
+    }
+}
+
+impl Statement {
+    // Classifies the statement for ResultSet::is_write(). Without the SQL parser,
+    // only statements starting with SELECT count as reads.
+    pub(crate) fn is_write(&self) -> bool {
+        #[cfg(feature = "sql_parsing")]
+        return !self.is_read_only();
+        #[cfg(not(feature = "sql_parsing"))]
+        return !self
+            .sql
+            .trim_start()
+            .get(..6)
+            .is_some_and(|prefix| prefix.eq_ignore_ascii_case("select"));
+    }
+}
+
+/// SQL of a statement executed repeatedly with different parameters.
+///
+/// Each call to [`PreparedStatement::bind()`] creates a [Statement] with the given parameters.
+/// The local backend keeps statements bound from the same SQL prepared between executions,
+/// so SQLite doesn't parse and plan them again; remote backends send them like any other statement.
+///
+/// # Examples
+///
+/// ```
+/// # fn f() -> anyhow::Result<()> {
+/// use libsql_client::PreparedStatement;
+///
+/// let db = libsql_client::SyncClient::in_memory()?;
+/// db.execute("CREATE TABLE users(id INTEGER, name TEXT)")?;
+/// let insert = PreparedStatement::new("INSERT INTO users VALUES (?, ?)");
+/// db.execute(insert.bind(libsql_client::args!(1, "Ann")))?;
+/// db.execute(insert.bind(libsql_client::args!(2, "Bob")))?;
+/// # Ok(())
+/// # }
+/// # f().unwrap();
+/// ```
+#[derive(Clone, Debug)]
+pub struct PreparedStatement {
+    sql: String,
+}
+
+impl PreparedStatement {
+    /// Creates a prepared statement from its SQL
+    pub fn new(sql: impl Into<String>) -> Self {
+        Self { sql: sql.into() }
+    }
+
+    /// Returns the SQL of the statement
+    pub fn sql(&self) -> &str {
+        &self.sql
+    }
+
+    /// Creates a statement with positional parameters, see [`Statement::with_args()`]
+    pub fn bind(&self, params: &[impl Into<Value> + Clone]) -> Statement {
+        Statement::with_args(self.sql.clone(), params).into_cached()
+    }
+
+    /// Creates a statement with parameters bound by name, see [`Statement::with_named_args()`]
+    pub fn bind_named(
+        &self,
+        params: &[(impl Into<String> + Clone, impl Into<Value> + Clone)],
+    ) -> Statement {
+        Statement::with_named_args(self.sql.clone(), params).into_cached()
+    }
+}
+
+impl Statement {
+    // Marks the statement for the local backend to keep prepared
+    fn into_cached(self) -> Statement {
+        #[cfg(feature = "local_backend")]
+        return Statement {
+            cached: true,
+            ..self
+        };
+        #[cfg(not(feature = "local_backend"))]
+        self
     }
 }
 
@@ -47,6 +353,9 @@ impl From<String> for Statement {
         Statement {
             sql: q,
             args: vec![],
+            named_args: vec![],
+            #[cfg(feature = "local_backend")]
+            cached: false,
         }
     }
 }
@@ -65,22 +374,162 @@ impl From<&&str> for Statement {
 
 impl std::fmt::Display for Statement {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let params: Vec<String> = self
-            .args
-            .iter()
-            .map(|p| match p {
-                Value::Blob { value } => serde_json::json!({
-                    "base64": BASE64_STANDARD_NO_PAD.encode(value),
-                })
-                .to_string(),
-                _ => serde_json::json!(p)["value"].to_string(),
+        let fmt_value = |p: &Value| match p {
+            Value::Blob { value } => serde_json::json!({
+                "base64": BASE64_STANDARD_NO_PAD.encode(value),
             })
-            .collect();
+            .to_string(),
+            _ => serde_json::json!(p)["value"].to_string(),
+        };
+        let params: Vec<String> = self.args.iter().map(fmt_value).collect();
         write!(
             f,
-            "{{\"sql\": {}, \"args\": [{}]}}",
+            "{{\"sql\": {}, \"args\": [{}]",
             serde_json::json!(self.sql),
             params.join(",")
-        )
+        )?;
+        if !self.named_args.is_empty() {
+            let named_params: Vec<String> = self
+                .named_args
+                .iter()
+                .map(|(name, p)| format!("{}: {}", serde_json::json!(name), fmt_value(p)))
+                .collect();
+            write!(f, ", \"named_args\": {{{}}}", named_params.join(","))?;
+        }
+        write!(f, "}}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn param_count() {
+        let count = |sql: &str| Statement::new(sql).expected_param_count();
+        assert_eq!(count("SELECT 1"), Some(0));
+        assert_eq!(
+            count("SELECT * FROM t WHERE a = '?' AND \"?\" = 1"),
+            Some(0)
+        );
+        assert_eq!(count("SELECT [:a], `@b` -- $c ?\n /* ? */"), Some(0));
+        assert_eq!(count("SELECT ?"), Some(1));
+        assert_eq!(count("SELECT 'it''s ?', ?"), Some(1));
+        assert_eq!(count("INSERT INTO t VALUES (?, ?, ?)"), Some(3));
+        assert_eq!(count("SELECT ?2, ?"), Some(3));
+        assert_eq!(count("SELECT ?5, ?1"), Some(5));
+        assert_eq!(count("SELECT :a, @a, :a, $b"), Some(3));
+        assert_eq!(count("SELECT ?, :x, ?"), Some(3));
+        assert_eq!(count("SELECT 'unterminated"), None);
+        assert_eq!(count("SELECT 1 /* unterminated"), None);
+    }
+
+    #[cfg(feature = "sql_parsing")]
+    #[test]
+    fn read_only_statements() {
+        let reads = [
+            "SELECT 1",
+            "select * from users where id = ?",
+            "WITH t AS (SELECT 1) SELECT * FROM t",
+            "SELECT 1 UNION ALL SELECT 2",
+            "VALUES (1), (2)",
+            "EXPLAIN DELETE FROM users",
+            "EXPLAIN QUERY PLAN SELECT * FROM users",
+            "PRAGMA user_version",
+            "PRAGMA main.journal_mode",
+            "PRAGMA table_info(users)",
+            "PRAGMA TABLE_INFO('users')",
+            "SELECT 1; SELECT 2;",
+        ];
+        for sql in reads {
+            assert!(Statement::new(sql).is_read_only(), "{sql}");
+        }
+
+        let writes = [
+            "INSERT INTO users VALUES (1)",
+            "INSERT INTO users SELECT * FROM old_users",
+            "UPDATE users SET name = 'Ann'",
+            "DELETE FROM users",
+            "REPLACE INTO users VALUES (1)",
+            "WITH t AS (SELECT 1) INSERT INTO users SELECT * FROM t",
+            "CREATE TABLE users(id)",
+            "CREATE INDEX users_name ON users(name)",
+            "DROP TABLE users",
+            "ALTER TABLE users ADD COLUMN age",
+            "BEGIN",
+            "COMMIT",
+            "VACUUM",
+            "ATTACH 'other.db' AS other",
+            "PRAGMA user_version = 5",
+            "PRAGMA user_version(5)",
+            "PRAGMA optimize",
+            "PRAGMA wal_checkpoint",
+            "SELECT 1; DELETE FROM users",
+            "",
+            "-- nothing",
+            "SELEKT 1",
+        ];
+        for sql in writes {
+            assert!(!Statement::new(sql).is_read_only(), "{sql}");
+        }
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn mixed_args() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        db.execute(Statement::with_mixed_args(
+            "INSERT INTO t VALUES (?, :name)",
+            &[7],
+            &[(":name", "Ann")],
+        ))
+        .unwrap();
+        let rs = db
+            .execute(Statement::with_mixed_args(
+                "SELECT name FROM t WHERE id = ?1 AND name <> @other",
+                &[7],
+                &[("@other", "Bob")],
+            ))
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<&str>(0).unwrap(), "Ann");
+
+        let misuse = |stmt| {
+            let err = db.execute(stmt).unwrap_err();
+            assert!(err.to_string().starts_with("Misuse"), "{err}");
+        };
+        misuse(Statement::with_mixed_args(
+            "SELECT :a, ?",
+            &[1],
+            &[(":a", 2)],
+        ));
+        misuse(Statement::with_mixed_args(
+            "SELECT ?, :a",
+            &[1],
+            &[(":b", 2)],
+        ));
+        misuse(Statement::with_mixed_args(
+            "SELECT ?, :a",
+            &[1, 2, 3],
+            &[(":a", 2)],
+        ));
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn cloned_statement_runs_twice() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, name TEXT)")
+            .unwrap();
+        let stmt = Statement::with_args("INSERT INTO t(name) VALUES (?)", &["John"]);
+        let copy = stmt.clone();
+        db.batch([stmt.clone(), copy]).unwrap();
+        db.execute(stmt).unwrap();
+
+        let rs = db
+            .execute("SELECT count(*) FROM t WHERE name = 'John'")
+            .unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 3);
     }
 }
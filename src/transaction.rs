@@ -2,18 +2,108 @@
 
 use crate::{Client, ResultSet, Statement, SyncClient};
 use anyhow::Result;
+use std::marker::PhantomData;
+use std::ops::Deref;
+use std::sync::{Arc, Mutex};
 
-pub struct Transaction<'a> {
+/// Aggregate effects of the statements executed in a transaction,
+/// returned by [`Transaction::commit_with_summary()`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TxSummary {
+    /// Sum of rows changed by the statements
+    pub total_affected: u64,
+    /// The rowid of the last insertion reported by any of the statements
+    pub last_insert_rowid: Option<i64>,
+}
+
+impl TxSummary {
+    fn record(summary: &Mutex<TxSummary>, rs: &ResultSet) {
+        let mut summary = summary.lock().unwrap();
+        summary.total_affected += rs.rows_affected;
+        if rs.last_insert_rowid.is_some() {
+            summary.last_insert_rowid = rs.last_insert_rowid;
+        }
+    }
+}
+
+/// Locking behavior of a transaction, which decides when it acquires the database locks.
+///
+/// See [SQLite docs](https://www.sqlite.org/lang_transaction.html) for details.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TxBehavior {
+    /// Acquires locks only when the transaction first reads and writes, with a plain `BEGIN`.
+    /// A transaction which reads first and writes later may fail with `SQLITE_BUSY`
+    /// if another one wrote in the meantime.
+    #[default]
+    Deferred,
+    /// Acquires the write lock right away with `BEGIN IMMEDIATE`, so other writers
+    /// wait at `BEGIN` instead of failing mid-way. The usual choice for transactions which write.
+    Immediate,
+    /// Starts with `BEGIN EXCLUSIVE`, which also keeps other connections from reading,
+    /// unless the database is in WAL mode, where it's the same as [`TxBehavior::Immediate`].
+    Exclusive,
+}
+
+impl TxBehavior {
+    fn begin(self) -> Statement {
+        Statement::from(match self {
+            TxBehavior::Deferred => "BEGIN",
+            TxBehavior::Immediate => "BEGIN IMMEDIATE",
+            TxBehavior::Exclusive => "BEGIN EXCLUSIVE",
+        })
+    }
+}
+
+/// An interactive transaction.
+///
+/// It runs on a handle to its client, `C`, which is either borrowed as with
+/// [`Client::transaction()`], or shared, as in an [OwnedTransaction].
+///
+/// If dropped without calling [`Transaction::commit()`] or [`Transaction::rollback()`],
+/// the transaction is rolled back on a best-effort basis - see its [Drop] implementation for details.
+pub struct Transaction<'a, C: Deref<Target = Client> = &'a Client> {
     pub(crate) id: u64,
-    pub(crate) client: &'a Client,
+    pub(crate) client: C,
+    finished: bool,
+    summary: Mutex<TxSummary>,
+    _client: PhantomData<&'a ()>,
 }
 
-impl<'a> Transaction<'a> {
-    pub async fn new(client: &'a Client, id: u64) -> Result<Transaction<'a>> {
-        client
-            .execute_in_transaction(id, Statement::from("BEGIN"))
-            .await?;
-        Ok(Self { id, client })
+/// An interactive transaction which owns a shared handle to its client instead of borrowing it,
+/// created with [`Client::transaction_owned()`].
+///
+/// It's `'static`, so it can outlive the function which began it, e.g. be stored in
+/// request-scoped state or moved into a spawned task. Whether it can be sent to other threads,
+/// e.g. with `tokio::spawn`, depends on the enabled backends: with only `hrana_backend` and
+/// `reqwest_backend`, [Client] is `Sync` and its futures are `Send`, so the transaction is `Send`
+/// too. With `local_backend`, [Client] is not `Sync`, and the wasm backends produce non-`Send`
+/// futures, so the transaction only moves between tasks on the same thread, e.g. with
+/// `tokio::task::spawn_local`.
+pub type OwnedTransaction = Transaction<'static, Arc<Client>>;
+
+impl<'a, C: Deref<Target = Client>> Transaction<'a, C> {
+    pub async fn new(client: C, id: u64) -> Result<Transaction<'a, C>> {
+        Self::begin(client, id, TxBehavior::Deferred).await
+    }
+
+    pub(crate) async fn begin(
+        client: C,
+        id: u64,
+        behavior: TxBehavior,
+    ) -> Result<Transaction<'a, C>> {
+        client.acquire_transaction_slot()?;
+        if let Err(e) = client.execute_in_transaction(id, behavior.begin()).await {
+            client.drop_transaction(id);
+            client.release_transaction_slot();
+            return Err(e);
+        }
+        Ok(Self {
+            id,
+            client,
+            finished: false,
+            summary: Mutex::default(),
+            _client: PhantomData,
+        })
     }
 
     /// Executes a statement within the current transaction.
@@ -35,31 +125,123 @@ impl<'a> Transaction<'a> {
     ///   # }
     /// ```
     pub async fn execute(&self, stmt: impl Into<Statement>) -> Result<ResultSet> {
-        self.client
+        let rs = self
+            .client
             .execute_in_transaction(self.id, stmt.into())
-            .await
+            .await?;
+        TxSummary::record(&self.summary, &rs);
+        Ok(rs)
+    }
+
+    /// Returns the client-side id of the transaction, unique within the process
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the current baton of the server-side stream running the transaction,
+    /// e.g. to correlate it with sqld logs.
+    ///
+    /// Only the HTTP backend uses batons, other backends return `None`. The baton changes
+    /// after every request in the transaction, so this is a snapshot meant for diagnostics.
+    pub fn baton(&self) -> Option<String> {
+        self.client.baton(self.id)
+    }
+
+    /// Returns the handle to the client running the transaction
+    pub fn client(&self) -> &C {
+        &self.client
+    }
+
+    /// Returns the effects of the statements executed so far in the transaction
+    pub fn summary(&self) -> TxSummary {
+        *self.summary.lock().unwrap()
+    }
+
+    /// Commits the transaction to the database and returns the effects of its statements.
+    ///
+    /// # Example
+    ///
+    /// ```rust,no_run
+    ///   # async fn f() -> anyhow::Result<()> {
+    ///   let db = libsql_client::Client::from_env().await?;
+    ///   let tx = db.transaction().await?;
+    ///   tx.execute("INSERT INTO users (name) VALUES ('John')").await?;
+    ///   tx.execute("UPDATE users SET verified = 1 WHERE name = 'John'").await?;
+    ///   let summary = tx.commit_with_summary().await?;
+    ///   tracing::info!("changed {} rows", summary.total_affected);
+    ///   # Ok(())
+    ///   # }
+    /// ```
+    pub async fn commit_with_summary(self) -> Result<TxSummary> {
+        let summary = self.summary();
+        self.commit().await?;
+        Ok(summary)
     }
 
     /// Commits the transaction to the database.
-    pub async fn commit(self) -> Result<()> {
-        self.client.commit_transaction(self.id).await
+    pub async fn commit(mut self) -> Result<()> {
+        self.client.commit_transaction(self.id).await?;
+        self.finished = true;
+        Ok(())
     }
 
     /// Rolls back the transaction, cancelling any of its side-effects.
-    pub async fn rollback(self) -> Result<()> {
-        self.client.rollback_transaction(self.id).await
+    pub async fn rollback(mut self) -> Result<()> {
+        self.client.rollback_transaction(self.id).await?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl<C: Deref<Target = Client>> Drop for Transaction<'_, C> {
+    /// Rolls back the transaction if it was neither committed nor rolled back.
+    ///
+    /// The local backend rolls back synchronously, and the hrana backend closes
+    /// the transaction's stream, which makes the server roll it back.
+    /// The HTTP backend cannot issue a request from a synchronous context, so it only
+    /// forgets the stream and logs a warning - the server rolls the transaction back
+    /// once the stream times out.
+    fn drop(&mut self) {
+        if !self.finished {
+            self.client.drop_transaction(self.id);
+        }
+        self.client.release_transaction_slot();
     }
 }
 
+/// A synchronous flavor of [Transaction].
+///
+/// If dropped without calling [`SyncTransaction::commit()`] or [`SyncTransaction::rollback()`],
+/// the transaction is rolled back on a best-effort basis, just like [Transaction].
 pub struct SyncTransaction<'a> {
     pub(crate) id: u64,
     pub(crate) client: &'a SyncClient,
+    finished: bool,
+    summary: Mutex<TxSummary>,
 }
 
 impl<'a> SyncTransaction<'a> {
     pub fn new(client: &'a SyncClient, id: u64) -> Result<SyncTransaction<'a>> {
-        client.execute_in_transaction(id, Statement::from("BEGIN"))?;
-        Ok(Self { id, client })
+        Self::begin(client, id, TxBehavior::Deferred)
+    }
+
+    pub(crate) fn begin(
+        client: &'a SyncClient,
+        id: u64,
+        behavior: TxBehavior,
+    ) -> Result<SyncTransaction<'a>> {
+        client.acquire_transaction_slot()?;
+        if let Err(e) = client.execute_in_transaction(id, behavior.begin()) {
+            client.drop_transaction(id);
+            client.release_transaction_slot();
+            return Err(e);
+        }
+        Ok(Self {
+            id,
+            client,
+            finished: false,
+            summary: Mutex::default(),
+        })
     }
 
     /// Executes a statement within the current transaction.
@@ -81,16 +263,322 @@ impl<'a> SyncTransaction<'a> {
     ///   # }
     /// ```
     pub fn execute(&self, stmt: impl Into<Statement>) -> Result<ResultSet> {
-        self.client.execute_in_transaction(self.id, stmt.into())
+        let rs = self.client.execute_in_transaction(self.id, stmt.into())?;
+        TxSummary::record(&self.summary, &rs);
+        Ok(rs)
+    }
+
+    /// Returns the client-side id of the transaction, unique within the process
+    pub fn id(&self) -> u64 {
+        self.id
+    }
+
+    /// Returns the current baton of the server-side stream running the transaction.
+    /// See [`Transaction::baton()`] for details.
+    pub fn baton(&self) -> Option<String> {
+        self.client.baton(self.id)
+    }
+
+    /// Returns the effects of the statements executed so far in the transaction
+    pub fn summary(&self) -> TxSummary {
+        *self.summary.lock().unwrap()
+    }
+
+    /// Commits the transaction to the database and returns the effects of its statements.
+    pub fn commit_with_summary(self) -> Result<TxSummary> {
+        let summary = self.summary();
+        self.commit()?;
+        Ok(summary)
     }
 
     /// Commits the transaction to the database.
-    pub fn commit(self) -> Result<()> {
-        self.client.commit_transaction(self.id)
+    pub fn commit(mut self) -> Result<()> {
+        self.client.commit_transaction(self.id)?;
+        self.finished = true;
+        Ok(())
     }
 
     /// Rolls back the transaction, cancelling any of its side-effects.
-    pub fn rollback(self) -> Result<()> {
-        self.client.rollback_transaction(self.id)
+    pub fn rollback(mut self) -> Result<()> {
+        self.client.rollback_transaction(self.id)?;
+        self.finished = true;
+        Ok(())
+    }
+}
+
+impl Drop for SyncTransaction<'_> {
+    fn drop(&mut self) {
+        if !self.finished {
+            self.client.drop_transaction(self.id);
+        }
+        self.client.release_transaction_slot();
+    }
+}
+
+#[cfg(all(test, feature = "local_backend"))]
+mod tests {
+    use crate::{Client, SyncClient, TxBehavior};
+
+    #[tokio::test]
+    async fn dropped_transaction_is_rolled_back() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        {
+            let tx = db.transaction().await.unwrap();
+            tx.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        }
+        let tx = db.transaction().await.unwrap();
+        let rs = tx.execute("SELECT * FROM t").await.unwrap();
+        assert!(rs.rows.is_empty());
+        tx.commit().await.unwrap();
     }
+
+    #[test]
+    fn dropped_sync_transaction_is_rolled_back() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        {
+            let tx = db.transaction().unwrap();
+            tx.execute("INSERT INTO t VALUES (1)").unwrap();
+        }
+        let tx = db.transaction().unwrap();
+        let rs = tx.execute("SELECT * FROM t").unwrap();
+        assert!(rs.rows.is_empty());
+        tx.commit().unwrap();
+    }
+
+    #[tokio::test]
+    async fn max_open_transactions() {
+        let config = crate::Config::new("file:////tmp/max_open_transactions.db")
+            .unwrap()
+            .with_max_open_transactions(1);
+        let db = Client::from_config(config).await.unwrap();
+        let tx = db.transaction().await.unwrap();
+        let err = db.transaction().await.err().unwrap();
+        assert_eq!(
+            err.downcast_ref::<crate::Error>(),
+            Some(&crate::Error::Misuse(
+                "too many open transactions".to_string()
+            ))
+        );
+        tx.commit().await.unwrap();
+        let tx = db.transaction().await.unwrap();
+        drop(tx);
+        db.transaction().await.unwrap().rollback().await.unwrap();
+    }
+
+    #[test]
+    fn with_transaction_retries_busy() {
+        let url = "file:////tmp/with_transaction_retries_busy.db";
+        let config = crate::Config::new(url)
+            .unwrap()
+            .with_transaction_retries(10, std::time::Duration::from_millis(10));
+        let db = SyncClient::from_config(config).unwrap();
+        db.execute("CREATE TABLE IF NOT EXISTS t(x)").unwrap();
+        db.execute("DELETE FROM t").unwrap();
+
+        // Another connection holds the write lock for a while
+        let (locked_tx, locked_rx) = std::sync::mpsc::channel();
+        let holder = std::thread::spawn(move || {
+            let other = SyncClient::from_config(crate::Config::new(url).unwrap()).unwrap();
+            other.execute("BEGIN IMMEDIATE").unwrap();
+            locked_tx.send(()).unwrap();
+            std::thread::sleep(std::time::Duration::from_millis(100));
+            other.execute("COMMIT").unwrap();
+        });
+        locked_rx.recv().unwrap();
+
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+        db.with_transaction(|tx| {
+            attempts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+            tx.execute("INSERT INTO t VALUES (1)")
+        })
+        .unwrap();
+        holder.join().unwrap();
+        assert!(attempts.into_inner() > 1);
+        assert_eq!(db.execute("SELECT * FROM t").unwrap().rows.len(), 1);
+    }
+
+    #[test]
+    fn transaction_behaviors() {
+        let url = "file:////tmp/transaction_behaviors.db";
+        let db = SyncClient::from_config(crate::Config::new(url).unwrap()).unwrap();
+        db.execute("PRAGMA journal_mode = DELETE").unwrap();
+        db.execute("CREATE TABLE IF NOT EXISTS t(x)").unwrap();
+        let other = SyncClient::from_config(crate::Config::new(url).unwrap()).unwrap();
+
+        // A deferred transaction takes no locks until it touches the database
+        let tx = db.transaction_with(TxBehavior::Deferred).unwrap();
+        other.execute("BEGIN IMMEDIATE").unwrap();
+        other.execute("COMMIT").unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.transaction_with(TxBehavior::Immediate).unwrap();
+        assert!(crate::client::is_busy(
+            &other.execute("BEGIN IMMEDIATE").unwrap_err()
+        ));
+        other.execute("SELECT * FROM t").unwrap();
+        tx.commit().unwrap();
+
+        let tx = db.transaction_with(TxBehavior::Exclusive).unwrap();
+        assert!(crate::client::is_busy(
+            &other.execute("SELECT * FROM t").unwrap_err()
+        ));
+        tx.rollback().unwrap();
+        other.execute("SELECT * FROM t").unwrap();
+    }
+
+    #[tokio::test]
+    async fn with_transaction_propagates_other_errors() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x UNIQUE)").await.unwrap();
+        db.execute("INSERT INTO t VALUES (1)").await.unwrap();
+        let attempts = std::sync::atomic::AtomicUsize::new(0);
+        let err = db
+            .with_transaction(async |tx| {
+                attempts.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                tx.execute("INSERT INTO t VALUES (2)").await?;
+                tx.execute("INSERT INTO t VALUES (1)").await
+            })
+            .await
+            .unwrap_err();
+        assert_eq!(attempts.into_inner(), 1);
+        assert_eq!(
+            err.downcast_ref::<crate::Error>().unwrap().code(),
+            Some("SQLITE_CONSTRAINT_UNIQUE")
+        );
+        let rs = db.execute("SELECT * FROM t").await.unwrap();
+        assert_eq!(rs.rows.len(), 1);
+    }
+
+    #[tokio::test]
+    async fn transaction_scope_borrows() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+        let mut inserted = vec![];
+        db.transaction_scope(async |tx| {
+            for x in 1..=3 {
+                tx.execute(crate::Statement::with_args(
+                    "INSERT INTO t VALUES (?)",
+                    &[x],
+                ))
+                .await?;
+                inserted.push(x);
+            }
+            Ok(())
+        })
+        .await
+        .unwrap();
+        assert_eq!(inserted, [1, 2, 3]);
+
+        let err = db
+            .transaction_scope(async |tx| {
+                tx.execute("DELETE FROM t").await?;
+                tx.execute("SELECT * FROM missing").await
+            })
+            .await;
+        assert!(err.is_err());
+        let rs = db.execute("SELECT * FROM t").await.unwrap();
+        assert_eq!(rs.rows.len(), 3);
+    }
+
+    #[test]
+    fn sync_transaction_scope() {
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        let count: i64 = db
+            .transaction_scope(|tx| {
+                tx.execute("INSERT INTO t VALUES (1)")?;
+                tx.execute("INSERT INTO t VALUES (2)")?;
+                tx.execute("SELECT count(*) FROM t")?.rows[0].try_get(0)
+            })
+            .unwrap();
+        assert_eq!(count, 2);
+        assert!(db
+            .transaction_scope(|tx| tx.execute("INSERT INTO missing VALUES (1)"))
+            .is_err());
+        assert_eq!(db.execute("SELECT * FROM t").unwrap().rows.len(), 2);
+    }
+
+    #[tokio::test]
+    #[allow(clippy::arc_with_non_send_sync)]
+    async fn owned_transaction_outlives_its_scope() {
+        let db = std::sync::Arc::new(Client::in_memory().unwrap());
+        db.execute("CREATE TABLE t(x)").await.unwrap();
+
+        fn begin(
+            db: &std::sync::Arc<Client>,
+        ) -> impl std::future::Future<Output = crate::transaction::OwnedTransaction> + 'static
+        {
+            let db = db.clone();
+            async move { db.transaction_owned().await.unwrap() }
+        }
+        let tx = begin(&db).await;
+        let local = tokio::task::LocalSet::new();
+        local
+            .run_until(async move {
+                tokio::task::spawn_local(async move {
+                    tx.execute("INSERT INTO t VALUES (1)").await.unwrap();
+                    tx.commit().await.unwrap();
+                })
+                .await
+                .unwrap();
+            })
+            .await;
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+
+        // Dropping it rolls back, like a borrowed transaction
+        let tx = db.clone().transaction_owned().await.unwrap();
+        tx.execute("INSERT INTO t VALUES (2)").await.unwrap();
+        drop(tx);
+        let rs = db.execute("SELECT count(*) FROM t").await.unwrap();
+        assert_eq!(rs.rows[0].try_get::<i64>(0).unwrap(), 1);
+    }
+
+    #[tokio::test]
+    async fn commit_with_summary() {
+        let db = Client::in_memory().unwrap();
+        db.execute("CREATE TABLE t(id INTEGER PRIMARY KEY, x)")
+            .await
+            .unwrap();
+
+        let tx = db.transaction().await.unwrap();
+        assert_eq!(tx.summary(), crate::TxSummary::default());
+        tx.execute("INSERT INTO t(x) VALUES (1)").await.unwrap();
+        tx.execute("INSERT INTO t(x) VALUES (2), (3)")
+            .await
+            .unwrap();
+        tx.execute("UPDATE t SET x = x + 1 WHERE x > 1")
+            .await
+            .unwrap();
+        tx.execute("SELECT * FROM t").await.unwrap();
+        let summary = tx.commit_with_summary().await.unwrap();
+        assert_eq!(summary.total_affected, 5);
+        assert_eq!(summary.last_insert_rowid, Some(3));
+
+        let db = SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(x)").unwrap();
+        let tx = db.transaction().unwrap();
+        tx.execute("INSERT INTO t VALUES (1), (2)").unwrap();
+        assert_eq!(tx.commit_with_summary().unwrap().total_affected, 2);
+    }
+}
+
+// Without the local and wasm backends the client is Sync and its futures are Send,
+// so owned transactions can move across threads
+#[cfg(not(any(
+    feature = "local_backend",
+    feature = "workers_backend",
+    feature = "spin_backend",
+    feature = "fetch_backend"
+)))]
+#[allow(unused)]
+fn owned_transaction_is_send(db: Arc<Client>) {
+    fn assert_send<T: Send>(_: T) {}
+    assert_send(async move {
+        let tx = db.transaction_owned().await?;
+        tx.execute("SELECT 1").await?;
+        tx.commit().await
+    });
 }
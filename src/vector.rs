@@ -0,0 +1,183 @@
+//! Helpers for libSQL vector columns, enabled with the `vector` feature.
+//!
+//! libSQL stores a `F32_BLOB(N)` vector, as produced by `vector32('[1, 2, 3]')`, as a blob of
+//! `N` 32-bit IEEE 754 floats in little-endian order, 4 bytes each, with nothing in between
+//! and no header or trailer. E.g. `[1.0, -2.0]` is stored as `00 00 80 3f 00 00 00 c0`.
+//! Other vector types (like `F64_BLOB`) append a byte identifying the type, so their
+//! length is not a multiple of 4 and they are rejected when read as [Vector].
+//!
+//! # Examples
+//!
+//! ```no_run
+//! # fn f() -> anyhow::Result<()> {
+//! use libsql_client::{args, vector, Statement};
+//!
+//! let db = libsql_client::SyncClient::from_env()?;
+//! db.execute("CREATE TABLE docs (id INTEGER PRIMARY KEY, embedding F32_BLOB(3))")?;
+//! db.execute(Statement::with_args(
+//!     "INSERT INTO docs (embedding) VALUES (?)",
+//!     args!(vector::from_f32_vector(&[0.1, 0.2, 0.3])),
+//! ))?;
+//!
+//! let rs = db.execute(vector::knn("docs", "embedding", &[0.1, 0.2, 0.25], 10))?;
+//! for row in &rs.rows {
+//!     let embedding: vector::Vector = row.try_column("embedding")?;
+//!     let distance: f64 = row.try_column("distance")?;
+//!     println!("{:?} at distance {distance}", embedding.as_slice());
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::utils::{quote_ident, quote_table};
+use crate::{Statement, Value};
+
+/// Size of a single element of an `F32_BLOB` vector in bytes
+const F32_SIZE: usize = std::mem::size_of::<f32>();
+
+/// A vector of 32-bit floats, stored by libSQL in `F32_BLOB` columns.
+///
+/// It converts to a [Value] with the byte layout described in the [module docs](self),
+/// and can be read back from one with [`Row::try_get()`](crate::Row::try_get).
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Vector(pub Vec<f32>);
+
+impl Vector {
+    /// Returns the elements of the vector
+    pub fn as_slice(&self) -> &[f32] {
+        &self.0
+    }
+
+    /// Consumes the vector, returning its elements
+    pub fn into_inner(self) -> Vec<f32> {
+        self.0
+    }
+}
+
+impl From<Vec<f32>> for Vector {
+    fn from(elements: Vec<f32>) -> Self {
+        Self(elements)
+    }
+}
+
+impl From<&[f32]> for Vector {
+    fn from(elements: &[f32]) -> Self {
+        Self(elements.to_vec())
+    }
+}
+
+impl From<Vector> for Value {
+    fn from(vector: Vector) -> Self {
+        from_f32_vector(&vector.0)
+    }
+}
+
+impl TryFrom<&Value> for Vector {
+    type Error = String;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value {
+            Value::Blob { value } if value.len() % F32_SIZE == 0 => Ok(Self(
+                value
+                    .chunks_exact(F32_SIZE)
+                    .map(|bytes| f32::from_le_bytes(bytes.try_into().unwrap()))
+                    .collect(),
+            )),
+            Value::Blob { value } => Err(format!(
+                "Blob of {} bytes is not a F32_BLOB vector",
+                value.len()
+            )),
+            _ => Err(format!("Cannot convert {value} to a vector")),
+        }
+    }
+}
+
+impl TryFrom<Value> for Vector {
+    type Error = String;
+
+    fn try_from(value: Value) -> Result<Self, Self::Error> {
+        Self::try_from(&value)
+    }
+}
+
+/// Encodes the elements as an `F32_BLOB` vector value, ready to be bound as a statement argument
+pub fn from_f32_vector(elements: &[f32]) -> Value {
+    let value = elements.iter().flat_map(|e| e.to_le_bytes()).collect();
+    Value::Blob { value }
+}
+
+/// Decodes an `F32_BLOB` vector value, the inverse of [from_f32_vector]
+pub fn to_f32_vector(value: &Value) -> anyhow::Result<Vec<f32>> {
+    Vector::try_from(value)
+        .map(Vector::into_inner)
+        .map_err(|e| anyhow::anyhow!(e))
+}
+
+/// Builds a statement selecting the `k` rows of `table` nearest to `query` by the cosine
+/// distance of their `column` vectors, closest first. The table may be qualified with
+/// a schema name, e.g. `main.docs`.
+///
+/// The rows contain all columns of the table and an extra `distance` column.
+/// It scans the whole table, which is fine for small ones - large tables should rather
+/// query a vector index with `vector_top_k()`.
+pub fn knn(table: &str, column: &str, query: &[f32], k: usize) -> Statement {
+    Statement::with_args(
+        format!(
+            "SELECT *, vector_distance_cos({}, vector32(?)) AS distance FROM {} ORDER BY distance LIMIT {k}",
+            quote_ident(column),
+            quote_table(table),
+        ),
+        &[from_f32_vector(query)],
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn byte_layout() {
+        let value = from_f32_vector(&[1.0, -2.0]);
+        assert!(matches!(
+            &value,
+            Value::Blob { value } if value == &[0x00, 0x00, 0x80, 0x3f, 0x00, 0x00, 0x00, 0xc0]
+        ));
+        assert_eq!(to_f32_vector(&value).unwrap(), [1.0, -2.0]);
+        assert!(to_f32_vector(&from_f32_vector(&[])).unwrap().is_empty());
+    }
+
+    #[test]
+    fn rejects_other_values() {
+        let f64_blob = Value::Blob { value: vec![0; 9] };
+        assert!(to_f32_vector(&f64_blob).is_err());
+        assert!(to_f32_vector(&Value::Integer { value: 1 }).is_err());
+        assert!(Vector::try_from(Value::Null).is_err());
+    }
+
+    #[test]
+    fn knn_statement() {
+        let stmt = knn("my \"docs\"", "embedding", &[1.0], 5);
+        assert_eq!(
+            stmt.sql,
+            "SELECT *, vector_distance_cos(\"embedding\", vector32(?)) AS distance \
+             FROM \"my \"\"docs\"\"\" ORDER BY distance LIMIT 5"
+        );
+        assert_eq!(stmt.args.len(), 1);
+        assert_eq!(to_f32_vector(&stmt.args[0]).unwrap(), [1.0]);
+    }
+
+    #[cfg(feature = "local_backend")]
+    #[test]
+    fn roundtrip_through_blob_column() {
+        let db = crate::SyncClient::in_memory().unwrap();
+        db.execute("CREATE TABLE t(v BLOB)").unwrap();
+        db.execute(Statement::with_args(
+            "INSERT INTO t VALUES (?)",
+            &[Vector(vec![0.5, 1.5, -3.0])],
+        ))
+        .unwrap();
+        let rs = db.execute("SELECT v FROM t").unwrap();
+        let vector: Vector = rs.rows[0].try_get(0).unwrap();
+        assert_eq!(vector.as_slice(), [0.5, 1.5, -3.0]);
+    }
+}
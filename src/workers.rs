@@ -17,8 +17,31 @@ impl HttpClient {
         auth: String,
         body: String,
     ) -> Result<pipeline::ServerMsg> {
+        let response = self.send_raw(url, auth, body).await?;
+        Ok(serde_json::from_str(&response)?)
+    }
+
+    /// Sends the request and returns the raw response body
+    pub async fn send_raw(&self, url: String, auth: String, body: String) -> Result<String> {
+        self.send_raw_with_request_id(url, auth, body, None).await
+    }
+
+    /// Like [`HttpClient::send_raw()`], tagging the request with
+    /// an [`X-Idempotency-Key`](crate::http::REQUEST_ID_HEADER) header if `request_id` is set
+    pub async fn send_raw_with_request_id(
+        &self,
+        url: String,
+        auth: String,
+        body: String,
+        request_id: Option<&str>,
+    ) -> Result<String> {
         let mut headers = Headers::new();
         headers.append("Authorization", &auth).ok();
+        if let Some(request_id) = request_id {
+            headers
+                .append(crate::http::REQUEST_ID_HEADER, request_id)
+                .ok();
+        }
 
         let request_init = RequestInit {
             body: Some(wasm_bindgen::JsValue::from_str(&body)),
@@ -37,9 +60,7 @@ impl HttpClient {
             anyhow::bail!("Status {}", response.status_code());
         }
 
-        let resp: String = response.text().await.map_err(|e| anyhow::anyhow!("{e}"))?;
-        let response: pipeline::ServerMsg = serde_json::from_str(&resp)?;
-        Ok(response)
+        response.text().await.map_err(|e| anyhow::anyhow!("{e}"))
     }
 }
 